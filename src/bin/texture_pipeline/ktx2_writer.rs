@@ -0,0 +1,212 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::RgbaImage;
+use std::io::Write;
+use std::path::Path;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+const SUPERCOMPRESSION_ZLIB: u32 = 3;
+
+/// Identifier, header and index, after which the level index starts.
+const LEVEL_INDEX_OFFSET: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+/// Basic data format descriptor block with four 8 bit samples.
+const DFD_BLOCK_SIZE: u32 = 24 + 4 * 16;
+
+/// Transfer function (OETF) the texels are encoded with.
+#[derive(Clone, Copy)]
+pub enum TransferFunction {
+    Linear = 1,
+    Srgb = 2,
+}
+
+/// Writes an RGBA8 KTX2 texture with the given mip levels (largest first),
+/// every level supercompressed with zlib.
+pub fn write_ktx2(
+    path: &Path,
+    levels: &[RgbaImage],
+    transfer: TransferFunction,
+) -> std::io::Result<()> {
+    std::fs::write(path, encode_ktx2(levels, transfer)?)
+}
+
+/// The content of a KTX2 file, see `write_ktx2`.
+fn encode_ktx2(levels: &[RgbaImage], transfer: TransferFunction) -> std::io::Result<Vec<u8>> {
+    let Some(first) = levels.first() else {
+        return Err(std::io::Error::other("no mip levels to write"));
+    };
+    let vk_format = match transfer {
+        TransferFunction::Linear => VK_FORMAT_R8G8B8A8_UNORM,
+        TransferFunction::Srgb => VK_FORMAT_R8G8B8A8_SRGB,
+    };
+
+    let compressed = levels
+        .iter()
+        .map(|level| {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(level.as_raw())?;
+            encoder.finish()
+        })
+        .collect::<std::io::Result<Vec<Vec<u8>>>>()?;
+
+    let dfd = data_format_descriptor(transfer);
+    let dfd_offset = LEVEL_INDEX_OFFSET + levels.len() * LEVEL_INDEX_ENTRY_SIZE;
+    let data_offset = dfd_offset + dfd.len();
+
+    let mut file = Vec::new();
+    file.extend_from_slice(&IDENTIFIER);
+    for value in [
+        vk_format,
+        1, // typeSize
+        first.width(),
+        first.height(),
+        0, // pixelDepth
+        0, // layerCount
+        1, // faceCount
+        levels.len() as u32,
+        SUPERCOMPRESSION_ZLIB,
+    ] {
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+    file.extend_from_slice(&(dfd_offset as u32).to_le_bytes());
+    file.extend_from_slice(&(dfd.len() as u32).to_le_bytes());
+    file.extend_from_slice(&0_u32.to_le_bytes()); // kvdByteOffset
+    file.extend_from_slice(&0_u32.to_le_bytes()); // kvdByteLength
+    file.extend_from_slice(&0_u64.to_le_bytes()); // sgdByteOffset
+    file.extend_from_slice(&0_u64.to_le_bytes()); // sgdByteLength
+
+    // The smallest level is stored first, the index lists the largest first
+    let mut offsets = vec![0; levels.len()];
+    let mut offset = data_offset;
+    for (index, data) in compressed.iter().enumerate().rev() {
+        offsets[index] = offset;
+        offset += data.len();
+    }
+    for ((level, data), offset) in levels.iter().zip(&compressed).zip(&offsets) {
+        file.extend_from_slice(&(*offset as u64).to_le_bytes());
+        file.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        file.extend_from_slice(&(level.as_raw().len() as u64).to_le_bytes());
+    }
+
+    file.extend_from_slice(&dfd);
+    for data in compressed.iter().rev() {
+        file.extend_from_slice(data);
+    }
+    Ok(file)
+}
+
+/// The data format descriptor of an RGBA8 texture, prefixed with its total size.
+fn data_format_descriptor(transfer: TransferFunction) -> Vec<u8> {
+    const COLOR_MODEL_RGBSDA: u32 = 1;
+    const COLOR_PRIMARIES_BT709: u32 = 1;
+    const CHANNEL_ALPHA: u32 = 15;
+    const QUALIFIER_LINEAR: u32 = 1 << 4;
+
+    let mut words = vec![
+        4 + DFD_BLOCK_SIZE,
+        0,                          // vendorId and descriptorType
+        2 | (DFD_BLOCK_SIZE << 16), // versionNumber and descriptorBlockSize
+        COLOR_MODEL_RGBSDA | (COLOR_PRIMARIES_BT709 << 8) | ((transfer as u32) << 16),
+        0, // texelBlockDimension 1x1x1x1
+        0, // bytesPlane0..3, zero because the levels are supercompressed
+        0, // bytesPlane4..7
+    ];
+    for (index, channel) in [0, 1, 2, CHANNEL_ALPHA].into_iter().enumerate() {
+        // Alpha stays linear in sRGB textures
+        let qualifiers = match transfer {
+            TransferFunction::Srgb if channel == CHANNEL_ALPHA => QUALIFIER_LINEAR,
+            _ => 0,
+        };
+        words.extend([
+            (index as u32 * 8) | (7 << 16) | ((channel | qualifiers) << 24),
+            0,   // samplePosition
+            0,   // sampleLower
+            255, // sampleUpper
+        ]);
+    }
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    fn word(file: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(file[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn long(file: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(file[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn levels() -> Vec<RgbaImage> {
+        [4, 2, 1]
+            .into_iter()
+            .map(|size| {
+                RgbaImage::from_fn(size, size, |x, y| image::Rgba([x as u8, y as u8, 7, 255]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn header_describes_the_texture() {
+        let file = encode_ktx2(&levels(), TransferFunction::Srgb).unwrap();
+        assert_eq!(file[..12], IDENTIFIER);
+        assert_eq!(word(&file, 12), VK_FORMAT_R8G8B8A8_SRGB);
+        assert_eq!(word(&file, 20), 4); // pixelWidth
+        assert_eq!(word(&file, 24), 4); // pixelHeight
+        assert_eq!(word(&file, 40), 3); // levelCount
+        assert_eq!(word(&file, 44), SUPERCOMPRESSION_ZLIB);
+
+        let linear = encode_ktx2(&levels(), TransferFunction::Linear).unwrap();
+        assert_eq!(word(&linear, 12), VK_FORMAT_R8G8B8A8_UNORM);
+    }
+
+    #[test]
+    fn level_index_points_at_the_levels() {
+        let levels = levels();
+        let file = encode_ktx2(&levels, TransferFunction::Linear).unwrap();
+        let dfd_offset = word(&file, 48) as usize;
+        let dfd_length = word(&file, 52) as usize;
+        assert_eq!(
+            dfd_offset,
+            LEVEL_INDEX_OFFSET + levels.len() * LEVEL_INDEX_ENTRY_SIZE
+        );
+
+        let mut previous_offset = file.len();
+        for (index, level) in levels.iter().enumerate() {
+            let entry = LEVEL_INDEX_OFFSET + index * LEVEL_INDEX_ENTRY_SIZE;
+            let offset = long(&file, entry) as usize;
+            let length = long(&file, entry + 8) as usize;
+            assert_eq!(long(&file, entry + 16), level.as_raw().len() as u64);
+            // The smallest level is stored first, right after the descriptor
+            assert!(offset >= dfd_offset + dfd_length);
+            assert_eq!(offset + length, previous_offset);
+            previous_offset = offset;
+
+            let mut texels = Vec::new();
+            ZlibDecoder::new(&file[offset..offset + length])
+                .read_to_end(&mut texels)
+                .unwrap();
+            assert_eq!(&texels, level.as_raw());
+        }
+        assert_eq!(previous_offset, dfd_offset + dfd_length);
+    }
+
+    #[test]
+    fn data_format_descriptor_has_the_transfer_function() {
+        for (transfer, expected) in [(TransferFunction::Linear, 1), (TransferFunction::Srgb, 2)] {
+            let file = encode_ktx2(&levels(), transfer).unwrap();
+            let dfd_offset = word(&file, 48) as usize;
+            assert_eq!(word(&file, 52), word(&file, dfd_offset));
+            assert_eq!((word(&file, dfd_offset + 12) >> 16) & 0xFF, expected);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Prepares a downloaded PBR texture set for the material library.
+//!
+//! Usage: `cargo run --release --bin texture_pipeline -- <input dir> [output dir]`
+//!
+//! The input directory holds PNG maps named like the ones from texturecan.com:
+//! `base_color.png` (or `color.png`), `normal_opengl.png`, `ao.png`, `metallic.png`, `roughness.png`
+//! or an already packed `metallic_roughness.png`. All of them are optional.
+//! The output directory (`<input dir>-ktx2` by default) gets mipmapped, zlib supercompressed KTX2 textures
+//! and the `pbr.material.ron` descriptor, ready to be copied to `assets/materials/`.
+
+mod ktx2_writer;
+mod mipmaps;
+mod texture_set;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use texture_set::TextureSet;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args_os().skip(1);
+    let Some(input_dir) = args.next().map(PathBuf::from) else {
+        eprintln!("Usage: texture_pipeline /path/to/dir/with/textures [/path/to/output/dir]");
+        return ExitCode::FAILURE;
+    };
+    let output_dir = args.next().map(PathBuf::from).unwrap_or_else(|| {
+        let mut name = input_dir.file_name().unwrap_or_default().to_os_string();
+        name.push("-ktx2");
+        input_dir.with_file_name(name)
+    });
+
+    if !input_dir.is_dir() {
+        eprintln!("Directory {} does not exist.", input_dir.display());
+        return ExitCode::FAILURE;
+    }
+    println!("input dir = {}", input_dir.display());
+    println!("output dir = {}", output_dir.display());
+
+    let result = TextureSet::read(&input_dir).and_then(|set| set.write(&output_dir));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use image::{Rgba, RgbaImage};
+
+/// How the texels of a map are averaged when it is downsampled.
+#[derive(Clone, Copy)]
+pub enum MapKind {
+    /// sRGB encoded color, averaged in linear space.
+    Color,
+    /// Tangent space normals, renormalized after averaging.
+    Normal,
+    /// Linear data like occlusion, roughness and metallic.
+    Data,
+}
+
+/// The full mip chain down to 1x1, starting with `image` itself.
+pub fn generate_mipmaps(image: RgbaImage, kind: MapKind) -> Vec<RgbaImage> {
+    let mut levels = vec![image];
+    loop {
+        let last = levels.last().expect("the chain starts with the image");
+        if last.width() == 1 && last.height() == 1 {
+            return levels;
+        }
+        let next = downsample(last, kind);
+        levels.push(next);
+    }
+}
+
+/// Halves the image with a box filter. Odd edges are clamped.
+fn downsample(image: &RgbaImage, kind: MapKind) -> RgbaImage {
+    let width = (image.width() / 2).max(1);
+    let height = (image.height() / 2).max(1);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let mut sum = [0.0_f32; 4];
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let source_x = (x * 2 + dx).min(image.width() - 1);
+            let source_y = (y * 2 + dy).min(image.height() - 1);
+            let texel = decode(image.get_pixel(source_x, source_y), kind);
+            for (sum, value) in sum.iter_mut().zip(texel) {
+                *sum += value / 4.0;
+            }
+        }
+        encode(sum, kind)
+    })
+}
+
+fn decode(texel: &Rgba<u8>, kind: MapKind) -> [f32; 4] {
+    let unorm = texel.0.map(|value| value as f32 / 255.0);
+    match kind {
+        MapKind::Color => [
+            srgb_to_linear(unorm[0]),
+            srgb_to_linear(unorm[1]),
+            srgb_to_linear(unorm[2]),
+            unorm[3],
+        ],
+        MapKind::Normal => [
+            unorm[0] * 2.0 - 1.0,
+            unorm[1] * 2.0 - 1.0,
+            unorm[2] * 2.0 - 1.0,
+            unorm[3],
+        ],
+        MapKind::Data => unorm,
+    }
+}
+
+fn encode(value: [f32; 4], kind: MapKind) -> Rgba<u8> {
+    let unorm = match kind {
+        MapKind::Color => [
+            linear_to_srgb(value[0]),
+            linear_to_srgb(value[1]),
+            linear_to_srgb(value[2]),
+            value[3],
+        ],
+        MapKind::Normal => {
+            let length = (value[0] * value[0] + value[1] * value[1] + value[2] * value[2]).sqrt();
+            let [x, y, z] = if length > f32::EPSILON {
+                [value[0] / length, value[1] / length, value[2] / length]
+            } else {
+                [0.0, 0.0, 1.0]
+            };
+            [x * 0.5 + 0.5, y * 0.5 + 0.5, z * 0.5 + 0.5, value[3]]
+        }
+        MapKind::Data => value,
+    };
+    Rgba(unorm.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain_goes_down_to_one_texel() {
+        let levels = generate_mipmaps(RgbaImage::new(8, 3), MapKind::Data);
+        let sizes: Vec<_> = levels.iter().map(RgbaImage::dimensions).collect();
+        assert_eq!(sizes, [(8, 3), (4, 1), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn color_is_averaged_in_linear_space() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| {
+            let value = if x == 0 { 0 } else { 255 };
+            Rgba([value, value, value, 255])
+        });
+        let levels = generate_mipmaps(image.clone(), MapKind::Color);
+        // Half of the light is about 188 in sRGB, not the 128 of averaging the encoded values
+        assert_eq!(levels[1].get_pixel(0, 0).0, [188, 188, 188, 255]);
+
+        let levels = generate_mipmaps(image, MapKind::Data);
+        assert_eq!(levels[1].get_pixel(0, 0).0, [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn normals_are_renormalized() {
+        // Pointing along +X and +Y, their plain average is shorter than a unit vector
+        let image = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([255, 128, 128, 255])
+            } else {
+                Rgba([128, 255, 128, 255])
+            }
+        });
+        let levels = generate_mipmaps(image, MapKind::Normal);
+        let [x, y, z, _] = decode(levels[1].get_pixel(0, 0), MapKind::Normal);
+        let length = (x * x + y * y + z * z).sqrt();
+        assert!((length - 1.0).abs() < 0.02, "length {length}");
+        assert!((x - y).abs() < 0.01);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::ktx2_writer::{write_ktx2, TransferFunction};
+use crate::mipmaps::{generate_mipmaps, MapKind};
+use image::imageops::FilterType;
+use image::{GrayImage, ImageError, RgbaImage};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const BASE_COLOR_FILE: &str = "base_color.ktx2";
+pub const NORMAL_FILE: &str = "normal_opengl.ktx2";
+/// Occlusion in red, roughness in green and metallic in blue,
+/// so one texture serves as both occlusion and metallic/roughness map.
+pub const ORM_FILE: &str = "orm.ktx2";
+pub const DESCRIPTOR_FILE: &str = "pbr.material.ron";
+
+#[derive(Debug)]
+pub enum PipelineError {
+    Io(PathBuf, std::io::Error),
+    Image(PathBuf, ImageError),
+    Empty,
+}
+
+impl Display for PipelineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            PipelineError::Image(path, err) => write!(f, "{}: {err}", path.display()),
+            PipelineError::Empty => write!(f, "No known texture maps found"),
+        }
+    }
+}
+
+/// The maps of one material as found in the input directory.
+pub struct TextureSet {
+    base_color: Option<RgbaImage>,
+    normal: Option<RgbaImage>,
+    occlusion: Option<GrayImage>,
+    roughness: Option<GrayImage>,
+    metallic: Option<GrayImage>,
+}
+
+impl TextureSet {
+    pub fn read(dir: &Path) -> Result<Self, PipelineError> {
+        let open = |names: &[&str]| -> Result<Option<image::DynamicImage>, PipelineError> {
+            let Some(path) = names
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file())
+            else {
+                return Ok(None);
+            };
+            println!("reading {}", path.display());
+            image::open(&path)
+                .map(Some)
+                .map_err(|err| PipelineError::Image(path, err))
+        };
+
+        let mut set = Self {
+            base_color: open(&["base_color.png", "color.png"])?.map(|image| image.to_rgba8()),
+            normal: open(&["normal_opengl.png"])?.map(|image| image.to_rgba8()),
+            occlusion: open(&["ao.png"])?.map(|image| image.to_luma8()),
+            roughness: open(&["roughness.png"])?.map(|image| image.to_luma8()),
+            metallic: open(&["metallic.png"])?.map(|image| image.to_luma8()),
+        };
+        // An already packed map: roughness in green, metallic in blue
+        if set.roughness.is_none() || set.metallic.is_none() {
+            if let Some(packed) = open(&["metallic_roughness.png"])? {
+                let packed = packed.to_rgba8();
+                let channel = |index: usize| {
+                    GrayImage::from_fn(packed.width(), packed.height(), |x, y| {
+                        image::Luma([packed.get_pixel(x, y)[index]])
+                    })
+                };
+                set.roughness.get_or_insert_with(|| channel(1));
+                set.metallic.get_or_insert_with(|| channel(2));
+            }
+        }
+
+        if set.base_color.is_none()
+            && set.normal.is_none()
+            && set.occlusion.is_none()
+            && set.roughness.is_none()
+            && set.metallic.is_none()
+        {
+            return Err(PipelineError::Empty);
+        }
+        Ok(set)
+    }
+
+    pub fn write(self, dir: &Path) -> Result<(), PipelineError> {
+        fs::create_dir_all(dir).map_err(|err| PipelineError::Io(dir.to_path_buf(), err))?;
+        let write = |file: &str, image: RgbaImage, kind: MapKind| {
+            let path = dir.join(file);
+            println!("writing {}", path.display());
+            let transfer = match kind {
+                MapKind::Color => TransferFunction::Srgb,
+                MapKind::Normal | MapKind::Data => TransferFunction::Linear,
+            };
+            write_ktx2(&path, &generate_mipmaps(image, kind), transfer)
+                .map_err(|err| PipelineError::Io(path, err))
+        };
+
+        let has_base_color = self.base_color.is_some();
+        let has_normal = self.normal.is_some();
+        let has_occlusion = self.occlusion.is_some();
+        let has_roughness = self.roughness.is_some();
+        let has_metallic = self.metallic.is_some();
+        let orm = self.pack_orm();
+
+        if let Some(base_color) = self.base_color {
+            write(BASE_COLOR_FILE, base_color, MapKind::Color)?;
+        }
+        if let Some(normal) = self.normal {
+            write(NORMAL_FILE, normal, MapKind::Normal)?;
+        }
+        if let Some(orm) = orm {
+            write(ORM_FILE, orm, MapKind::Data)?;
+        }
+
+        let texture = |present: bool, file: &str| {
+            if present {
+                format!("Some(\"{file}\")")
+            } else {
+                "None".to_string()
+            }
+        };
+        let descriptor = format!(
+            "(\n    base_color_texture: {},\n    normal_map_texture: {},\n    occlusion_texture: {},\n    metallic_roughness_texture: {},\n    metallic: {},\n    perceptual_roughness: 1.0,\n)\n",
+            texture(has_base_color, BASE_COLOR_FILE),
+            texture(has_normal, NORMAL_FILE),
+            texture(has_occlusion, ORM_FILE),
+            texture(has_roughness || has_metallic, ORM_FILE),
+            if has_metallic { "1.0" } else { "0.0" },
+        );
+        let path = dir.join(DESCRIPTOR_FILE);
+        println!("writing {}", path.display());
+        fs::write(&path, descriptor).map_err(|err| PipelineError::Io(path, err))
+    }
+
+    /// Packs occlusion, roughness and metallic into one texture, `None` if there is none of them.
+    /// Smaller maps are scaled up to the largest one.
+    /// Missing channels are filled with white occlusion, full roughness and no metal.
+    fn pack_orm(&self) -> Option<RgbaImage> {
+        let maps = [&self.occlusion, &self.roughness, &self.metallic];
+        let (width, height) = maps
+            .iter()
+            .filter_map(|map| map.as_ref().map(|map| map.dimensions()))
+            .max_by_key(|(width, height)| width * height)?;
+        let fit = |map: &Option<GrayImage>| {
+            map.as_ref().map(|map| {
+                if map.dimensions() == (width, height) {
+                    map.clone()
+                } else {
+                    image::imageops::resize(map, width, height, FilterType::Lanczos3)
+                }
+            })
+        };
+        let [occlusion, roughness, metallic] = maps.map(fit);
+
+        Some(RgbaImage::from_fn(width, height, |x, y| {
+            let value = |map: &Option<GrayImage>, default: u8| {
+                map.as_ref().map_or(default, |map| map.get_pixel(x, y)[0])
+            };
+            image::Rgba([
+                value(&occlusion, u8::MAX),
+                value(&roughness, u8::MAX),
+                value(&metallic, 0),
+                u8::MAX,
+            ])
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: u8) -> Option<GrayImage> {
+        Some(GrayImage::from_pixel(2, 2, image::Luma([value])))
+    }
+
+    fn set(
+        occlusion: Option<GrayImage>,
+        roughness: Option<GrayImage>,
+        metallic: Option<GrayImage>,
+    ) -> TextureSet {
+        TextureSet {
+            base_color: None,
+            normal: None,
+            occlusion,
+            roughness,
+            metallic,
+        }
+    }
+
+    #[test]
+    fn orm_channels_are_occlusion_roughness_metallic() {
+        let orm = set(gray(10), gray(20), gray(30)).pack_orm().unwrap();
+        assert_eq!(orm.get_pixel(1, 1).0, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn missing_orm_channels_get_defaults() {
+        let orm = set(None, gray(20), None).pack_orm().unwrap();
+        assert_eq!(orm.get_pixel(0, 0).0, [255, 20, 0, 255]);
+        let orm = set(gray(10), None, gray(30)).pack_orm().unwrap();
+        assert_eq!(orm.get_pixel(0, 0).0, [10, 255, 30, 255]);
+        assert!(set(None, None, None).pack_orm().is_none());
+    }
+
+    #[test]
+    fn smaller_maps_are_scaled_to_the_largest() {
+        let large = Some(GrayImage::from_pixel(4, 4, image::Luma([10])));
+        let orm = set(large, gray(20), None).pack_orm().unwrap();
+        assert_eq!(orm.dimensions(), (4, 4));
+        assert_eq!(orm.get_pixel(3, 3).0, [10, 20, 0, 255]);
+    }
+}
@@ -0,0 +1,172 @@
+use super::building_assets::{BuildingAssets, PreviewPiece};
+use super::selection::SelectedBuildings;
+use crate::file_names::is_valid_file_stem;
+use crate::world_save::PlacedBuildingSave;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+
+/// Directory (relative to the working directory) with one file per blueprint, named after it.
+pub const BLUEPRINTS_DIR: &str = "blueprints";
+
+/// A structure saved to be built again, e.g. a house.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Blueprint {
+    /// The pieces, positioned relative to the anchor the blueprint is placed with.
+    pub pieces: Vec<PlacedBuildingSave>,
+}
+
+impl Blueprint {
+    /// The pieces as a group preview. Pieces whose building doesn't exist (anymore) are left out.
+    pub fn preview_pieces(&self, building_assets: &BuildingAssets) -> Vec<PreviewPiece> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| {
+                let Some(pack) = building_assets.get(&piece.name) else {
+                    warn!(
+                        "Blueprint piece \"{}\" not found in the building assets",
+                        piece.name
+                    );
+                    return None;
+                };
+                Some(PreviewPiece {
+                    name: piece.name.clone(),
+                    scene: pack.scene.clone(),
+                    material: piece.material.clone(),
+                    tier: piece.tier,
+                    transform: piece.transform,
+                    footprint: pack.footprint,
+                    open: piece.open,
+                })
+            })
+            .collect()
+    }
+}
+
+/// All blueprints by name, read from the blueprints directory.
+#[derive(Resource, Default)]
+pub struct Blueprints(pub Vec<(String, Blueprint)>);
+
+impl Blueprints {
+    /// Reads all blueprints, sorted by name. Unreadable files are skipped.
+    pub fn read_all() -> Self {
+        let Ok(entries) = fs::read_dir(BLUEPRINTS_DIR) else {
+            return Self::default();
+        };
+        let mut blueprints: Vec<(String, Blueprint)> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_string_lossy().into_owned();
+                match read_blueprint(&name) {
+                    Ok(blueprint) => Some((name, blueprint)),
+                    Err(err) => {
+                        warn!("Skipping blueprint \"{name}\": {err}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        blueprints.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self(blueprints)
+    }
+}
+
+#[derive(Debug)]
+pub enum BlueprintError {
+    InvalidName,
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl Display for BlueprintError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlueprintError::InvalidName => write!(f, "invalid blueprint name"),
+            BlueprintError::Io(err) => write!(f, "I/O error: {err}"),
+            BlueprintError::Parse(err) => write!(f, "parse error: {err}"),
+            BlueprintError::Serialize(err) => write!(f, "serialize error: {err}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for BlueprintError {
+    fn from(err: std::io::Error) -> Self {
+        BlueprintError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for BlueprintError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        BlueprintError::Parse(err)
+    }
+}
+
+impl From<ron::Error> for BlueprintError {
+    fn from(err: ron::Error) -> Self {
+        BlueprintError::Serialize(err)
+    }
+}
+
+fn blueprint_file(name: &str) -> PathBuf {
+    PathBuf::from(BLUEPRINTS_DIR).join(format!("{name}.ron"))
+}
+
+fn read_blueprint(name: &str) -> Result<Blueprint, BlueprintError> {
+    let text = fs::read_to_string(blueprint_file(name))?;
+    Ok(ron::from_str(&text)?)
+}
+
+/// Writes the blueprint, overwriting the one with the same name.
+fn write_blueprint(name: &str, blueprint: &Blueprint) -> Result<(), BlueprintError> {
+    if !is_valid_file_stem(name) {
+        return Err(BlueprintError::InvalidName);
+    }
+    fs::create_dir_all(BLUEPRINTS_DIR)?;
+    let text = ron::ser::to_string_pretty(blueprint, ron::ser::PrettyConfig::default())?;
+    fs::write(blueprint_file(name), text)?;
+    Ok(())
+}
+
+/// Saves the selected buildings as a blueprint with the given name.
+#[derive(Event)]
+pub struct SaveBlueprintEvent(pub String);
+
+pub fn save_selection_blueprint(
+    mut events: EventReader<SaveBlueprintEvent>,
+    selected: SelectedBuildings,
+    mut blueprints: ResMut<Blueprints>,
+) {
+    for SaveBlueprintEvent(name) in events.read() {
+        let (_, pieces) = selected.pieces();
+        if pieces.is_empty() {
+            continue;
+        }
+        let blueprint = Blueprint {
+            pieces: pieces
+                .into_iter()
+                .map(|piece| PlacedBuildingSave {
+                    name: piece.name,
+                    transform: piece.transform,
+                    material: piece.material,
+                    tier: piece.tier,
+                    open: piece.open,
+                })
+                .collect(),
+        };
+        match write_blueprint(name, &blueprint) {
+            Ok(()) => {
+                info!(
+                    "Saved blueprint \"{name}\" with {} pieces",
+                    blueprint.pieces.len()
+                );
+                *blueprints = Blueprints::read_all();
+            }
+            Err(err) => error!("Failed to save blueprint \"{name}\": {err}"),
+        }
+    }
+}
@@ -1,10 +1,21 @@
 use super::building_assets::PreviewBuildingHandle;
-use super::{BuildingSettings, PreviewBuilding, RoundToStep};
+use super::building_cost::BuildingEconomy;
+use super::building_lod::BuildingLod;
+use super::building_tier::BuildingTier;
+use super::foundation::{terraform_foundation_pad, terraform_group_pads, FoundationLeveling};
+use super::material_variant::MaterialVariant;
+use super::opening::Openable;
+use super::placement::PlacementControls;
+use super::selection::MovingBuilding;
+use super::{BuildingSettings, PlacedBuilding, PlacementBlocked, PreviewBuilding, RoundToStep};
+use crate::terrain::{Terrain, TerrainSettings};
 use crate::universal_camera_controller::UniCamController;
+use crate::world_streaming::WorldChunks;
 use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 
 pub mod prelude {
+    pub use super::block_placement_in_unloaded_chunks;
     pub use super::building_system;
     pub use super::enter_building_mode;
     pub use super::exit_building_mode;
@@ -16,34 +27,141 @@ pub fn enter_building_mode(
     mut commands: Commands,
     preview_building_handle: Res<PreviewBuildingHandle>,
 ) {
-    if let Some(preview) = preview_building_handle.0.clone() {
-        commands.spawn((SceneRoot(preview), PreviewBuilding));
+    if !preview_building_handle.group.is_empty() {
+        // The pieces follow the group's anchor, which is moved like a single building
+        commands
+            .spawn((Transform::default(), Visibility::default(), PreviewBuilding))
+            .with_children(|parent| {
+                for piece in &preview_building_handle.group {
+                    parent.spawn((
+                        SceneRoot(piece.scene.clone()),
+                        MaterialVariant(piece.material.clone()),
+                        piece.tier,
+                        piece.transform,
+                    ));
+                }
+            });
+    } else if let Some(preview) = preview_building_handle.scene.clone() {
+        commands.spawn((
+            SceneRoot(preview),
+            MaterialVariant(preview_building_handle.material.clone()),
+            PreviewBuilding,
+        ));
     } else {
         error!("No preview_obj found in assets.preview_obj. Does it okay?");
     }
 }
 
-/// Handles the building system by placing a building when the left mouse button is pressed.
+/// The preview, without a scene of its own when it is a group of buildings.
+type PreviewQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static SceneRoot>,
+        &'static Transform,
+        Has<PlacementBlocked>,
+    ),
+    With<PreviewBuilding>,
+>;
+
+/// Handles the building system by placing a building, run when the left mouse button is pressed.
+/// Foundations placed with terraform leveling flatten the ground under them, also those of a group.
+/// Placing a moved selection removes the buildings it was taken from.
+/// In survival mode, placing pays for the buildings.
 pub fn building_system(
     mut commands: Commands,
-    preview_building: Query<(&SceneRoot, &Transform), With<PreviewBuilding>>,
-    buttons: Res<ButtonInput<MouseButton>>,
+    preview_building: PreviewQuery,
+    preview_building_handle: Res<PreviewBuildingHandle>,
+    building_settings: Res<BuildingSettings>,
+    terrain: Option<ResMut<Terrain>>,
+    moving_buildings: Query<Entity, With<MovingBuilding>>,
+    mut economy: BuildingEconomy,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
-        if let Some((root, transform)) = preview_building.iter().next() {
-            commands.spawn((root.clone(), transform.clone()));
+    let cost = economy.preview_cost(&preview_building_handle, !moving_buildings.is_empty());
+    if !preview_building_handle.group.is_empty() {
+        if let Some((_, anchor, false)) = preview_building.iter().next() {
+            if let (Some(mut terrain), FoundationLeveling::Terraform) =
+                (terrain, building_settings.foundation_leveling)
+            {
+                terraform_group_pads(&mut terrain, anchor, &preview_building_handle.group);
+            }
+            economy.pay(&cost);
+            for piece in &preview_building_handle.group {
+                spawn_placed_building(
+                    &mut commands,
+                    &piece.name,
+                    piece.scene.clone(),
+                    anchor.mul_transform(piece.transform),
+                    piece.material.clone(),
+                    piece.tier,
+                    piece.open,
+                );
+            }
+            for entity in moving_buildings.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        return;
+    }
+    if let Some((Some(root), transform, false)) = preview_building.iter().next() {
+        if let (Some(footprint), Some(mut terrain), FoundationLeveling::Terraform) = (
+            preview_building_handle.footprint,
+            terrain,
+            building_settings.foundation_leveling,
+        ) {
+            terraform_foundation_pad(&mut terrain, transform, footprint);
         }
+        economy.pay(&cost);
+        spawn_placed_building(
+            &mut commands,
+            &preview_building_handle.name,
+            root.0.clone(),
+            *transform,
+            preview_building_handle.material.clone(),
+            BuildingTier::default(),
+            false,
+        );
     }
 }
 
+/// Spawns a placed building. Used both by the building mode and when loading a world.
+/// Open doors, hatches and windows are posed open once their scene is spawned.
+pub fn spawn_placed_building(
+    commands: &mut Commands,
+    name: &str,
+    scene: Handle<Scene>,
+    transform: Transform,
+    material: Option<String>,
+    tier: BuildingTier,
+    open: bool,
+) -> Entity {
+    let mut entity = commands.spawn((
+        SceneRoot(scene),
+        transform,
+        MaterialVariant(material),
+        tier,
+        BuildingLod::default(),
+        PlacedBuilding {
+            name: name.to_string(),
+        },
+    ));
+    if open {
+        entity.insert(Openable { open });
+    }
+    entity.id()
+}
+
 /// Updates the position of the building preview relative to the camera and grid.
+/// Scrolling turns the preview by the rotation step, see `PlacementControls::rotate`.
 pub fn update_preview_building_position(
     mut params: ParamSet<(
         Single<&mut Transform, With<PreviewBuilding>>,
         Single<&Transform, With<UniCamController>>,
     )>,
     building_settings: Res<BuildingSettings>,
+    terrain: Option<Res<Terrain>>,
     mut evr_scroll: EventReader<MouseWheel>,
+    controls: PlacementControls,
 ) {
     let mut vertical_scroll = 0_f32;
     evr_scroll.read().for_each(|scroll| match scroll.unit {
@@ -56,27 +174,65 @@ pub fn update_preview_building_position(
     let cam_transform = params.p1().clone();
     let mut building_transform = params.p0();
 
-    building_transform
-        .translation
-        .round_to_step(building_settings.grid_size);
-
-    let rotation = Quat::from_rotation_y(vertical_scroll * 15_f32.to_radians());
-    building_transform.rotation *= rotation;
+    let angle = vertical_scroll * building_settings.rotation_step.to_radians();
+    building_transform.rotation = controls.rotate(building_transform.rotation, angle);
 
     let distance_in_front = 7.0;
     let camera_position = cam_transform.translation;
     let camera_forward = cam_transform.rotation * Vec3::NEG_Z;
 
     let new_cube_position = camera_position + camera_forward * distance_in_front;
-    building_transform.translation = new_cube_position.round_to_step(building_settings.grid_size);
+    let new_cube_position = if building_settings.snapping {
+        new_cube_position.round_to_step(building_settings.grid_size)
+    } else {
+        new_cube_position
+    };
+    building_transform.translation = new_cube_position + controls.nudge.0;
+
+    // Don't let the preview sink into the ground
+    let translation = &mut building_transform.translation;
+    if let Some(ground) =
+        terrain.and_then(|terrain| terrain.height_at(translation.x, translation.z))
+    {
+        translation.y = translation.y.max(ground);
+    }
+}
+
+/// Blocks placing buildings in chunks which aren't loaded yet, their saved buildings would replace them.
+/// Runs after the foundation leveling, which unblocks the preview on its own.
+pub fn block_placement_in_unloaded_chunks(
+    mut commands: Commands,
+    preview: Single<(Entity, &Transform), With<PreviewBuilding>>,
+    preview_building_handle: Res<PreviewBuildingHandle>,
+    world_chunks: Res<WorldChunks>,
+    terrain_settings: Res<TerrainSettings>,
+) {
+    let (entity, transform) = *preview;
+    // Every piece of a group has to be in a loaded chunk
+    let pieces = preview_building_handle
+        .group
+        .iter()
+        .map(|piece| transform.transform_point(piece.transform.translation));
+    let positions = std::iter::once(transform.translation).chain(pieces);
+    if !world_chunks.all_loaded(positions, terrain_settings.chunk_size) {
+        commands.entity(entity).insert(PlacementBlocked);
+    }
 }
 
 ///Destroy the preview building entity.
+/// Buildings of a moved selection that wasn't placed come back.
 pub fn exit_building_mode(
     mut commands: Commands,
     preview_building_query: Query<Entity, With<PreviewBuilding>>,
+    moving_buildings: Query<Entity, With<MovingBuilding>>,
 ) {
     preview_building_query.iter().for_each(|entity| {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     });
+    for entity in moving_buildings.iter() {
+        commands
+            .entity(entity)
+            .remove::<MovingBuilding>()
+            .insert(Visibility::Inherited);
+    }
 }
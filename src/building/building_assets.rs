@@ -1,9 +1,60 @@
+use super::building_light::{BuildingLight, BuildingLightKind};
+use super::building_tier::BuildingTier;
+use super::gltf_import::{imported_dir, is_gltf, IMPORTED_DIR};
+use super::opening::{OpeningAnimation, OpeningKind};
+use crate::inventory::{ResourceCost, ResourceKind};
 use bevy::asset::AssetPath;
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
+/// The building selected in the menu, which the preview is spawned from.
 #[derive(Resource, Default)]
-pub struct PreviewBuildingHandle(pub Option<Handle<Scene>>);
+pub struct PreviewBuildingHandle {
+    pub name: String,
+    pub scene: Option<Handle<Scene>>,
+    pub footprint: Option<Vec2>,
+    pub variants: Vec<String>,
+    /// Material variant the building is placed with, `None` for the glTF material.
+    pub material: Option<String>,
+    /// Pieces placed together instead of a single building, e.g. a selection being moved.
+    pub group: Vec<PreviewPiece>,
+}
+
+/// A building of a group preview, positioned relative to the group's anchor.
+#[derive(Clone)]
+pub struct PreviewPiece {
+    pub name: String,
+    pub scene: Handle<Scene>,
+    pub material: Option<String>,
+    pub tier: BuildingTier,
+    pub transform: Transform,
+    /// Footprint of the building if it is a foundation, leveled with the other foundations of the group.
+    pub footprint: Option<Vec2>,
+    /// Whether the door, hatch or window is open.
+    pub open: bool,
+}
+
+impl PreviewBuildingHandle {
+    /// Selects the building to place and the material variant it is placed with.
+    pub fn select(&mut self, building: &BuildingAssetsPack, material: Option<String>) {
+        self.name = building.name.clone();
+        self.scene = Some(building.scene.clone());
+        self.footprint = building.footprint;
+        self.variants = building.variants.clone();
+        self.material = material;
+        self.group.clear();
+    }
+
+    /// Selects a group of buildings to place together.
+    pub fn select_group(&mut self, name: impl Into<String>, pieces: Vec<PreviewPiece>) {
+        self.name = name.into();
+        self.scene = None;
+        self.footprint = None;
+        self.variants.clear();
+        self.material = None;
+        self.group = pieces;
+    }
+}
 
 #[derive(Resource)]
 pub struct BuildingAssets {
@@ -13,12 +64,42 @@ pub struct BuildingAssets {
     pub wall: BuildingsGroup,
     pub gable: BuildingsGroup,
     pub roof: BuildingsGroup,
+    pub light: BuildingsGroup,
+    /// Doors, hatches and windows which can be opened.
+    pub opening: BuildingsGroup,
+    /// Buildings imported from glTFs by the player.
+    pub custom: BuildingsGroup,
 }
 
 pub struct BuildingAssetsPack {
     pub name: String,
     pub scene: Handle<Scene>,
     pub _snap_points: Vec<Vec3>,
+    /// Size (x, z) of the base of a foundation, centered on its origin.
+    /// Foundations are leveled against the terrain when placed.
+    pub footprint: Option<Vec2>,
+    /// Names of `MaterialLibrary` materials the building can be placed with
+    /// instead of the material of its glTF.
+    pub variants: Vec<String>,
+    /// Light spawned with the building when it is placed.
+    pub light: Option<BuildingLight>,
+    /// Whether the glTF hierarchy of the scene has been flattened, see `flatten_building_scenes`.
+    pub flattened: bool,
+    /// Meshes the collider of the building is built from, known once its scene is loaded.
+    pub collision: Option<CollisionMeshes>,
+    /// Resources placing the building takes in survival mode.
+    pub cost: ResourceCost,
+    /// Animation opening the building, for doors and windows.
+    pub opening: Option<OpeningAnimation>,
+}
+
+/// Meshes of a building positioned relative to its origin, for building its collider.
+#[cfg_attr(not(feature = "physics"), allow(dead_code))]
+pub struct CollisionMeshes {
+    pub meshes: Vec<(Handle<Mesh>, Transform)>,
+    /// The meshes are collision meshes authored in the glTF, each of them convex,
+    /// instead of the meshes the building is drawn with.
+    pub authored: bool,
 }
 
 impl BuildingAssetsPack {
@@ -32,8 +113,98 @@ impl BuildingAssetsPack {
             name: name.into(),
             scene: bridge.asset_server.load(asset_path),
             _snap_points: snap_points,
+            footprint: None,
+            variants: Vec::new(),
+            light: None,
+            flattened: false,
+            collision: None,
+            cost: ResourceCost::default(),
+            opening: None,
         }
     }
+
+    /// A building imported to `assets/<IMPORTED_DIR>/<name>/<file_name>`.
+    pub fn imported(bridge: &mut BuildingAssetsInitBridge, name: &str, file_name: &str) -> Self {
+        Self::new(
+            bridge,
+            name,
+            GltfAssetLabel::Scene(0).from_asset(format!("{IMPORTED_DIR}/{name}/{file_name}")),
+            Vec::new(),
+        )
+    }
+
+    /// A building without a glTF, assembled from primitive shapes placed relative to its origin.
+    pub fn from_shapes(
+        bridge: &mut BuildingAssetsInitBridge,
+        name: impl Into<String>,
+        shapes: Vec<(Mesh, StandardMaterial, Transform)>,
+    ) -> Self {
+        let mut world = World::new();
+        let mut collision_meshes = Vec::new();
+        for (mesh, material, transform) in shapes {
+            let mesh = bridge.meshes.add(mesh);
+            collision_meshes.push((mesh.clone(), transform));
+            world.spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(bridge.materials.add(material)),
+                transform,
+            ));
+        }
+        Self {
+            name: name.into(),
+            scene: bridge.scenes.add(Scene::new(world)),
+            _snap_points: Vec::new(),
+            footprint: None,
+            variants: Vec::new(),
+            light: None,
+            // Shapes are spawned directly below the root already
+            flattened: true,
+            collision: Some(CollisionMeshes {
+                meshes: collision_meshes,
+                authored: false,
+            }),
+            cost: ResourceCost::default(),
+            opening: None,
+        }
+    }
+
+    pub fn with_footprint(mut self, footprint: Vec2) -> Self {
+        self.footprint = Some(footprint);
+        self
+    }
+
+    pub fn with_variants(mut self, variants: &[&str]) -> Self {
+        self.variants = variants.iter().map(|variant| variant.to_string()).collect();
+        self
+    }
+
+    pub fn with_cost(mut self, cost: &[(ResourceKind, u32)]) -> Self {
+        self.cost = ResourceCost::new(cost);
+        self
+    }
+
+    pub fn with_light(mut self, light: BuildingLight) -> Self {
+        self.light = Some(light);
+        self
+    }
+
+    /// Opens the building with an animation of its glTF, played backwards to close it.
+    pub fn with_gltf_opening(
+        mut self,
+        bridge: &mut BuildingAssetsInitBridge,
+        kind: OpeningKind,
+        animation: AssetPath,
+    ) -> Self {
+        let clip = bridge.asset_server.load(animation);
+        let (graph, node) = AnimationGraph::from_clip(clip.clone());
+        self.opening = Some(OpeningAnimation {
+            kind,
+            clip,
+            graph: bridge.animation_graphs.add(graph),
+            node,
+        });
+        self
+    }
 }
 
 pub struct BuildingsGroup(pub Vec<BuildingAssetsPack>);
@@ -51,12 +222,81 @@ impl BuildingsGroup {
     }
 }
 
+pub const WOOD_PLANKS: &str = "Rough Parallel Wood Plank Texture 2k";
+pub const BRICK_WALL: &str = "Brick Wall of Medieval Forts 2k";
+pub const TIMBERED_WALL: &str = "Medieval Timbered Wall 2k";
+const CLAY_TILES: &str = "Orange Clay Rooftop Tiles 2k";
+
 #[derive(SystemParam)]
 pub struct BuildingAssetsInitBridge<'w> {
     asset_server: Res<'w, AssetServer>,
+    scenes: ResMut<'w, Assets<Scene>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    animation_graphs: ResMut<'w, Assets<AnimationGraph>>,
 }
 
 impl BuildingAssets {
+    /// Iterates over the buildings of all groups.
+    pub fn iter(&self) -> impl Iterator<Item = &BuildingAssetsPack> {
+        [
+            &self.foundation,
+            &self.beam,
+            &self.floor,
+            &self.wall,
+            &self.gable,
+            &self.roof,
+            &self.light,
+            &self.opening,
+            &self.custom,
+        ]
+        .into_iter()
+        .flat_map(|group| group.0.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut BuildingAssetsPack> {
+        [
+            &mut self.foundation,
+            &mut self.beam,
+            &mut self.floor,
+            &mut self.wall,
+            &mut self.gable,
+            &mut self.roof,
+            &mut self.light,
+            &mut self.opening,
+            &mut self.custom,
+        ]
+        .into_iter()
+        .flat_map(|group| group.0.iter_mut())
+    }
+
+    /// What the named buildings cost together. Unknown buildings are free.
+    pub fn cost_of<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> ResourceCost {
+        let mut cost = ResourceCost::default();
+        for building in names.into_iter().filter_map(|name| self.get(name)) {
+            cost.add(&building.cost);
+        }
+        cost
+    }
+
+    /// What the named buildings cost together with the upgrades to their tiers.
+    pub fn cost_with_tiers<'a>(
+        &self,
+        pieces: impl IntoIterator<Item = (&'a str, BuildingTier)>,
+    ) -> ResourceCost {
+        let mut cost = ResourceCost::default();
+        for (name, tier) in pieces {
+            cost.add(&self.cost_of([name]));
+            cost.add(&tier.total_cost());
+        }
+        cost
+    }
+
+    /// Finds a building by its unique name.
+    pub fn get(&self, name: &str) -> Option<&BuildingAssetsPack> {
+        self.iter().find(|building| building.name == name)
+    }
+
     pub fn load_all(mut bridge: BuildingAssetsInitBridge) -> Self {
         let foundation = load_group_foundation(&mut bridge);
         let gable = load_group_gabble(&mut bridge);
@@ -64,6 +304,9 @@ impl BuildingAssets {
         let beam = load_group_beam(&mut bridge);
         let wall = load_group_wall(&mut bridge);
         let roof = load_group_roof(&mut bridge);
+        let light = load_group_light(&mut bridge);
+        let opening = load_group_opening(&mut bridge);
+        let custom = load_group_custom(&mut bridge);
 
         Self {
             foundation,
@@ -72,14 +315,38 @@ impl BuildingAssets {
             wall,
             gable,
             roof,
+            light,
+            opening,
+            custom,
         }
     }
 }
 
 #[inline]
-#[allow(unused_variables, unused_mut)]
-fn load_group_foundation(bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
+fn load_group_foundation(mut bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
     BuildingsGroup::empty()
+        .add(
+            BuildingAssetsPack::new(
+                &mut bridge,
+                "Foundation 2x2",
+                GltfAssetLabel::Scene(0).from_asset("models/floor.gltf"),
+                Vec::new(),
+            )
+            .with_footprint(Vec2::new(2.0, 2.0))
+            .with_cost(&[(ResourceKind::Stone, 8)])
+            .with_variants(&[WOOD_PLANKS, BRICK_WALL]),
+        )
+        .add(
+            BuildingAssetsPack::new(
+                &mut bridge,
+                "Foundation 1x1",
+                GltfAssetLabel::Scene(1).from_asset("models/floor.gltf"),
+                Vec::new(),
+            )
+            .with_footprint(Vec2::new(1.0, 1.0))
+            .with_cost(&[(ResourceKind::Stone, 2)])
+            .with_variants(&[WOOD_PLANKS, BRICK_WALL]),
+        )
 }
 
 #[inline]
@@ -91,28 +358,40 @@ fn load_group_beam(mut bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup
 #[inline]
 fn load_group_floor(mut bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
     BuildingsGroup::empty()
-        .add(BuildingAssetsPack::new(
-            &mut bridge,
-            "Floor 2x2",
-            GltfAssetLabel::Scene(0).from_asset("models/floor.gltf"),
-            Vec::new(),
-        ))
-        .add(BuildingAssetsPack::new(
-            &mut bridge,
-            "Floor 1x1",
-            GltfAssetLabel::Scene(1).from_asset("models/floor.gltf"),
-            Vec::new(),
-        ))
+        .add(
+            BuildingAssetsPack::new(
+                &mut bridge,
+                "Floor 2x2",
+                GltfAssetLabel::Scene(0).from_asset("models/floor.gltf"),
+                Vec::new(),
+            )
+            .with_cost(&[(ResourceKind::Wood, 4)])
+            .with_variants(&[WOOD_PLANKS]),
+        )
+        .add(
+            BuildingAssetsPack::new(
+                &mut bridge,
+                "Floor 1x1",
+                GltfAssetLabel::Scene(1).from_asset("models/floor.gltf"),
+                Vec::new(),
+            )
+            .with_cost(&[(ResourceKind::Wood, 1)])
+            .with_variants(&[WOOD_PLANKS]),
+        )
 }
 
 #[inline]
 fn load_group_wall(mut bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
-    BuildingsGroup::empty().add(BuildingAssetsPack::new(
-        &mut bridge,
-        "Wall 2x2",
-        GltfAssetLabel::Scene(0).from_asset("models/wall.gltf"),
-        Vec::new(),
-    ))
+    BuildingsGroup::empty().add(
+        BuildingAssetsPack::new(
+            &mut bridge,
+            "Wall 2x2",
+            GltfAssetLabel::Scene(0).from_asset("models/wall.gltf"),
+            Vec::new(),
+        )
+        .with_cost(&[(ResourceKind::Wood, 4), (ResourceKind::Clay, 2)])
+        .with_variants(&[BRICK_WALL, TIMBERED_WALL, WOOD_PLANKS]),
+    )
 }
 
 #[inline]
@@ -123,10 +402,201 @@ fn load_group_gabble(mut bridge: &mut BuildingAssetsInitBridge) -> BuildingsGrou
 
 #[inline]
 fn load_group_roof(mut bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
-    BuildingsGroup::empty().add(BuildingAssetsPack::new(
-        &mut bridge,
-        "Roof 2x2 45",
-        GltfAssetLabel::Scene(0).from_asset("models/roof.gltf"),
-        Vec::new(),
-    ))
+    BuildingsGroup::empty().add(
+        BuildingAssetsPack::new(
+            &mut bridge,
+            "Roof 2x2 45",
+            GltfAssetLabel::Scene(0).from_asset("models/roof.gltf"),
+            Vec::new(),
+        )
+        .with_cost(&[(ResourceKind::Wood, 3), (ResourceKind::Clay, 4)])
+        .with_variants(&[CLAY_TILES, WOOD_PLANKS]),
+    )
+}
+
+#[inline]
+fn load_group_light(bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
+    let wood = || StandardMaterial {
+        base_color: Color::srgb(0.35, 0.22, 0.12),
+        perceptual_roughness: 0.9,
+        ..default()
+    };
+    let iron = || StandardMaterial {
+        base_color: Color::srgb(0.15, 0.15, 0.16),
+        metallic: 0.8,
+        perceptual_roughness: 0.5,
+        ..default()
+    };
+    let glow = |color: Color, strength: f32| StandardMaterial {
+        base_color: color,
+        emissive: LinearRgba::from(color) * strength,
+        ..default()
+    };
+    let fire = Color::srgb(1.0, 0.55, 0.2);
+    let lamp = Color::srgb(1.0, 0.85, 0.6);
+
+    BuildingsGroup::empty()
+        .add(
+            BuildingAssetsPack::from_shapes(
+                bridge,
+                "Torch",
+                vec![
+                    (
+                        Cylinder::new(0.03, 0.6).into(),
+                        wood(),
+                        Transform::from_xyz(0.0, 0.3, 0.0),
+                    ),
+                    (
+                        Sphere::new(0.06).into(),
+                        glow(fire, 20.0),
+                        Transform::from_xyz(0.0, 0.65, 0.0),
+                    ),
+                ],
+            )
+            .with_cost(&[(ResourceKind::Wood, 1)])
+            .with_light(BuildingLight {
+                kind: BuildingLightKind::Point,
+                color: fire,
+                intensity: 100_000.0,
+                range: 8.0,
+                shadows: true,
+                offset: Vec3::new(0.0, 0.75, 0.0),
+            }),
+        )
+        .add(
+            BuildingAssetsPack::from_shapes(
+                bridge,
+                "Lantern",
+                vec![
+                    (
+                        Cylinder::new(0.04, 1.6).into(),
+                        iron(),
+                        Transform::from_xyz(0.0, 0.8, 0.0),
+                    ),
+                    (
+                        Cuboid::new(0.2, 0.25, 0.2).into(),
+                        glow(lamp, 10.0),
+                        Transform::from_xyz(0.0, 1.72, 0.0),
+                    ),
+                    (
+                        Cone::new(0.18, 0.12).into(),
+                        iron(),
+                        Transform::from_xyz(0.0, 1.9, 0.0),
+                    ),
+                ],
+            )
+            .with_cost(&[(ResourceKind::Iron, 2)])
+            .with_light(BuildingLight {
+                kind: BuildingLightKind::Point,
+                color: lamp,
+                intensity: 150_000.0,
+                range: 10.0,
+                shadows: true,
+                offset: Vec3::new(0.0, 1.72, 0.0),
+            }),
+        )
+        .add(
+            // Hangs down from its origin, which is placed against the ceiling
+            BuildingAssetsPack::from_shapes(
+                bridge,
+                "Ceiling Lamp",
+                vec![
+                    (
+                        Cylinder::new(0.01, 0.3).into(),
+                        iron(),
+                        Transform::from_xyz(0.0, -0.15, 0.0),
+                    ),
+                    (
+                        Cone::new(0.2, 0.15).into(),
+                        iron(),
+                        Transform::from_xyz(0.0, -0.35, 0.0),
+                    ),
+                    (
+                        Sphere::new(0.05).into(),
+                        glow(lamp, 15.0),
+                        Transform::from_xyz(0.0, -0.42, 0.0),
+                    ),
+                ],
+            )
+            .with_cost(&[(ResourceKind::Iron, 1)])
+            .with_light(BuildingLight {
+                kind: BuildingLightKind::Spot {
+                    outer_angle: 60_f32.to_radians(),
+                },
+                color: lamp,
+                intensity: 300_000.0,
+                range: 12.0,
+                shadows: true,
+                offset: Vec3::new(0.0, -0.45, 0.0),
+            }),
+        )
+}
+
+#[inline]
+fn load_group_opening(bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
+    // Their leaves are turned by the "Open" animation of the glTF
+    let mut opening = |name: &str, file: &str, kind: OpeningKind| {
+        BuildingAssetsPack::new(
+            bridge,
+            name,
+            GltfAssetLabel::Scene(0).from_asset(file.to_owned()),
+            Vec::new(),
+        )
+        .with_gltf_opening(
+            bridge,
+            kind,
+            GltfAssetLabel::Animation(0).from_asset(file.to_owned()),
+        )
+    };
+
+    BuildingsGroup::empty()
+        .add(
+            opening("Door", "models/door.gltf", OpeningKind::Door)
+                .with_cost(&[(ResourceKind::Wood, 3), (ResourceKind::Iron, 1)]),
+        )
+        .add(
+            opening("Hatch", "models/hatch.gltf", OpeningKind::Hatch)
+                .with_cost(&[(ResourceKind::Wood, 2), (ResourceKind::Iron, 1)]),
+        )
+        // Both shutters open outwards, towards +Z
+        .add(
+            opening(
+                "Window with shutters",
+                "models/shutters.gltf",
+                OpeningKind::Shutters,
+            )
+            .with_cost(&[(ResourceKind::Wood, 3), (ResourceKind::Iron, 1)]),
+        )
+        .add(
+            opening("Double door", "models/double_door.gltf", OpeningKind::Door)
+                .with_cost(&[(ResourceKind::Wood, 5), (ResourceKind::Iron, 2)]),
+        )
+}
+
+/// The buildings imported before, see `import_buildings`.
+fn load_group_custom(bridge: &mut BuildingAssetsInitBridge) -> BuildingsGroup {
+    let Ok(entries) = std::fs::read_dir(imported_dir()) else {
+        return BuildingsGroup::empty();
+    };
+    let mut buildings: Vec<(String, String)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let file_name = std::fs::read_dir(entry.path())
+                .ok()?
+                .filter_map(Result::ok)
+                .find(|file| is_gltf(&file.path()))?
+                .file_name()
+                .into_string()
+                .ok()?;
+            Some((name, file_name))
+        })
+        .collect();
+    buildings.sort();
+    BuildingsGroup::new(
+        buildings
+            .into_iter()
+            .map(|(name, file_name)| BuildingAssetsPack::imported(bridge, &name, &file_name))
+            .collect(),
+    )
 }
@@ -0,0 +1,99 @@
+use super::building_assets::{BuildingAssets, PreviewBuildingHandle};
+use super::building_tier::BuildingTier;
+use super::demolish::CollapseBuildingEvent;
+use super::selection::MovingBuilding;
+use super::{PlacedBuilding, PlacementBlocked, PreviewBuilding};
+use crate::inventory::{GameMode, Inventory, ResourceCost};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// Share of its cost a demolished building gives back.
+const REFUND_FRACTION: f32 = 0.5;
+
+/// What buildings cost in survival mode, paid from and refunded to the inventory.
+#[derive(SystemParam)]
+pub struct BuildingEconomy<'w> {
+    game_mode: Res<'w, GameMode>,
+    inventory: ResMut<'w, Inventory>,
+    building_assets: Res<'w, BuildingAssets>,
+}
+
+impl BuildingEconomy<'_> {
+    pub fn is_survival(&self) -> bool {
+        *self.game_mode == GameMode::Survival
+    }
+
+    /// What placing the preview costs. Moved buildings have been paid for already.
+    pub fn preview_cost(&self, handle: &PreviewBuildingHandle, moving: bool) -> ResourceCost {
+        if moving {
+            return ResourceCost::default();
+        }
+        if handle.group.is_empty() {
+            self.building_assets.cost_of([handle.name.as_str()])
+        } else {
+            self.building_assets.cost_with_tiers(
+                handle
+                    .group
+                    .iter()
+                    .map(|piece| (piece.name.as_str(), piece.tier)),
+            )
+        }
+    }
+
+    /// Everything is affordable outside of survival mode.
+    pub fn can_afford(&self, cost: &ResourceCost) -> bool {
+        !self.is_survival() || self.inventory.can_afford(cost)
+    }
+
+    pub fn pay(&mut self, cost: &ResourceCost) {
+        if self.is_survival() {
+            self.inventory.spend(cost);
+        }
+    }
+
+    /// Gives back all of what was paid, e.g. for an undone upgrade.
+    pub fn give_back(&mut self, cost: &ResourceCost) {
+        if self.is_survival() {
+            self.inventory.add(cost);
+        }
+    }
+
+    /// Gives a part of what the demolished buildings and their upgrades cost back.
+    pub fn refund<'a>(&mut self, pieces: impl IntoIterator<Item = (&'a str, BuildingTier)>) {
+        if self.is_survival() {
+            let refund = self
+                .building_assets
+                .cost_with_tiers(pieces)
+                .scaled(REFUND_FRACTION);
+            self.inventory.add(&refund);
+        }
+    }
+}
+
+/// Blocks placing the preview when the inventory doesn't hold what it costs.
+pub fn block_unaffordable_placement(
+    mut commands: Commands,
+    preview: Single<Entity, With<PreviewBuilding>>,
+    preview_building_handle: Res<PreviewBuildingHandle>,
+    moving_buildings: Query<(), With<MovingBuilding>>,
+    economy: BuildingEconomy,
+) {
+    let cost = economy.preview_cost(&preview_building_handle, !moving_buildings.is_empty());
+    if !economy.can_afford(&cost) {
+        commands.entity(*preview).insert(PlacementBlocked);
+    }
+}
+
+/// Refunds demolished buildings, before they are removed or fall apart.
+pub fn refund_collapsed_buildings(
+    mut collapse_events: EventReader<CollapseBuildingEvent>,
+    buildings: Query<(&PlacedBuilding, &BuildingTier)>,
+    mut economy: BuildingEconomy,
+) {
+    let pieces: Vec<(&str, BuildingTier)> = collapse_events
+        .read()
+        .filter_map(|event| buildings.get(event.0).ok())
+        .map(|(building, tier)| (building.name.as_str(), *tier))
+        .collect();
+    economy.refund(pieces);
+}
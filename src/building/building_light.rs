@@ -0,0 +1,127 @@
+use super::building_assets::BuildingAssets;
+use super::PlacedBuilding;
+use crate::settings::GameSettings;
+use crate::universal_camera_controller::UniCamController;
+use bevy::prelude::*;
+
+/// Shape of the light a light piece casts.
+#[derive(Clone, Copy)]
+pub enum BuildingLightKind {
+    Point,
+    /// Shines down (-Y). `outer_angle` is the half-angle of the cone in radians.
+    Spot {
+        outer_angle: f32,
+    },
+}
+
+/// Light spawned with a placed building, e.g. the flame of a torch.
+#[derive(Clone)]
+pub struct BuildingLight {
+    pub kind: BuildingLightKind,
+    pub color: Color,
+    /// Luminous power in lumens.
+    pub intensity: f32,
+    /// Distance in meters after which the light has no effect.
+    pub range: f32,
+    /// Whether the light casts shadows while it is within the shadow budget.
+    pub shadows: bool,
+    /// Position of the light relative to the origin of the building.
+    pub offset: Vec3,
+}
+
+/// A light spawned as a child of a placed building.
+#[derive(Component)]
+pub struct BuildingLightSource {
+    shadows: bool,
+}
+
+/// Adds the lights of newly placed buildings, both when they are built and when a world is loaded.
+pub fn spawn_building_lights(
+    mut commands: Commands,
+    placed_buildings: Query<(Entity, &PlacedBuilding), Added<PlacedBuilding>>,
+    building_assets: Res<BuildingAssets>,
+) {
+    for (entity, building) in placed_buildings.iter() {
+        let Some(light) = building_assets
+            .get(&building.name)
+            .and_then(|pack| pack.light.as_ref())
+        else {
+            continue;
+        };
+
+        let source = BuildingLightSource {
+            shadows: light.shadows,
+        };
+        // Shadows are switched on by `apply_light_shadow_budget`
+        commands
+            .entity(entity)
+            .with_children(|parent| match light.kind {
+                BuildingLightKind::Point => {
+                    parent.spawn((
+                        source,
+                        PointLight {
+                            color: light.color,
+                            intensity: light.intensity,
+                            range: light.range,
+                            shadows_enabled: false,
+                            ..default()
+                        },
+                        Transform::from_translation(light.offset),
+                    ));
+                }
+                BuildingLightKind::Spot { outer_angle } => {
+                    parent.spawn((
+                        source,
+                        SpotLight {
+                            color: light.color,
+                            intensity: light.intensity,
+                            range: light.range,
+                            shadows_enabled: false,
+                            outer_angle,
+                            inner_angle: outer_angle * 0.7,
+                            ..default()
+                        },
+                        Transform::from_translation(light.offset).looking_to(Vec3::NEG_Y, Vec3::Z),
+                    ));
+                }
+            });
+    }
+}
+
+/// Lets only the lights closest to the camera cast shadows,
+/// as every shadow casting light renders the scene again.
+pub fn apply_light_shadow_budget(
+    camera: Single<&GlobalTransform, With<UniCamController>>,
+    game_settings: Res<GameSettings>,
+    sources: Query<(Entity, &GlobalTransform, &BuildingLightSource)>,
+    mut point_lights: Query<&mut PointLight, With<BuildingLightSource>>,
+    mut spot_lights: Query<&mut SpotLight, With<BuildingLightSource>>,
+) {
+    let camera_position = camera.translation();
+    let mut shadowed: Vec<(Entity, f32)> = sources
+        .iter()
+        .filter(|(_, _, source)| source.shadows)
+        .map(|(entity, transform, _)| {
+            (
+                entity,
+                transform.translation().distance_squared(camera_position),
+            )
+        })
+        .collect();
+    shadowed.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    shadowed.truncate(game_settings.video.max_shadowed_lights);
+
+    for (entity, _, _) in sources.iter() {
+        let shadows_enabled = shadowed.iter().any(|(shadowed, _)| *shadowed == entity);
+        // Only touch lights that change, so their shadow maps aren't invalidated every frame
+        if let Ok(mut light) = point_lights.get_mut(entity) {
+            if light.shadows_enabled != shadows_enabled {
+                light.shadows_enabled = shadows_enabled;
+            }
+        } else if let Ok(mut light) = spot_lights.get_mut(entity) {
+            if light.shadows_enabled != shadows_enabled {
+                light.shadows_enabled = shadows_enabled;
+            }
+        }
+    }
+}
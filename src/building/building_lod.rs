@@ -0,0 +1,177 @@
+use super::foundation::FoundationPillar;
+use super::mesh_simplify::simplify_mesh;
+use super::selection::MovingBuilding;
+use super::PlacedBuilding;
+use crate::settings::{GameSettings, GraphicsQuality};
+use crate::universal_camera_controller::UniCamController;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use bevy::utils::HashMap;
+
+/// Share of a LOD distance a building has to come closer than it, or move farther than it,
+/// before its level changes. Keeps buildings at the border from switching every frame.
+const HYSTERESIS: f32 = 0.1;
+
+/// Cells along the longest side of a mesh its vertices are merged into when it is simplified.
+const SIMPLIFIED_CELLS: u32 = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum LodLevel {
+    #[default]
+    Full,
+    /// Drawn with its meshes simplified.
+    Simplified,
+    /// Not drawn at all.
+    Culled,
+}
+
+impl LodLevel {
+    /// The level a building at `distance` from the camera should change to from `self`.
+    fn next(self, distance: f32, quality: GraphicsQuality) -> Self {
+        let threshold = |level: Self, distance: f32| {
+            if self >= level {
+                distance * (1.0 - HYSTERESIS)
+            } else {
+                distance * (1.0 + HYSTERESIS)
+            }
+        };
+        if distance > threshold(Self::Culled, quality.cull_distance()) {
+            Self::Culled
+        } else if distance > threshold(Self::Simplified, quality.lod_distance()) {
+            Self::Simplified
+        } else {
+            Self::Full
+        }
+    }
+}
+
+/// Level of detail a placed building is currently drawn with.
+#[derive(Component, Default)]
+pub struct BuildingLod(LodLevel);
+
+/// The mesh a building's mesh had before it was replaced by its simplified version.
+#[derive(Component)]
+pub struct OriginalMesh(pub Handle<Mesh>);
+
+/// Simplified versions of the meshes of buildings, shared by all buildings using the same mesh.
+#[derive(Resource, Default)]
+pub struct SimplifiedMeshes(HashMap<AssetId<Mesh>, Handle<Mesh>>);
+
+impl SimplifiedMeshes {
+    /// `None` while the mesh isn't loaded or if it can't be simplified.
+    fn get_or_simplify(
+        &mut self,
+        original: &Handle<Mesh>,
+        meshes: &mut Assets<Mesh>,
+    ) -> Option<Handle<Mesh>> {
+        if let Some(simplified) = self.0.get(&original.id()) {
+            return Some(simplified.clone());
+        }
+        let simplified = meshes.add(simplify_mesh(meshes.get(original)?, SIMPLIFIED_CELLS)?);
+        self.0.insert(original.id(), simplified.clone());
+        Some(simplified)
+    }
+}
+
+/// Everything needed to swap the meshes of a building's scene. Pillars keep their own.
+#[derive(SystemParam)]
+pub struct LodMeshes<'w, 's> {
+    children: Query<'w, 's, &'static Children>,
+    meshes: Query<
+        'w,
+        's,
+        (&'static mut Mesh3d, Option<&'static OriginalMesh>),
+        Without<FoundationPillar>,
+    >,
+    simplified_meshes: ResMut<'w, SimplifiedMeshes>,
+    mesh_assets: ResMut<'w, Assets<Mesh>>,
+    commands: Commands<'w, 's>,
+}
+
+impl LodMeshes<'_, '_> {
+    /// Replaces the meshes below `root` by their simplified versions, or restores the originals.
+    fn apply(&mut self, root: Entity, simplified: bool) {
+        for entity in self.children.iter_descendants(root) {
+            let Ok((mut mesh, original)) = self.meshes.get_mut(entity) else {
+                continue;
+            };
+            match (simplified, original) {
+                (true, None) => {
+                    let Some(replacement) = self
+                        .simplified_meshes
+                        .get_or_simplify(&mesh.0, &mut self.mesh_assets)
+                    else {
+                        continue;
+                    };
+                    self.commands
+                        .entity(entity)
+                        .insert(OriginalMesh(mesh.0.clone()));
+                    mesh.0 = replacement;
+                }
+                (false, Some(original)) => {
+                    mesh.0 = original.0.clone();
+                    self.commands.entity(entity).remove::<OriginalMesh>();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+type LodBuildings<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static GlobalTransform,
+        &'static mut BuildingLod,
+        &'static mut Visibility,
+    ),
+    // Moved buildings are hidden while their preview is placed
+    (With<PlacedBuilding>, Without<MovingBuilding>),
+>;
+
+/// Switches placed buildings between full detail, simplified and hidden by their distance to the camera.
+pub fn update_building_lods(
+    camera: Single<&GlobalTransform, With<UniCamController>>,
+    game_settings: Res<GameSettings>,
+    mut buildings: LodBuildings,
+    mut lod_meshes: LodMeshes,
+) {
+    let camera_position = camera.translation();
+    let quality = game_settings.video.quality;
+    for (entity, transform, mut lod, mut visibility) in buildings.iter_mut() {
+        let distance = transform.translation().distance(camera_position);
+        let level = lod.0.next(distance, quality);
+        if level == lod.0 {
+            continue;
+        }
+        lod.0 = level;
+
+        *visibility = match level {
+            LodLevel::Culled => Visibility::Hidden,
+            _ => Visibility::Inherited,
+        };
+        // Hidden buildings keep whatever they were drawn with, so they come back without a swap
+        if level == LodLevel::Culled {
+            continue;
+        }
+        lod_meshes.apply(entity, level == LodLevel::Simplified);
+    }
+}
+
+/// Simplifies the meshes of buildings whose scene is spawned while they are already far away.
+pub fn apply_building_lod_on_scene_ready(
+    trigger: Trigger<SceneInstanceReady>,
+    buildings: Query<&BuildingLod, With<PlacedBuilding>>,
+    mut lod_meshes: LodMeshes,
+) {
+    let entity = trigger.entity();
+    let Ok(lod) = buildings.get(entity) else {
+        return;
+    };
+    if lod.0 == LodLevel::Simplified {
+        lod_meshes.apply(entity, true);
+    }
+}
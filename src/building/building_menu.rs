@@ -1,9 +1,20 @@
-use super::building_assets::{BuildingAssets, BuildingsGroup};
-use super::{BuildingMode, ChangeBuildingModeEvent};
+use super::blueprint::Blueprints;
+use super::building_assets::{BuildingAssets, BuildingAssetsPack, BuildingsGroup};
+use super::building_cost::BuildingEconomy;
+use super::foundation::FoundationLeveling;
+use super::gltf_export::{ExportBuildingsEvent, ExportScope};
+use super::gltf_import::ImportBuildingEvent;
+use super::placement::GRID_SIZES;
+use super::{BuildingMode, BuildingSettings, ChangeBuildingModeEvent};
 use crate::building::building_assets::PreviewBuildingHandle;
+use crate::file_names::is_valid_file_stem;
+use crate::inventory::ResourceCost;
 use crate::universal_camera_controller::{UniCamChangeStateEvent, UniCamState};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
+use bevy_egui::egui::Slider;
 use bevy_egui::{egui, EguiContexts};
 
 pub fn enter_building_menu(
@@ -15,24 +26,60 @@ pub fn enter_building_menu(
     evw_change_universal_cam.send(UniCamChangeStateEvent(UniCamState::Disabled));
 }
 
+/// What the building menu offers, and whether the player can afford it.
+#[derive(SystemParam)]
+pub struct BuildingMenuContents<'w> {
+    building_assets: Res<'w, BuildingAssets>,
+    blueprints: Res<'w, Blueprints>,
+    economy: BuildingEconomy<'w>,
+}
+
+impl BuildingMenuContents<'_> {
+    /// Shows what the buildings cost in survival mode, in red when they can't be afforded.
+    fn show_cost(&self, ui: &mut egui::Ui, cost: &ResourceCost) {
+        if !self.economy.is_survival() {
+            return;
+        }
+        if self.economy.can_afford(cost) {
+            ui.label(cost.to_string());
+        } else {
+            ui.colored_label(egui::Color32::LIGHT_RED, cost.to_string())
+                .on_hover_text("Not enough resources");
+        }
+    }
+}
+
 pub fn building_menu(
     mut contexts: EguiContexts,
-    building_assets: Res<BuildingAssets>,
+    contents: BuildingMenuContents,
     mut evw_change_build_mode: EventWriter<ChangeBuildingModeEvent>,
     mut preview_building_handle: ResMut<PreviewBuildingHandle>,
+    mut building_settings: ResMut<BuildingSettings>,
+    mut chosen_variants: Local<HashMap<String, Option<String>>>,
 ) {
+    let building_assets = &contents.building_assets;
     let mut show_building_category =
         |ui: &mut egui::Ui, category_name: &str, buildings: &BuildingsGroup| {
             ui.collapsing(category_name, |ui| {
                 for building in &buildings.0 {
-                    ui.button(building.name.clone()).clicked().then(|| {
-                        preview_building_handle.0 = Some(building.scene.clone());
-                        evw_change_build_mode.send(ChangeBuildingModeEvent(BuildingMode::Building));
+                    let variant = chosen_variants.entry(building.name.clone()).or_default();
+                    ui.horizontal(|ui| {
+                        ui.button(building.name.clone()).clicked().then(|| {
+                            preview_building_handle.select(building, variant.clone());
+                            evw_change_build_mode
+                                .send(ChangeBuildingModeEvent(BuildingMode::Building));
+                        });
+                        if !building.variants.is_empty() {
+                            show_variant_selection(ui, building, variant);
+                        }
+                        contents.show_cost(ui, &building.cost);
                     });
                 }
             });
         };
 
+    // Chosen in the window but selected after it, the categories above hold the preview handle
+    let mut chosen_blueprint = None;
     egui::Window::new("Building Menu").show(contexts.ctx_mut(), |ui| {
         show_building_category(ui, "Foundation", &building_assets.foundation);
         show_building_category(ui, "Beam", &building_assets.beam);
@@ -40,6 +87,116 @@ pub fn building_menu(
         show_building_category(ui, "Wall", &building_assets.wall);
         show_building_category(ui, "Gable", &building_assets.gable);
         show_building_category(ui, "Roof", &building_assets.roof);
+        show_building_category(ui, "Light", &building_assets.light);
+        show_building_category(ui, "Doors & windows", &building_assets.opening);
+        show_building_category(ui, "Custom", &building_assets.custom);
+        ui.collapsing("Blueprints", |ui| {
+            if contents.blueprints.0.is_empty() {
+                ui.label("Save a selection as a blueprint to build it again");
+            }
+            for (name, blueprint) in &contents.blueprints.0 {
+                ui.horizontal(|ui| {
+                    if ui.button(name).clicked() {
+                        chosen_blueprint = Some((name, blueprint));
+                    }
+                    let pieces = blueprint
+                        .pieces
+                        .iter()
+                        .map(|piece| (piece.name.as_str(), piece.tier));
+                    contents.show_cost(ui, &building_assets.cost_with_tiers(pieces));
+                });
+            }
+        });
+        ui.separator();
+        ui.collapsing("Placement", |ui| {
+            ui.checkbox(&mut building_settings.snapping, "Snap to grid");
+            ui.add_enabled_ui(building_settings.snapping, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Grid (m)");
+                    for size in GRID_SIZES {
+                        ui.radio_value(&mut building_settings.grid_size, size, size.to_string());
+                    }
+                });
+            });
+            ui.add(
+                Slider::new(&mut building_settings.rotation_step, 1.0..=90.0)
+                    .text("Rotation step (°)"),
+            );
+        });
+        ui.collapsing("Foundation leveling", |ui| {
+            ui.radio_value(
+                &mut building_settings.foundation_leveling,
+                FoundationLeveling::Pillars,
+                "Pillars",
+            );
+            ui.radio_value(
+                &mut building_settings.foundation_leveling,
+                FoundationLeveling::Terraform,
+                "Terraform",
+            );
+            ui.add(
+                Slider::new(&mut building_settings.max_foundation_slope, 0.0..=60.0)
+                    .text("Max slope (°)"),
+            );
+        });
+    });
+
+    if let Some((name, blueprint)) = chosen_blueprint {
+        preview_building_handle.select_group(name, blueprint.preview_pieces(building_assets));
+        evw_change_build_mode.send(ChangeBuildingModeEvent(BuildingMode::Building));
+    }
+}
+
+fn show_variant_selection(
+    ui: &mut egui::Ui,
+    building: &BuildingAssetsPack,
+    variant: &mut Option<String>,
+) {
+    egui::ComboBox::from_id_salt(&building.name)
+        .selected_text(variant.as_deref().unwrap_or("Default"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(variant, None, "Default");
+            for name in &building.variants {
+                ui.selectable_value(variant, Some(name.clone()), name);
+            }
+        });
+}
+
+/// Window to import glTFs as custom buildings and to export the whole settlement.
+pub fn import_export_menu(
+    mut contexts: EguiContexts,
+    mut import_events: EventWriter<ImportBuildingEvent>,
+    mut export_events: EventWriter<ExportBuildingsEvent>,
+    mut import_path: Local<String>,
+    mut export_name: Local<String>,
+) {
+    egui::Window::new("Import / Export").show(contexts.ctx_mut(), |ui| {
+        ui.label("Import a .gltf or .glb file as a custom building");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut *import_path);
+            let path = import_path.trim();
+            if ui
+                .add_enabled(!path.is_empty(), egui::Button::new("Import"))
+                .clicked()
+            {
+                import_events.send(ImportBuildingEvent(path.into()));
+            }
+        });
+        ui.separator();
+        ui.label("Export all placed buildings to a .glb file");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut *export_name);
+            let name = export_name.trim();
+            if ui
+                .add_enabled(is_valid_file_stem(name), egui::Button::new("Export"))
+                .clicked()
+            {
+                export_events.send(ExportBuildingsEvent {
+                    name: name.to_string(),
+                    scope: ExportScope::Settlement,
+                });
+            }
+        });
     });
 }
 
@@ -0,0 +1,188 @@
+use super::building_assets::{BRICK_WALL, TIMBERED_WALL, WOOD_PLANKS};
+use super::building_cost::BuildingEconomy;
+use super::demolish::{BuildingDamage, TargetedBuilding, DEMOLISH_DISTANCE};
+use crate::inventory::{ResourceCost, ResourceKind};
+use crate::settings::GameSettings;
+use crate::world_streaming::StreamWorldEvent;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Upgrades which are remembered to be undone, the oldest are forgotten first.
+const MAX_UPGRADE_HISTORY: usize = 50;
+
+/// How sturdy a placed building is built. Buildings are drawn with the material of their tier,
+/// the material variant they were placed with only shows while the tier has none.
+#[derive(
+    Component, Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum BuildingTier {
+    /// Freshly placed, with the materials of its glTF.
+    #[default]
+    Twig,
+    Wood,
+    /// Half-timbered. Saves from before it replaced the stone tier, which had no stone
+    /// material and was drawn timbered already, call it `Stone`.
+    #[serde(alias = "Stone")]
+    Timbered,
+    Brick,
+}
+
+impl BuildingTier {
+    pub const ALL: [BuildingTier; 4] = [
+        BuildingTier::Twig,
+        BuildingTier::Wood,
+        BuildingTier::Timbered,
+        BuildingTier::Brick,
+    ];
+
+    pub fn next(self) -> Option<BuildingTier> {
+        match self {
+            BuildingTier::Twig => Some(BuildingTier::Wood),
+            BuildingTier::Wood => Some(BuildingTier::Timbered),
+            BuildingTier::Timbered => Some(BuildingTier::Brick),
+            BuildingTier::Brick => None,
+        }
+    }
+
+    /// Material from the `MaterialLibrary` the building is drawn with, `None` keeps its material variant.
+    pub fn material(self) -> Option<String> {
+        match self {
+            BuildingTier::Twig => None,
+            BuildingTier::Wood => Some(WOOD_PLANKS.to_string()),
+            BuildingTier::Timbered => Some(TIMBERED_WALL.to_string()),
+            BuildingTier::Brick => Some(BRICK_WALL.to_string()),
+        }
+    }
+
+    /// Demolish hits it takes for the building to collapse.
+    pub fn strength(self) -> u32 {
+        match self {
+            BuildingTier::Twig => 1,
+            BuildingTier::Wood => 2,
+            BuildingTier::Timbered => 4,
+            BuildingTier::Brick => 6,
+        }
+    }
+
+    /// What upgrading to this tier from the previous one costs.
+    pub fn upgrade_cost(self) -> ResourceCost {
+        match self {
+            BuildingTier::Twig => ResourceCost::default(),
+            BuildingTier::Wood => ResourceCost::new(&[(ResourceKind::Wood, 3)]),
+            BuildingTier::Timbered => {
+                ResourceCost::new(&[(ResourceKind::Wood, 4), (ResourceKind::Clay, 2)])
+            }
+            BuildingTier::Brick => {
+                ResourceCost::new(&[(ResourceKind::Clay, 4), (ResourceKind::Stone, 2)])
+            }
+        }
+    }
+
+    /// What the upgrades up to this tier cost together, on top of the building itself.
+    pub fn total_cost(self) -> ResourceCost {
+        let mut cost = ResourceCost::default();
+        for tier in Self::ALL.into_iter().filter(|tier| *tier <= self) {
+            cost.add(&tier.upgrade_cost());
+        }
+        cost
+    }
+}
+
+impl Display for BuildingTier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// An upgrade of a placed building, with the tier it had before.
+struct UpgradeRecord {
+    building: Entity,
+    previous_tier: BuildingTier,
+    cost: ResourceCost,
+}
+
+/// Upgrades which can be undone, the latest last. Only kept for the current session:
+/// it isn't saved, and loading a world clears it.
+#[derive(Resource, Default)]
+pub struct UpgradeHistory(Vec<UpgradeRecord>);
+
+/// Forgets the upgrades of the previous world. Its buildings are despawned as the new world
+/// is streamed in, none of them is left to undo an upgrade of.
+pub fn clear_upgrade_history(
+    mut evr_stream: EventReader<StreamWorldEvent>,
+    mut history: ResMut<UpgradeHistory>,
+) {
+    if evr_stream.read().count() > 0 {
+        history.0.clear();
+    }
+}
+
+/// Upgrades the placed building in the middle of the view to the next tier.
+/// In survival mode, the upgrade is paid from the inventory.
+pub fn upgrade_targeted_building(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut target: TargetedBuilding,
+    mut buildings: Query<&mut BuildingTier>,
+    mut economy: BuildingEconomy,
+    mut history: ResMut<UpgradeHistory>,
+) {
+    if !keys.just_pressed(game_settings.keyboard.upgrade_building) {
+        return;
+    }
+    let Some(building) = target.building_within(DEMOLISH_DISTANCE) else {
+        return;
+    };
+    let Ok(mut tier) = buildings.get_mut(building) else {
+        return;
+    };
+    let Some(next) = tier.next() else {
+        info!("The building is already of the highest tier");
+        return;
+    };
+    let cost = next.upgrade_cost();
+    if !economy.can_afford(&cost) {
+        info!("Not enough resources to upgrade to {next}, it costs {cost}");
+        return;
+    }
+    economy.pay(&cost);
+
+    history.0.push(UpgradeRecord {
+        building,
+        previous_tier: *tier,
+        cost,
+    });
+    if history.0.len() > MAX_UPGRADE_HISTORY {
+        history.0.remove(0);
+    }
+    *tier = next;
+    // Upgrading repairs the building as well
+    commands.entity(building).remove::<BuildingDamage>();
+}
+
+/// Reverts the latest upgrade of a building which still exists and gives back what it cost.
+pub fn undo_building_upgrade(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut history: ResMut<UpgradeHistory>,
+    mut buildings: Query<&mut BuildingTier>,
+    mut economy: BuildingEconomy,
+) {
+    if !keys.just_pressed(game_settings.keyboard.undo_upgrade) {
+        return;
+    }
+    while let Some(record) = history.0.pop() {
+        // Demolished buildings, or ones upgraded since in another way, are skipped
+        let Ok(mut tier) = buildings.get_mut(record.building) else {
+            continue;
+        };
+        if record.previous_tier.next() != Some(*tier) {
+            continue;
+        }
+        *tier = record.previous_tier;
+        economy.give_back(&record.cost);
+        return;
+    }
+}
@@ -0,0 +1,89 @@
+use super::building_tier::BuildingTier;
+use super::PlacedBuilding;
+use crate::settings::GameSettings;
+use crate::universal_camera_controller::UniCamController;
+use bevy::ecs::system::SystemParam;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, RayCastSettings};
+use bevy::prelude::*;
+
+/// Farthest a building can be demolished from, in meters.
+pub const DEMOLISH_DISTANCE: f32 = 50.0;
+
+/// A placed building falls apart. With the "physics" feature its pieces fall down as debris,
+/// without it the building is simply removed.
+#[derive(Event)]
+pub struct CollapseBuildingEvent(pub Entity);
+
+/// Demolish hits a building has taken. It collapses once they reach the strength of its tier.
+#[derive(Component)]
+pub struct BuildingDamage(u32);
+
+/// Finds the placed building in the middle of the view.
+#[derive(SystemParam)]
+pub struct TargetedBuilding<'w, 's> {
+    camera: Single<'w, &'static GlobalTransform, With<UniCamController>>,
+    ray_cast: MeshRayCast<'w, 's>,
+    parents: Query<'w, 's, &'static Parent>,
+    placed_buildings: Query<'w, 's, (), With<PlacedBuilding>>,
+}
+
+impl TargetedBuilding<'_, '_> {
+    /// The building the view is on, if it's within `distance`.
+    pub fn building_within(&mut self, distance: f32) -> Option<Entity> {
+        let parents = &self.parents;
+        let placed_buildings = &self.placed_buildings;
+        // The preview and other meshes in front don't belong to a placed building, the ray passes them
+        let building_of = |entity: Entity| {
+            std::iter::once(entity)
+                .chain(parents.iter_ancestors(entity))
+                .find(|ancestor| placed_buildings.contains(*ancestor))
+        };
+        let filter = |entity: Entity| building_of(entity).is_some();
+        let settings = RayCastSettings::default().with_filter(&filter);
+        let ray = Ray3d::new(self.camera.translation(), self.camera.forward());
+        self.ray_cast
+            .cast_ray(ray, &settings)
+            .first()
+            .filter(|(_, hit)| hit.distance <= distance)
+            .and_then(|(entity, _)| building_of(*entity))
+    }
+}
+
+/// Hits the placed building in the middle of the view, which collapses once its strength is used up.
+pub fn demolish_targeted_building(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut target: TargetedBuilding,
+    damage: Query<(&BuildingTier, Option<&BuildingDamage>)>,
+    mut collapse_events: EventWriter<CollapseBuildingEvent>,
+) {
+    if !keys.just_pressed(game_settings.keyboard.demolish) {
+        return;
+    }
+    let Some(building) = target.building_within(DEMOLISH_DISTANCE) else {
+        return;
+    };
+    let Ok((tier, damage)) = damage.get(building) else {
+        return;
+    };
+    let hits = damage.map_or(0, |damage| damage.0) + 1;
+    if hits >= tier.strength() {
+        collapse_events.send(CollapseBuildingEvent(building));
+    } else {
+        commands.entity(building).insert(BuildingDamage(hits));
+    }
+}
+
+/// Removes collapsed buildings when there is no physics to let them fall apart.
+#[cfg(not(feature = "physics"))]
+pub fn remove_collapsed_buildings(
+    mut commands: Commands,
+    mut collapse_events: EventReader<CollapseBuildingEvent>,
+) {
+    for event in collapse_events.read() {
+        if let Some(entity) = commands.get_entity(event.0) {
+            entity.despawn_recursive();
+        }
+    }
+}
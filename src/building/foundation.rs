@@ -0,0 +1,362 @@
+use super::building_assets::{BuildingAssets, PreviewBuildingHandle, PreviewPiece};
+use super::{BuildingSettings, PlacedBuilding, PlacementBlocked, PreviewBuilding};
+use crate::material_library::MaterialLibrary;
+use crate::terrain::Terrain;
+use bevy::prelude::*;
+
+/// Material of the pillars from the material library.
+const PILLAR_MATERIAL: &str = "Rough Parallel Wood Plank Texture 2k";
+
+/// Side of the square pillars supporting foundations.
+const PILLAR_WIDTH: f32 = 0.2;
+
+/// Gaps between a foundation and the ground smaller than this don't get a pillar.
+const MIN_PILLAR_HEIGHT: f32 = 0.02;
+
+/// Width of the band around a terraformed pad which blends into the surrounding terrain.
+const TERRAFORM_MARGIN: f32 = 1.0;
+
+/// How a foundation is supported on uneven ground.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FoundationLeveling {
+    /// The foundation rests on the highest point of the ground, pillars reach down to the rest.
+    Pillars,
+    /// The ground under the foundation is flattened to its average height.
+    Terraform,
+}
+
+/// Terrain heights sampled over the footprint of a foundation.
+pub struct FootprintGround {
+    min: f32,
+    max: f32,
+    average: f32,
+    /// Steepest slope across the footprint, in degrees.
+    slope: f32,
+}
+
+impl FootprintGround {
+    /// Samples the corners, edge centers and center of a footprint placed with `transform`.
+    /// `None` if part of the footprint is outside of the terrain.
+    pub fn sample(terrain: &Terrain, transform: &Transform, footprint: Vec2) -> Option<Self> {
+        let half = footprint / 2.0;
+        let heights = [-1.0, 0.0, 1.0]
+            .into_iter()
+            .flat_map(|x| [-1.0, 0.0, 1.0].map(|z| Vec2::new(x, z) * half))
+            .map(|local| {
+                let world = footprint_point(transform, local);
+                terrain.height_at(world.x, world.z)
+            })
+            .collect::<Option<Vec<f32>>>()?;
+
+        let min = heights.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let average = heights.iter().sum::<f32>() / heights.len() as f32;
+        let slope = ((max - min) / footprint.length().max(f32::EPSILON))
+            .atan()
+            .to_degrees();
+        Some(Self {
+            min,
+            max,
+            average,
+            slope,
+        })
+    }
+
+    /// Height the bottom of the foundation is placed at.
+    pub fn level(&self, leveling: FoundationLeveling) -> f32 {
+        match leveling {
+            FoundationLeveling::Pillars => self.max,
+            FoundationLeveling::Terraform => self.average,
+        }
+    }
+}
+
+/// Foundations placed together, e.g. those of a moved selection, leveled as one:
+/// the whole group moves up or down until the foundation needing the highest ground rests on it.
+pub struct LeveledFoundations {
+    /// World transforms of the foundations once leveled, with their footprints and the ground under them.
+    foundations: Vec<(Transform, Vec2, FootprintGround)>,
+    /// How far the anchor of the group moves up or down.
+    pub offset: f32,
+}
+
+impl LeveledFoundations {
+    /// Levels `foundations`, placed relative to `anchor` with their footprints.
+    /// `None` without foundations, or if part of a footprint is outside of the terrain.
+    pub fn level(
+        terrain: &Terrain,
+        anchor: &Transform,
+        foundations: &[(Transform, Vec2)],
+        leveling: FoundationLeveling,
+    ) -> Option<Self> {
+        let sampled = foundations
+            .iter()
+            .map(|(local, footprint)| {
+                let transform = anchor.mul_transform(*local);
+                let ground = FootprintGround::sample(terrain, &transform, *footprint)?;
+                Some((transform, *footprint, ground))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let offset = sampled
+            .iter()
+            .map(|(transform, _, ground)| ground.level(leveling) - transform.translation.y)
+            .reduce(f32::max)?;
+        let foundations = sampled
+            .into_iter()
+            .map(|(mut transform, footprint, ground)| {
+                transform.translation.y += offset;
+                (transform, footprint, ground)
+            })
+            .collect();
+        Some(Self {
+            foundations,
+            offset,
+        })
+    }
+
+    /// Whether the ground under any of the foundations is steeper than `max_slope` degrees.
+    pub fn too_steep(&self, max_slope: f32) -> bool {
+        self.foundations
+            .iter()
+            .any(|(_, _, ground)| ground.slope > max_slope)
+    }
+}
+
+/// The foundations among the pieces of a group, positioned relative to its anchor, with their footprints.
+pub fn group_foundations(pieces: &[PreviewPiece]) -> Vec<(Transform, Vec2)> {
+    pieces
+        .iter()
+        .filter_map(|piece| Some((piece.transform, piece.footprint?)))
+        .collect()
+}
+
+/// World position of a point of the footprint, `local` relative to the foundation center.
+fn footprint_point(transform: &Transform, local: Vec2) -> Vec3 {
+    transform.translation + transform.rotation * Vec3::new(local.x, 0.0, local.y)
+}
+
+/// Centers of the pillars under the corners of a footprint, relative to the foundation center.
+fn pillar_corners(footprint: Vec2) -> [Vec2; 4] {
+    let inset = footprint / 2.0 - PILLAR_WIDTH / 2.0;
+    [
+        Vec2::new(-inset.x, -inset.y),
+        Vec2::new(inset.x, -inset.y),
+        Vec2::new(-inset.x, inset.y),
+        Vec2::new(inset.x, inset.y),
+    ]
+}
+
+/// Places the foundation preview, or a group preview with foundations, on the ground,
+/// checks the slope under them and draws the pillars or the terraformed pads they need.
+pub fn level_preview_foundation(
+    mut commands: Commands,
+    preview: Single<(Entity, &mut Transform), With<PreviewBuilding>>,
+    preview_building_handle: Res<PreviewBuildingHandle>,
+    building_settings: Res<BuildingSettings>,
+    terrain: Option<Res<Terrain>>,
+    mut gizmos: Gizmos,
+) {
+    let (entity, mut transform) = preview.into_inner();
+    let foundations = match preview_building_handle.footprint {
+        Some(footprint) => vec![(Transform::IDENTITY, footprint)],
+        None => group_foundations(&preview_building_handle.group),
+    };
+    let Some(terrain) = terrain.filter(|_| !foundations.is_empty()) else {
+        commands.entity(entity).remove::<PlacementBlocked>();
+        return;
+    };
+
+    let leveling = building_settings.foundation_leveling;
+    let Some(leveled) = LeveledFoundations::level(&terrain, &transform, &foundations, leveling)
+    else {
+        commands.entity(entity).insert(PlacementBlocked);
+        return;
+    };
+    transform.translation.y += leveled.offset;
+
+    if leveled.too_steep(building_settings.max_foundation_slope) {
+        commands.entity(entity).insert(PlacementBlocked);
+    } else {
+        commands.entity(entity).remove::<PlacementBlocked>();
+    }
+
+    for (transform, footprint, ground) in &leveled.foundations {
+        let too_steep = ground.slope > building_settings.max_foundation_slope;
+        draw_foundation(
+            &mut gizmos,
+            &terrain,
+            transform,
+            *footprint,
+            ground,
+            leveling,
+            too_steep,
+        );
+    }
+}
+
+/// Outlines a leveled foundation and draws the pillars or the terraformed pad it needs.
+fn draw_foundation(
+    gizmos: &mut Gizmos,
+    terrain: &Terrain,
+    transform: &Transform,
+    footprint: Vec2,
+    ground: &FootprintGround,
+    leveling: FoundationLeveling,
+    too_steep: bool,
+) {
+    let outline_color = if too_steep {
+        Color::srgb(1.0, 0.2, 0.2)
+    } else {
+        Color::srgb(0.2, 1.0, 0.2)
+    };
+    let corners = [
+        Vec2::new(-1.0, -1.0),
+        Vec2::new(1.0, -1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, 1.0),
+    ]
+    .map(|corner| footprint_point(transform, corner * footprint / 2.0));
+    gizmos.linestrip(
+        corners.iter().chain(corners.first()).copied(),
+        outline_color,
+    );
+
+    match leveling {
+        FoundationLeveling::Pillars => {
+            for corner in pillar_corners(footprint) {
+                let top = footprint_point(transform, corner);
+                let Some(ground_height) = terrain.height_at(top.x, top.z) else {
+                    continue;
+                };
+                if top.y - ground_height > MIN_PILLAR_HEIGHT {
+                    gizmos.line(top, top.with_y(ground_height), Color::srgb(1.0, 0.8, 0.2));
+                }
+            }
+        }
+        FoundationLeveling::Terraform => {
+            // Red where the ground is cut away, blue where it is filled up
+            for corner in corners {
+                let Some(ground_height) = terrain.height_at(corner.x, corner.z) else {
+                    continue;
+                };
+                let color = if ground_height > corner.y {
+                    Color::srgb(1.0, 0.3, 0.3)
+                } else {
+                    Color::srgb(0.3, 0.5, 1.0)
+                };
+                gizmos.line(corner, corner.with_y(ground_height), color);
+            }
+            gizmos.line(
+                Vec3::new(transform.translation.x, ground.min, transform.translation.z),
+                Vec3::new(transform.translation.x, ground.max, transform.translation.z),
+                Color::WHITE,
+            );
+        }
+    }
+}
+
+/// Flattens the terrain under a foundation placed with `transform` to its height.
+pub fn terraform_foundation_pad(terrain: &mut Terrain, transform: &Transform, footprint: Vec2) {
+    let half = footprint / 2.0;
+    let target = transform.translation.y;
+    let inverse_rotation = transform.rotation.inverse();
+    let radius = half.length() + TERRAFORM_MARGIN;
+
+    for (vertex, _) in terrain.vertices_in_radius(transform.translation.xz(), radius) {
+        let position = terrain.vertex_position(vertex);
+        let offset = Vec3::new(position.x, 0.0, position.y) - transform.translation.with_y(0.0);
+        let local = (inverse_rotation * offset).xz();
+        // Distance outside of the pad, 0 inside
+        let outside = (local.abs() - half).max(Vec2::ZERO).length();
+        if outside >= TERRAFORM_MARGIN {
+            continue;
+        }
+        let Some(height) = terrain.vertex_height(vertex) else {
+            continue;
+        };
+        let blend = 1.0 - outside / TERRAFORM_MARGIN;
+        terrain.set_vertex_height(vertex, height.lerp(target, blend * blend));
+    }
+}
+
+/// Flattens the terrain under the foundations among `pieces` placed relative to `anchor`.
+pub fn terraform_group_pads(terrain: &mut Terrain, anchor: &Transform, pieces: &[PreviewPiece]) {
+    for (transform, footprint) in group_foundations(pieces) {
+        terraform_foundation_pad(terrain, &anchor.mul_transform(transform), footprint);
+    }
+}
+
+/// A pillar under a foundation.
+#[derive(Component)]
+pub struct FoundationPillar;
+
+/// Mesh of the pillars under foundations and the material used if the library doesn't have theirs.
+#[derive(Resource)]
+pub struct PillarAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for PillarAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh =
+            world
+                .resource_mut::<Assets<Mesh>>()
+                .add(Cuboid::new(PILLAR_WIDTH, 1.0, PILLAR_WIDTH));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::srgb(0.45, 0.33, 0.22),
+                perceptual_roughness: 0.9,
+                ..default()
+            });
+        Self { mesh, material }
+    }
+}
+
+/// Adds pillars reaching down to the ground under newly placed foundations,
+/// both when they are built and when a world is loaded.
+pub fn spawn_foundation_pillars(
+    mut commands: Commands,
+    placed_buildings: Query<(Entity, &PlacedBuilding, &Transform), Added<PlacedBuilding>>,
+    building_assets: Res<BuildingAssets>,
+    pillar_assets: Res<PillarAssets>,
+    material_library: Res<MaterialLibrary>,
+    terrain: Option<Res<Terrain>>,
+) {
+    let Some(terrain) = terrain else {
+        return;
+    };
+    let material = material_library
+        .get(PILLAR_MATERIAL)
+        .unwrap_or_else(|| pillar_assets.material.clone());
+    for (entity, building, transform) in placed_buildings.iter() {
+        let Some(footprint) = building_assets
+            .get(&building.name)
+            .and_then(|pack| pack.footprint)
+        else {
+            continue;
+        };
+
+        commands.entity(entity).with_children(|parent| {
+            for corner in pillar_corners(footprint) {
+                let top = footprint_point(transform, corner);
+                let Some(ground_height) = terrain.height_at(top.x, top.z) else {
+                    continue;
+                };
+                let height = top.y - ground_height;
+                if height <= MIN_PILLAR_HEIGHT {
+                    continue;
+                }
+                // Children are in the foundation's space, which may be rotated but never scaled
+                parent.spawn((
+                    FoundationPillar,
+                    Mesh3d(pillar_assets.mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform::from_xyz(corner.x, -height / 2.0, corner.y)
+                        .with_scale(Vec3::new(1.0, height, 1.0)),
+                ));
+            }
+        });
+    }
+}
@@ -0,0 +1,500 @@
+use super::building_lod::OriginalMesh;
+use super::selection::{group_anchor, BuildingSelection};
+use super::PlacedBuilding;
+use crate::file_names::is_valid_file_stem;
+use ::image::codecs::png::PngEncoder;
+use ::image::{ExtendedColorType, ImageEncoder};
+use bevy::asset::io::file::FileAssetReader;
+use bevy::ecs::system::SystemParam;
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+use bevy::render::mesh::{PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_resource::TextureFormat;
+use bevy::utils::HashMap;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+
+/// Directory (relative to the working directory) exported buildings are written to.
+pub const EXPORTS_DIR: &str = "exports";
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+#[derive(Clone, Copy, Debug)]
+pub enum ExportScope {
+    /// The selected buildings.
+    Selection,
+    /// All placed buildings.
+    Settlement,
+}
+
+/// Exports placed buildings to `<EXPORTS_DIR>/<name>.glb`, positioned around the same anchor
+/// as blueprints, at their center and level with the lowest of them.
+#[derive(Event)]
+pub struct ExportBuildingsEvent {
+    pub name: String,
+    pub scope: ExportScope,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    InvalidName,
+    Nothing,
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::InvalidName => write!(f, "invalid file name"),
+            ExportError::Nothing => write!(f, "no buildings to export"),
+            ExportError::Io(err) => write!(f, "I/O error: {err}"),
+            ExportError::Json(err) => write!(f, "JSON error: {err}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(err: std::io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(err: serde_json::Error) -> Self {
+        ExportError::Json(err)
+    }
+}
+
+/// Triangles of all exported meshes sharing a material.
+#[derive(Default)]
+struct MergedPrimitive {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl MergedPrimitive {
+    /// Appends the triangles of the mesh, transformed by `transform`.
+    /// Meshes which aren't made of triangles are left out.
+    fn append(&mut self, mesh: &Mesh, transform: Affine3A) {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return;
+        }
+        let computed;
+        let mesh = if mesh.contains_attribute(Mesh::ATTRIBUTE_NORMAL) {
+            mesh
+        } else {
+            computed = mesh.clone().with_computed_normals();
+            &computed
+        };
+        let (
+            Some(VertexAttributeValues::Float32x3(positions)),
+            Some(VertexAttributeValues::Float32x3(normals)),
+        ) = (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+        )
+        else {
+            return;
+        };
+
+        let base = self.positions.len() as u32;
+        let normal_matrix = Mat3::from(transform.matrix3).inverse().transpose();
+        self.positions.extend(
+            positions
+                .iter()
+                .map(|position| transform.transform_point3(Vec3::from(*position)).to_array()),
+        );
+        self.normals.extend(normals.iter().map(|normal| {
+            (normal_matrix * Vec3::from(*normal))
+                .normalize_or_zero()
+                .to_array()
+        }));
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => self.uvs.extend(uvs),
+            _ => self
+                .uvs
+                .extend(std::iter::repeat_n([0.0; 2], positions.len())),
+        }
+        match mesh.indices() {
+            Some(indices) => self
+                .indices
+                .extend(indices.iter().map(|index| base + index as u32)),
+            None => self.indices.extend(base..base + positions.len() as u32),
+        }
+    }
+}
+
+type ExportMeshes<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Mesh3d,
+        &'static MeshMaterial3d<StandardMaterial>,
+        &'static GlobalTransform,
+        Option<&'static OriginalMesh>,
+    ),
+>;
+
+#[derive(SystemParam)]
+pub struct ExportAssets<'w> {
+    meshes: Res<'w, Assets<Mesh>>,
+    materials: Res<'w, Assets<StandardMaterial>>,
+    images: Res<'w, Assets<Image>>,
+}
+
+pub fn export_buildings(
+    mut events: EventReader<ExportBuildingsEvent>,
+    selection: Res<BuildingSelection>,
+    buildings: Query<&Transform, With<PlacedBuilding>>,
+    all_buildings: Query<Entity, With<PlacedBuilding>>,
+    children: Query<&Children>,
+    building_meshes: ExportMeshes,
+    assets: ExportAssets,
+) {
+    for event in events.read() {
+        let roots: Vec<Entity> = match event.scope {
+            ExportScope::Selection => selection.0.clone(),
+            ExportScope::Settlement => all_buildings.iter().collect(),
+        };
+        let result = group_anchor(roots.iter().filter_map(|root| {
+            buildings
+                .get(*root)
+                .ok()
+                .map(|transform| transform.translation)
+        }))
+        .ok_or(ExportError::Nothing)
+        .and_then(|anchor| {
+            // One primitive per material, in the order the materials are first met
+            let mut primitives: Vec<(Handle<StandardMaterial>, MergedPrimitive)> = Vec::new();
+            let mut primitive_of = HashMap::new();
+            let to_anchor = Affine3A::from_translation(-anchor);
+            for root in roots.iter().filter(|root| buildings.contains(**root)) {
+                for (mesh, material, transform, original) in std::iter::once(*root)
+                    .chain(children.iter_descendants(*root))
+                    .filter_map(|entity| building_meshes.get(entity).ok())
+                {
+                    // Far away buildings are drawn with simplified meshes, they export with the full ones
+                    let mesh = original.map_or(&mesh.0, |original| &original.0);
+                    let Some(mesh) = assets.meshes.get(mesh) else {
+                        continue;
+                    };
+                    let index = *primitive_of.entry(material.0.id()).or_insert_with(|| {
+                        primitives.push((material.0.clone(), MergedPrimitive::default()));
+                        primitives.len() - 1
+                    });
+                    primitives[index]
+                        .1
+                        .append(mesh, to_anchor * transform.affine());
+                }
+            }
+            write_glb(&event.name, &primitives, &assets)
+        });
+        match result {
+            Ok(path) => info!("Exported {:?} to {}", event.scope, path.display()),
+            Err(err) => error!("Failed to export {:?}: {err}", event.scope),
+        }
+    }
+}
+
+/// Writes the primitives as a binary glTF with its textures embedded,
+/// returning where it has been written to.
+fn write_glb(
+    name: &str,
+    primitives: &[(Handle<StandardMaterial>, MergedPrimitive)],
+    assets: &ExportAssets,
+) -> Result<PathBuf, ExportError> {
+    if !is_valid_file_stem(name) {
+        return Err(ExportError::InvalidName);
+    }
+    if primitives.is_empty() {
+        return Err(ExportError::Nothing);
+    }
+    let mut gltf = GltfBuilder::default();
+    let mut gltf_primitives = Vec::new();
+    let mut gltf_materials = Vec::new();
+    for (material, primitive) in primitives {
+        gltf_materials.push(
+            assets
+                .materials
+                .get(material)
+                .map(|material| gltf.material(material, &assets.images))
+                .unwrap_or_else(|| json!({})),
+        );
+        let (min, max) = primitive.positions.iter().fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), position| {
+                (
+                    min.min(Vec3::from(*position)),
+                    max.max(Vec3::from(*position)),
+                )
+            },
+        );
+        let position = gltf.accessor(primitive.positions.as_flattened(), "VEC3", Some((min, max)));
+        let normal = gltf.accessor(primitive.normals.as_flattened(), "VEC3", None);
+        let uv = gltf.accessor(primitive.uvs.as_flattened(), "VEC2", None);
+        let indices = gltf.indices(&primitive.indices);
+        gltf_primitives.push(json!({
+            "attributes": { "POSITION": position, "NORMAL": normal, "TEXCOORD_0": uv },
+            "indices": indices,
+            "material": gltf_materials.len() - 1,
+        }));
+    }
+
+    let mut root = json!({
+        "asset": { "version": "2.0", "generator": env!("CARGO_PKG_NAME") },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "name": name, "mesh": 0 }],
+        "meshes": [{ "name": name, "primitives": gltf_primitives }],
+        "materials": gltf_materials,
+        "accessors": gltf.accessors,
+        "bufferViews": gltf.buffer_views,
+        "buffers": [{ "byteLength": gltf.buffer.len() }],
+    });
+    if !gltf.images.is_empty() {
+        root["images"] = Value::from(gltf.images);
+        root["textures"] = Value::from(gltf.textures);
+    }
+    if !gltf.extensions_used.is_empty() {
+        root["extensionsUsed"] = json!(gltf.extensions_used);
+    }
+    // KTX2 textures have no fallback image, viewers without the extension can't show the file
+    if gltf.extensions_used.contains(KTX2_EXTENSION) {
+        root["extensionsRequired"] = json!([KTX2_EXTENSION]);
+    }
+
+    fs::create_dir_all(EXPORTS_DIR)?;
+    let path = PathBuf::from(EXPORTS_DIR).join(format!("{name}.glb"));
+    fs::write(&path, glb(serde_json::to_vec(&root)?, gltf.buffer))?;
+    Ok(path)
+}
+
+const KTX2_EXTENSION: &str = "KHR_texture_basisu";
+const EMISSIVE_STRENGTH_EXTENSION: &str = "KHR_materials_emissive_strength";
+
+/// The parts of the glTF JSON which refer to each other by index, and the binary buffer.
+#[derive(Default)]
+struct GltfBuilder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    images: Vec<Value>,
+    textures: Vec<Value>,
+    /// Texture index by image, so materials sharing a texture share it in the file as well.
+    texture_of: HashMap<AssetId<Image>, usize>,
+    extensions_used: BTreeSet<&'static str>,
+}
+
+impl GltfBuilder {
+    fn buffer_view(&mut self, bytes: impl Iterator<Item = [u8; 4]>, target: u32) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend(bytes.flatten());
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.buffer.len() - offset,
+            "target": target,
+        }));
+        self.buffer_views.len() - 1
+    }
+
+    /// Adds the file of an image, padded so the data after it stays aligned.
+    fn image_view(&mut self, bytes: &[u8]) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(bytes);
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": bytes.len(),
+        }));
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+        self.buffer_views.len() - 1
+    }
+
+    /// Adds float vertex data with `kind` ("VEC2", "VEC3") components per vertex.
+    fn accessor(&mut self, values: &[f32], kind: &str, bounds: Option<(Vec3, Vec3)>) -> usize {
+        let components = if kind == "VEC2" { 2 } else { 3 };
+        let view = self.buffer_view(values.iter().map(|value| value.to_le_bytes()), ARRAY_BUFFER);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": kind,
+        });
+        if let Some((min, max)) = bounds {
+            accessor["min"] = json!(min.to_array());
+            accessor["max"] = json!(max.to_array());
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn indices(&mut self, indices: &[u32]) -> usize {
+        let view = self.buffer_view(
+            indices.iter().map(|index| index.to_le_bytes()),
+            ELEMENT_ARRAY_BUFFER,
+        );
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn material(&mut self, material: &StandardMaterial, images: &Assets<Image>) -> Value {
+        let base_color = material.base_color.to_linear();
+        let mut pbr = json!({
+            "baseColorFactor": base_color.to_f32_array(),
+            "metallicFactor": material.metallic,
+            "roughnessFactor": material.perceptual_roughness,
+        });
+        if let Some(index) = self.texture(&material.base_color_texture, images) {
+            pbr["baseColorTexture"] = json!({ "index": index });
+        }
+        if let Some(index) = self.texture(&material.metallic_roughness_texture, images) {
+            pbr["metallicRoughnessTexture"] = json!({ "index": index });
+        }
+        let mut gltf_material = json!({
+            "pbrMetallicRoughness": pbr,
+            "doubleSided": material.double_sided,
+        });
+        if let Some(index) = self.texture(&material.normal_map_texture, images) {
+            gltf_material["normalTexture"] = json!({ "index": index });
+        }
+        if let Some(index) = self.texture(&material.occlusion_texture, images) {
+            gltf_material["occlusionTexture"] = json!({ "index": index });
+        }
+
+        // Emissive factors are at most 1 in glTF, brighter glows need the strength extension
+        let emissive = material.emissive.to_vec3();
+        let strength = emissive.max_element();
+        if strength > 1.0 {
+            gltf_material["emissiveFactor"] = json!((emissive / strength).to_array());
+            gltf_material["extensions"] =
+                json!({ EMISSIVE_STRENGTH_EXTENSION: { "emissiveStrength": strength } });
+            self.extensions_used.insert(EMISSIVE_STRENGTH_EXTENSION);
+        } else if strength > 0.0 {
+            gltf_material["emissiveFactor"] = json!(emissive.to_array());
+        }
+
+        match material.alpha_mode {
+            AlphaMode::Opaque => {}
+            AlphaMode::Mask(cutoff) => {
+                gltf_material["alphaMode"] = json!("MASK");
+                gltf_material["alphaCutoff"] = json!(cutoff);
+            }
+            _ => gltf_material["alphaMode"] = json!("BLEND"),
+        }
+        gltf_material
+    }
+
+    /// Index of the texture embedding the image in the file. `None` for images which can't be exported.
+    fn texture(&mut self, image: &Option<Handle<Image>>, images: &Assets<Image>) -> Option<usize> {
+        let image = image.as_ref()?;
+        if let Some(index) = self.texture_of.get(&image.id()) {
+            return Some(*index);
+        }
+        let (bytes, mime_type) = asset_file(image).or_else(|| encode_png(images.get(image)?))?;
+
+        let view = self.image_view(&bytes);
+        self.images
+            .push(json!({ "bufferView": view, "mimeType": mime_type }));
+        let source = self.images.len() - 1;
+        self.textures.push(if mime_type == "image/ktx2" {
+            self.extensions_used.insert(KTX2_EXTENSION);
+            json!({ "extensions": { KTX2_EXTENSION: { "source": source } } })
+        } else {
+            json!({ "source": source })
+        });
+        let index = self.textures.len() - 1;
+        self.texture_of.insert(image.id(), index);
+        Some(index)
+    }
+}
+
+/// The file in the assets the image was loaded from, with its MIME type, if it can be embedded as it is.
+/// KTX2 files only can if they are Basis Universal compressed, the only KTX2 the extension allows.
+fn asset_file(image: &Handle<Image>) -> Option<(Vec<u8>, &'static str)> {
+    let path = image.path()?;
+    if path.label().is_some() {
+        return None;
+    }
+    let extension = path.get_full_extension()?.to_lowercase();
+    let mime_type = match extension.as_str() {
+        "ktx2" => "image/ktx2",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        _ => return None,
+    };
+    let bytes = fs::read(
+        FileAssetReader::get_base_path()
+            .join("assets")
+            .join(path.path()),
+    )
+    .ok()?;
+    if mime_type == "image/ktx2" && !is_basis_ktx2(&bytes) {
+        return None;
+    }
+    Some((bytes, mime_type))
+}
+
+/// Basis Universal KTX2 files leave the Vulkan format undefined, it depends on what they are transcoded to.
+fn is_basis_ktx2(bytes: &[u8]) -> bool {
+    const IDENTIFIER: &[u8; 12] = b"\xABKTX 20\xBB\r\n\x1A\n";
+    bytes.starts_with(IDENTIFIER)
+        && bytes
+            .get(12..16)
+            .is_some_and(|vk_format| vk_format == [0; 4])
+}
+
+/// Encodes the full size level of an uncompressed 8 bit RGBA image to PNG.
+/// `None` for other formats, e.g. GPU compressed textures.
+fn encode_png(image: &Image) -> Option<(Vec<u8>, &'static str)> {
+    if !matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    ) {
+        return None;
+    }
+    let (width, height) = (image.width(), image.height());
+    let level = image.data.get(..(width * height * 4) as usize)?;
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(level, width, height, ExtendedColorType::Rgba8)
+        .ok()?;
+    Some((png, "image/png"))
+}
+
+/// Packs JSON and binary buffer into a GLB container, see the "GLB File Format Specification".
+fn glb(mut json: Vec<u8>, mut bin: Vec<u8>) -> Vec<u8> {
+    const MAGIC: &[u8; 4] = b"glTF";
+    const VERSION: u32 = 2;
+    const JSON_CHUNK: &[u8; 4] = b"JSON";
+    const BIN_CHUNK: &[u8; 4] = b"BIN\0";
+    // Chunks are 4 byte aligned, JSON padded with spaces and binary data with zeros
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(MAGIC);
+    glb.extend_from_slice(&VERSION.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    for (kind, data) in [(JSON_CHUNK, json), (BIN_CHUNK, bin)] {
+        glb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        glb.extend_from_slice(kind);
+        glb.extend_from_slice(&data);
+    }
+    glb
+}
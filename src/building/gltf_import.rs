@@ -0,0 +1,165 @@
+use super::building_assets::{BuildingAssets, BuildingAssetsInitBridge, BuildingAssetsPack};
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory inside of the assets imported glTFs are copied to, one subdirectory per building
+/// named after it. They are loaded as custom buildings on every start.
+pub const IMPORTED_DIR: &str = "imported";
+
+/// Imports the glTF (`.gltf` or `.glb`) at the path as a custom building named after the file.
+#[derive(Event)]
+pub struct ImportBuildingEvent(pub PathBuf);
+
+#[derive(Debug)]
+pub enum ImportError {
+    UnsupportedFormat,
+    NameTaken(String),
+    /// A `.gltf` refers to a file outside of its directory.
+    OutsideFile(String),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::UnsupportedFormat => write!(f, "not a .gltf or .glb file"),
+            ImportError::NameTaken(name) => write!(f, "a building named \"{name}\" exists already"),
+            ImportError::OutsideFile(uri) => {
+                write!(f, "refers to \"{uri}\" outside of its directory")
+            }
+            ImportError::Io(err) => write!(f, "I/O error: {err}"),
+            ImportError::Json(err) => write!(f, "damaged glTF: {err}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(err: std::io::Error) -> Self {
+        ImportError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(err: serde_json::Error) -> Self {
+        ImportError::Json(err)
+    }
+}
+
+pub fn imported_dir() -> PathBuf {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(IMPORTED_DIR)
+}
+
+/// Whether the path is a glTF file the building can be imported from.
+pub fn is_gltf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| matches!(extension.to_lowercase().as_str(), "gltf" | "glb"))
+}
+
+pub fn import_buildings(
+    mut events: EventReader<ImportBuildingEvent>,
+    mut building_assets: ResMut<BuildingAssets>,
+    mut bridge: BuildingAssetsInitBridge,
+) {
+    for ImportBuildingEvent(source) in events.read() {
+        let result = copy_into_assets(source, &building_assets).map(|(name, file_name)| {
+            let pack = BuildingAssetsPack::imported(&mut bridge, &name, &file_name);
+            building_assets.custom.0.push(pack);
+            name
+        });
+        match result {
+            Ok(name) => info!("Imported {} as building \"{name}\"", source.display()),
+            Err(err) => error!("Failed to import {}: {err}", source.display()),
+        }
+    }
+}
+
+/// Copies the glTF, and the buffers and images a `.gltf` refers to, to its directory in the assets.
+/// Returns the name of the building and the file name of its glTF.
+fn copy_into_assets(
+    source: &Path,
+    building_assets: &BuildingAssets,
+) -> Result<(String, String), ImportError> {
+    let (Some(name), Some(file_name)) = (
+        source.file_stem().and_then(|stem| stem.to_str()),
+        source.file_name().and_then(|file_name| file_name.to_str()),
+    ) else {
+        return Err(ImportError::UnsupportedFormat);
+    };
+    if !is_gltf(source) {
+        return Err(ImportError::UnsupportedFormat);
+    }
+    if building_assets.get(name).is_some() {
+        return Err(ImportError::NameTaken(name.to_string()));
+    }
+
+    let mut files = vec![PathBuf::from(file_name)];
+    if !file_name.to_lowercase().ends_with(".glb") {
+        files.extend(external_files(&fs::read_to_string(source)?)?);
+    }
+    let source_dir = source.parent().unwrap_or(Path::new(""));
+    let target_dir = imported_dir().join(name);
+    for file in files {
+        let target = target_dir.join(&file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source_dir.join(&file), target)?;
+    }
+    Ok((name.to_string(), file_name.to_string()))
+}
+
+/// Files next to a `.gltf` its buffers and images are loaded from.
+fn external_files(gltf: &str) -> Result<Vec<PathBuf>, ImportError> {
+    let json: serde_json::Value = serde_json::from_str(gltf)?;
+    let uris = ["buffers", "images"]
+        .into_iter()
+        .filter_map(|key| json[key].as_array())
+        .flatten()
+        .filter_map(|item| item["uri"].as_str())
+        // Data URIs are embedded in the file
+        .filter(|uri| !uri.starts_with("data:"));
+
+    let mut files = Vec::new();
+    for uri in uris {
+        let file = PathBuf::from(decode_uri(uri));
+        if !file
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ImportError::OutsideFile(uri.to_string()));
+        }
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Undoes the percent-encoding of URIs, e.g. "%20" for spaces.
+fn decode_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,150 @@
+use super::building_assets::PreviewBuildingHandle;
+use super::building_tier::BuildingTier;
+use super::foundation::FoundationPillar;
+use super::PreviewBuilding;
+use crate::material_library::MaterialLibrary;
+use crate::settings::GameSettings;
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+
+/// Material from the `MaterialLibrary` replacing the materials of a building's scene, as the player picked it.
+/// `None` keeps the materials the glTF ships with. The material of a `BuildingTier` takes precedence.
+#[derive(Component, Clone, Default)]
+pub struct MaterialVariant(pub Option<String>);
+
+/// The material a mesh had before a variant replaced it.
+#[derive(Component)]
+pub struct OriginalMaterial(Handle<StandardMaterial>);
+
+/// Meshes of building scenes whose material a variant can replace. Pillars keep their own.
+type VariantMeshes<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut MeshMaterial3d<StandardMaterial>,
+        Option<&'static OriginalMaterial>,
+    ),
+    Without<FoundationPillar>,
+>;
+
+/// Switches the preview to the next material variant of the selected building.
+pub fn cycle_material_variant(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut preview_building_handle: ResMut<PreviewBuildingHandle>,
+    mut preview_variant: Single<&mut MaterialVariant, With<PreviewBuilding>>,
+) {
+    if !keys.just_pressed(game_settings.keyboard.cycle_material) {
+        return;
+    }
+    let variants = &preview_building_handle.variants;
+    // The glTF material comes after the last variant
+    let next = match &preview_building_handle.material {
+        None => variants.first().cloned(),
+        Some(current) => variants
+            .iter()
+            .position(|variant| variant == current)
+            .and_then(|index| variants.get(index + 1))
+            .cloned(),
+    };
+    preview_building_handle.material = next.clone();
+    preview_variant.0 = next;
+}
+
+/// Buildings whose variant or tier changed.
+type ChangedVariants<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static MaterialVariant,
+        Option<&'static BuildingTier>,
+    ),
+    Or<(Changed<MaterialVariant>, Changed<BuildingTier>)>,
+>;
+
+/// Applies changed variants and tiers to buildings whose scene is already spawned.
+pub fn apply_changed_material_variants(
+    changed: ChangedVariants,
+    children: Query<&Children>,
+    mut meshes: VariantMeshes,
+    material_library: Res<MaterialLibrary>,
+    mut commands: Commands,
+) {
+    for (entity, variant, tier) in changed.iter() {
+        apply_material_variant(
+            entity,
+            shown_material(variant, tier),
+            &children,
+            &mut meshes,
+            &material_library,
+            &mut commands,
+        );
+    }
+}
+
+/// Applies the variant once the building's scene has been spawned.
+pub fn apply_material_variant_on_scene_ready(
+    trigger: Trigger<SceneInstanceReady>,
+    variants: Query<(&MaterialVariant, Option<&BuildingTier>)>,
+    children: Query<&Children>,
+    mut meshes: VariantMeshes,
+    material_library: Res<MaterialLibrary>,
+    mut commands: Commands,
+) {
+    let entity = trigger.entity();
+    if let Ok((variant, tier)) = variants.get(entity) {
+        apply_material_variant(
+            entity,
+            shown_material(variant, tier),
+            &children,
+            &mut meshes,
+            &material_library,
+            &mut commands,
+        );
+    }
+}
+
+/// The material of the building's tier, or else its variant.
+fn shown_material(variant: &MaterialVariant, tier: Option<&BuildingTier>) -> Option<String> {
+    tier.and_then(|tier| tier.material())
+        .or_else(|| variant.0.clone())
+}
+
+fn apply_material_variant(
+    root: Entity,
+    material_name: Option<String>,
+    children: &Query<&Children>,
+    meshes: &mut VariantMeshes,
+    material_library: &MaterialLibrary,
+    commands: &mut Commands,
+) {
+    let replacement = material_name.and_then(|name| {
+        let material = material_library.get(&name);
+        if material.is_none() {
+            warn!("Material \"{name}\" not found in the material library");
+        }
+        material
+    });
+
+    for entity in children.iter_descendants(root) {
+        let Ok((mut material, original)) = meshes.get_mut(entity) else {
+            continue;
+        };
+        match (&replacement, original) {
+            (Some(replacement), original) => {
+                if original.is_none() {
+                    commands
+                        .entity(entity)
+                        .insert(OriginalMaterial(material.0.clone()));
+                }
+                material.0 = replacement.clone();
+            }
+            (None, Some(original)) => {
+                material.0 = original.0.clone();
+                commands.entity(entity).remove::<OriginalMaterial>();
+            }
+            (None, None) => {}
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::utils::HashMap;
+
+/// Simplifies a mesh by merging all vertices within each cell of a grid laid over its bounds,
+/// with `cells` cells along the longest side. Triangles collapsing into a line are dropped.
+/// Vertices on either side of a UV seam stay apart, so the texture doesn't smear across it.
+/// `None` if the mesh isn't a triangle list or has no `Float32x3` positions,
+/// meshes without indices are simplified as if each vertex had its own index.
+pub fn simplify_mesh(mesh: &Mesh, cells: u32) -> Option<Mesh> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+    let normals = mesh
+        .attribute(Mesh::ATTRIBUTE_NORMAL)
+        .and_then(VertexAttributeValues::as_float3);
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => Some(uvs),
+        _ => None,
+    };
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(indices) => indices.iter().map(|index| index as u32).collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    let min = positions
+        .iter()
+        .fold(Vec3::INFINITY, |min, p| min.min(Vec3::from_array(*p)));
+    let max = positions
+        .iter()
+        .fold(Vec3::NEG_INFINITY, |max, p| max.max(Vec3::from_array(*p)));
+    let cell_size = (max - min).max_element().max(f32::EPSILON) / cells.max(1) as f32;
+    // UVs get a grid as fine as the positions. At a seam the UVs jump,
+    // so the vertices on either side fall into different UV cells
+    let (uv_min, uv_cell_size) = match uvs {
+        Some(uvs) => {
+            let uv_min = uvs
+                .iter()
+                .fold(Vec2::INFINITY, |min, uv| min.min(Vec2::from_array(*uv)));
+            let uv_max = uvs
+                .iter()
+                .fold(Vec2::NEG_INFINITY, |max, uv| max.max(Vec2::from_array(*uv)));
+            let size = (uv_max - uv_min).max_element().max(f32::EPSILON) / cells.max(1) as f32;
+            (uv_min, size)
+        }
+        None => (Vec2::ZERO, 1.0),
+    };
+
+    // Sums of the attributes of the merged vertices, averaged below
+    let mut cell_vertices: HashMap<(IVec3, IVec2), u32> = HashMap::default();
+    let mut position_sums: Vec<Vec3> = Vec::new();
+    let mut normal_sums: Vec<Vec3> = Vec::new();
+    let mut uv_sums: Vec<Vec2> = Vec::new();
+    let mut counts: Vec<f32> = Vec::new();
+    let remap: Vec<u32> = positions
+        .iter()
+        .enumerate()
+        .map(|(i, position)| {
+            let position = Vec3::from_array(*position);
+            let cell = ((position - min) / cell_size).floor().as_ivec3();
+            let uv_cell = uvs.map_or(IVec2::ZERO, |uvs| {
+                ((Vec2::from_array(uvs[i]) - uv_min) / uv_cell_size)
+                    .floor()
+                    .as_ivec2()
+            });
+            let vertex = *cell_vertices.entry((cell, uv_cell)).or_insert_with(|| {
+                position_sums.push(Vec3::ZERO);
+                normal_sums.push(Vec3::ZERO);
+                uv_sums.push(Vec2::ZERO);
+                counts.push(0.0);
+                counts.len() as u32 - 1
+            }) as usize;
+            position_sums[vertex] += position;
+            if let Some(normals) = normals {
+                normal_sums[vertex] += Vec3::from_array(normals[i]);
+            }
+            if let Some(uvs) = uvs {
+                uv_sums[vertex] += Vec2::from_array(uvs[i]);
+            }
+            counts[vertex] += 1.0;
+            vertex as u32
+        })
+        .collect();
+
+    let simplified_indices: Vec<u32> = indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            triangle
+                .iter()
+                .map(|&i| remap[i as usize])
+                .collect::<Vec<_>>()
+        })
+        .filter(|t| t[0] != t[1] && t[1] != t[2] && t[0] != t[2])
+        .flatten()
+        .collect();
+
+    let mut simplified = Mesh::new(PrimitiveTopology::TriangleList, mesh.asset_usage)
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            position_sums
+                .iter()
+                .zip(&counts)
+                .map(|(sum, count)| *sum / *count)
+                .collect::<Vec<Vec3>>(),
+        )
+        .with_inserted_indices(Indices::U32(simplified_indices));
+    if normals.is_some() {
+        simplified.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            normal_sums
+                .iter()
+                .map(|sum| sum.normalize_or(Vec3::Y))
+                .collect::<Vec<Vec3>>(),
+        );
+    }
+    if uvs.is_some() {
+        simplified.insert_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            uv_sums
+                .iter()
+                .zip(&counts)
+                .map(|(sum, count)| *sum / *count)
+                .collect::<Vec<Vec2>>(),
+        );
+    }
+    // Normal mapped materials need tangents, meshes without UVs simply don't get them
+    if mesh.attribute(Mesh::ATTRIBUTE_TANGENT).is_some() {
+        let _ = simplified.generate_tangents();
+    }
+    Some(simplified)
+}
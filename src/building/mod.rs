@@ -1,14 +1,64 @@
+mod blueprint;
 mod building;
 mod building_assets;
+mod building_cost;
+mod building_light;
+mod building_lod;
 mod building_menu;
+mod building_tier;
+mod demolish;
+mod foundation;
+mod gltf_export;
+mod gltf_import;
+mod material_variant;
+mod mesh_simplify;
+mod opening;
+mod placement;
+mod scene_flatten;
+mod selection;
 
+use crate::keyboard_focus::shortcuts_enabled;
+use crate::settings::GameSettings;
+use bevy::input::common_conditions::input_just_pressed;
 use bevy::prelude::*;
+use blueprint::{save_selection_blueprint, Blueprints, SaveBlueprintEvent};
 use building::prelude::*;
-use building_assets::{BuildingAssets, BuildingAssetsInitBridge, PreviewBuildingHandle};
-use building_menu::{building_menu, enter_building_menu, exit_building_menu};
+use building_assets::{BuildingAssetsInitBridge, PreviewBuildingHandle};
+use building_cost::{block_unaffordable_placement, refund_collapsed_buildings};
+use building_light::{apply_light_shadow_budget, spawn_building_lights};
+use building_lod::{apply_building_lod_on_scene_ready, update_building_lods, SimplifiedMeshes};
+use building_menu::{building_menu, enter_building_menu, exit_building_menu, import_export_menu};
+use building_tier::{
+    clear_upgrade_history, undo_building_upgrade, upgrade_targeted_building, UpgradeHistory,
+};
+use demolish::demolish_targeted_building;
+use foundation::{
+    level_preview_foundation, spawn_foundation_pillars, FoundationLeveling, PillarAssets,
+};
+use gltf_export::{export_buildings, ExportBuildingsEvent};
+use gltf_import::{import_buildings, ImportBuildingEvent};
+use material_variant::{
+    apply_changed_material_variants, apply_material_variant_on_scene_ready, cycle_material_variant,
+};
+use opening::{
+    interact_with_openings, opening_prompt_ui, prepare_opening_on_scene_ready, TargetedOpening,
+};
+use placement::{adjust_placement, placement_hud, reset_preview_nudge, PreviewNudge};
+use scene_flatten::{flatten_building_scenes, measure_buildings, register_building_diagnostics};
+use selection::{
+    apply_selection_actions, highlight_selection, select_buildings, selection_menu,
+    BuildingSelection, SelectionAction,
+};
+
+pub use building::spawn_placed_building;
+pub use building_assets::BuildingAssets;
+pub use building_tier::BuildingTier;
+pub use demolish::CollapseBuildingEvent;
+pub use material_variant::MaterialVariant;
+pub use opening::Openable;
 
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
-enum BuildingReadinessState {
+pub enum BuildingReadinessState {
     #[default]
     Loading,
     Ready,
@@ -20,16 +70,32 @@ pub enum BuildingMode {
     Disabled,
     Menu,
     Building,
+    /// Selecting placed buildings to move, copy, rotate or delete them.
+    Selection,
 }
 
 #[derive(Resource)]
 struct BuildingSettings {
+    /// One of `placement::GRID_SIZES`.
     grid_size: f32,
+    /// Whether the preview snaps to the grid.
+    snapping: bool,
+    /// Degrees the preview turns per scroll step.
+    rotation_step: f32,
+    foundation_leveling: FoundationLeveling,
+    /// Steepest ground a foundation can be placed on, in degrees.
+    max_foundation_slope: f32,
 }
 
 impl Default for BuildingSettings {
     fn default() -> Self {
-        Self { grid_size: 0.1 }
+        Self {
+            grid_size: 0.1,
+            snapping: true,
+            rotation_step: 15.0,
+            foundation_leveling: FoundationLeveling::Pillars,
+            max_foundation_slope: 30.0,
+        }
     }
 }
 
@@ -40,28 +106,122 @@ impl Plugin for BuildingPlugin {
             .init_state::<BuildingMode>()
             .init_resource::<BuildingSettings>()
             .init_resource::<PreviewBuildingHandle>()
+            .init_resource::<PillarAssets>()
+            .init_resource::<SimplifiedMeshes>()
+            .init_resource::<BuildingSelection>()
+            .init_resource::<Blueprints>()
+            .init_resource::<UpgradeHistory>()
+            .init_resource::<TargetedOpening>()
+            .init_resource::<PreviewNudge>()
             .add_event::<ChangeBuildingModeEvent>()
+            .add_event::<CollapseBuildingEvent>()
+            .add_event::<SelectionAction>()
+            .add_event::<SaveBlueprintEvent>()
+            .add_event::<ImportBuildingEvent>()
+            .add_event::<ExportBuildingsEvent>()
             .add_systems(
                 OnEnter(BuildingReadinessState::Loading),
                 load_building_assets,
             )
             .add_systems(
                 Update,
-                building_watchdog_system.run_if(in_state(BuildingReadinessState::Ready)),
+                (
+                    building_watchdog_system,
+                    building_mode_shortcuts.run_if(shortcuts_enabled),
+                    spawn_foundation_pillars,
+                    spawn_building_lights,
+                    apply_light_shadow_budget,
+                    apply_changed_material_variants,
+                    clear_upgrade_history,
+                    update_building_lods,
+                    flatten_building_scenes,
+                    measure_buildings,
+                    import_buildings,
+                    export_buildings,
+                )
+                    .run_if(in_state(BuildingReadinessState::Ready)),
+            )
+            .add_observer(apply_material_variant_on_scene_ready)
+            .add_observer(apply_building_lod_on_scene_ready)
+            .add_observer(prepare_opening_on_scene_ready)
+            // Doors and windows are opened while walking around, not while building
+            .add_systems(
+                Update,
+                (
+                    interact_with_openings.run_if(shortcuts_enabled),
+                    opening_prompt_ui,
+                )
+                    .chain()
+                    .run_if(in_state(BuildingMode::Disabled)),
             )
             // ---------- Menu Mode
             .add_systems(OnEnter(BuildingMode::Menu), enter_building_menu)
-            .add_systems(Update, building_menu.run_if(in_state(BuildingMode::Menu)))
+            // Paths and names are typed into the import and export fields here, the build mode
+            // keys wait meanwhile, see `building_mode_shortcuts`
+            .add_systems(
+                Update,
+                (building_menu, import_export_menu).run_if(in_state(BuildingMode::Menu)),
+            )
             .add_systems(OnExit(BuildingMode::Menu), exit_building_menu)
             // ---------- Building Mode
-            .add_systems(OnEnter(BuildingMode::Building), enter_building_mode)
+            .add_systems(
+                OnEnter(BuildingMode::Building),
+                (enter_building_mode, reset_preview_nudge),
+            )
             .add_systems(
                 Update,
-                (building_system, update_preview_building_position)
+                (
+                    cycle_material_variant.run_if(shortcuts_enabled),
+                    adjust_placement.run_if(shortcuts_enabled),
+                    building_system.run_if(input_just_pressed(MouseButton::Left)),
+                    update_preview_building_position,
+                    level_preview_foundation,
+                    block_placement_in_unloaded_chunks,
+                    block_unaffordable_placement,
+                )
                     .chain()
                     .run_if(in_state(BuildingMode::Building)),
             )
-            .add_systems(OnExit(BuildingMode::Building), exit_building_mode);
+            .add_systems(
+                Update,
+                (
+                    (
+                        upgrade_targeted_building,
+                        undo_building_upgrade,
+                        demolish_targeted_building,
+                    )
+                        .chain()
+                        .run_if(in_state(BuildingMode::Building).and(shortcuts_enabled)),
+                    // Before the collapsed buildings are gone
+                    refund_collapsed_buildings,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                placement_hud.run_if(in_state(BuildingMode::Building)),
+            )
+            .add_systems(OnExit(BuildingMode::Building), exit_building_mode)
+            // ---------- Selection Mode
+            // Like the menu, selecting needs the cursor and a still camera
+            .add_systems(OnEnter(BuildingMode::Selection), enter_building_menu)
+            .add_systems(
+                Update,
+                (
+                    selection_menu,
+                    save_selection_blueprint,
+                    select_buildings,
+                    highlight_selection,
+                    apply_selection_actions,
+                )
+                    .chain()
+                    .run_if(in_state(BuildingMode::Selection)),
+            )
+            .add_systems(OnExit(BuildingMode::Selection), exit_building_menu);
+        register_building_diagnostics(app);
+        // With physics, collapsed buildings turn into debris instead
+        #[cfg(not(feature = "physics"))]
+        app.add_systems(Update, demolish::remove_collapsed_buildings);
     }
 }
 
@@ -73,6 +233,7 @@ fn load_building_assets(
     bridge: BuildingAssetsInitBridge,
 ) {
     commands.insert_resource(BuildingAssets::load_all(bridge));
+    commands.insert_resource(Blueprints::read_all());
     // wait while resources loading !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
     building_readiness_state.set(BuildingReadinessState::Ready);
     info!("BuildingReadinessState::Ready");
@@ -85,7 +246,6 @@ struct ChangeBuildingModeEvent(BuildingMode);
 fn building_watchdog_system(
     mut ev_switch_mode: EventReader<ChangeBuildingModeEvent>,
     mut building_mode_state: ResMut<NextState<BuildingMode>>,
-    keys: Res<ButtonInput<KeyCode>>, // for debug only !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
 ) {
     for ev in ev_switch_mode.read() {
         info!("Changing building mode: {:?}", ev.0);
@@ -93,22 +253,43 @@ fn building_watchdog_system(
             BuildingMode::Menu => building_mode_state.set(BuildingMode::Menu),
             BuildingMode::Building => building_mode_state.set(BuildingMode::Building),
             BuildingMode::Disabled => building_mode_state.set(BuildingMode::Disabled),
+            BuildingMode::Selection => building_mode_state.set(BuildingMode::Selection),
         }
     }
+}
 
-    // for debug only !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
-    if keys.just_pressed(KeyCode::KeyB) {
+/// Switches the construction mode with the keys of the settings.
+fn building_mode_shortcuts(
+    mut building_mode_state: ResMut<NextState<BuildingMode>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+) {
+    let keyboard = &game_settings.keyboard;
+    if keys.just_pressed(keyboard.start_building) {
         building_mode_state.set(BuildingMode::Menu);
-        info!("Changing building mode: Building");
-    } else if keys.just_pressed(KeyCode::KeyN) {
+        info!("Changing building mode: Menu");
+    } else if keys.just_pressed(keyboard.stop_building) {
         building_mode_state.set(BuildingMode::Disabled);
         info!("Changing building mode: Disabled");
+    } else if keys.just_pressed(keyboard.select_buildings) {
+        building_mode_state.set(BuildingMode::Selection);
+        info!("Changing building mode: Selection");
     }
 }
 
 #[derive(Component)]
 struct PreviewBuilding;
 
+/// The preview can't be placed where it is, e.g. because the ground is too steep.
+#[derive(Component)]
+struct PlacementBlocked;
+
+/// A building placed in the world. `name` refers to the `BuildingAssetsPack` it was spawned from.
+#[derive(Component)]
+pub struct PlacedBuilding {
+    pub name: String,
+}
+
 trait RoundToStep {
     fn round_to_step(self, step: f32) -> Self;
 }
@@ -0,0 +1,181 @@
+use super::building_assets::BuildingAssets;
+use super::demolish::TargetedBuilding;
+use super::PlacedBuilding;
+use crate::settings::GameSettings;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use bevy_egui::{egui, EguiContexts};
+use std::fmt::{Display, Formatter};
+
+/// Farthest a door or window can be opened from, in meters.
+const INTERACT_DISTANCE: f32 = 4.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OpeningKind {
+    Door,
+    Hatch,
+    Shutters,
+}
+
+impl Display for OpeningKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpeningKind::Door => write!(f, "door"),
+            OpeningKind::Hatch => write!(f, "hatch"),
+            OpeningKind::Shutters => write!(f, "shutters"),
+        }
+    }
+}
+
+/// The animation of its glTF which opens a piece, played backwards to close it.
+/// Openings are only animated from glTFs, which name the hinges their leaves turn on.
+pub struct OpeningAnimation {
+    pub kind: OpeningKind,
+    pub clip: Handle<AnimationClip>,
+    pub graph: Handle<AnimationGraph>,
+    pub node: AnimationNodeIndex,
+}
+
+/// Whether a placed door, hatch or window is open. Stored with the world.
+#[derive(Component, Clone, Copy, Default)]
+pub struct Openable {
+    pub open: bool,
+}
+
+/// The animation player in the scene of an openable piece.
+#[derive(Component)]
+pub struct OpeningPlayer {
+    kind: OpeningKind,
+    player: Entity,
+    node: AnimationNodeIndex,
+    clip: Handle<AnimationClip>,
+}
+
+/// Makes placed pieces with an opening animation openable once their scene has been spawned,
+/// and poses them open or closed. Pieces loaded from a save keep the state they were saved in.
+pub fn prepare_opening_on_scene_ready(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    buildings: Query<(&PlacedBuilding, Option<&Openable>)>,
+    building_assets: Res<BuildingAssets>,
+    children: Query<&Children>,
+    mut players: Query<&mut AnimationPlayer>,
+    clips: Res<Assets<AnimationClip>>,
+) {
+    let entity = trigger.entity();
+    let Ok((building, openable)) = buildings.get(entity) else {
+        return;
+    };
+    let Some(opening) = building_assets
+        .get(&building.name)
+        .and_then(|pack| pack.opening.as_ref())
+    else {
+        return;
+    };
+    let Some(player) = children
+        .iter_descendants(entity)
+        .find(|descendant| players.contains(*descendant))
+    else {
+        warn!("\"{}\" has no animation player to open it", building.name);
+        return;
+    };
+    let open = openable.is_some_and(|openable| openable.open);
+    let duration = clips
+        .get(&opening.clip)
+        .map_or(0.0, AnimationClip::duration);
+    if let Ok(mut player) = players.get_mut(player) {
+        player
+            .play(opening.node)
+            .set_seek_time(if open { duration } else { 0.0 })
+            .pause();
+    }
+    commands
+        .entity(player)
+        .insert(AnimationGraphHandle(opening.graph.clone()));
+    commands.entity(entity).insert((
+        Openable { open },
+        OpeningPlayer {
+            kind: opening.kind,
+            player,
+            node: opening.node,
+            clip: opening.clip.clone(),
+        },
+    ));
+}
+
+/// The openable piece in the middle of the view, close enough to be opened.
+#[derive(Resource, Default)]
+pub struct TargetedOpening(Option<Entity>);
+
+/// Animation players and clips of the openable pieces.
+#[derive(SystemParam)]
+pub struct OpeningAnimations<'w, 's> {
+    pieces: Query<'w, 's, (&'static mut Openable, &'static OpeningPlayer)>,
+    players: Query<'w, 's, &'static mut AnimationPlayer>,
+    clips: Res<'w, Assets<AnimationClip>>,
+}
+
+impl OpeningAnimations<'_, '_> {
+    /// Opens the piece if it's closed and closes it if it's open, from wherever its animation is.
+    fn toggle(&mut self, entity: Entity) {
+        let Ok((mut openable, opening)) = self.pieces.get_mut(entity) else {
+            return;
+        };
+        let Ok(mut player) = self.players.get_mut(opening.player) else {
+            return;
+        };
+        openable.open = !openable.open;
+        let duration = self
+            .clips
+            .get(&opening.clip)
+            .map_or(0.0, AnimationClip::duration);
+        let animation = player.play(opening.node);
+        let seek_time = animation.seek_time().clamp(0.0, duration);
+        animation.replay();
+        animation
+            .set_seek_time(seek_time)
+            .set_speed(if openable.open { 1.0 } else { -1.0 })
+            .resume();
+    }
+}
+
+/// Opens or closes the door, hatch or window in the middle of the view when the interact key is pressed.
+pub fn interact_with_openings(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut target: TargetedBuilding,
+    mut targeted: ResMut<TargetedOpening>,
+    mut animations: OpeningAnimations,
+) {
+    targeted.0 = target
+        .building_within(INTERACT_DISTANCE)
+        .filter(|building| animations.pieces.contains(*building));
+    if let Some(building) = targeted.0 {
+        if keys.just_pressed(game_settings.keyboard.interact) {
+            animations.toggle(building);
+        }
+    }
+}
+
+/// Tells which key opens or closes the targeted piece.
+pub fn opening_prompt_ui(
+    mut contexts: EguiContexts,
+    targeted: Res<TargetedOpening>,
+    openables: Query<(&Openable, &OpeningPlayer)>,
+    game_settings: Res<GameSettings>,
+) {
+    let Some((openable, opening)) = targeted.0.and_then(|entity| openables.get(entity).ok()) else {
+        return;
+    };
+    let action = if openable.open { "close" } else { "open" };
+    // Below the gathering hint, in case both are shown
+    egui::Area::new(egui::Id::new("opening_prompt"))
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 90.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "Press {:?} to {action} the {}",
+                game_settings.keyboard.interact, opening.kind
+            ));
+        });
+}
@@ -0,0 +1,128 @@
+use super::building_assets::PreviewBuildingHandle;
+use super::BuildingSettings;
+use crate::settings::GameSettings;
+use crate::universal_camera_controller::UniCamController;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+/// Grid sizes the grid cycles through, in meters.
+pub const GRID_SIZES: [f32; 5] = [0.1, 0.25, 0.5, 1.0, 2.0];
+
+/// Offset of the preview from where the camera points, moved in grid steps by the nudge keys.
+/// Cleared whenever the building mode is entered.
+#[derive(Resource, Default)]
+pub struct PreviewNudge(pub Vec3);
+
+pub fn reset_preview_nudge(mut nudge: ResMut<PreviewNudge>) {
+    nudge.0 = Vec3::ZERO;
+}
+
+/// How the player turns and offsets the preview.
+#[derive(SystemParam)]
+pub struct PlacementControls<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    game_settings: Res<'w, GameSettings>,
+    preview_building_handle: Res<'w, PreviewBuildingHandle>,
+    pub nudge: Res<'w, PreviewNudge>,
+}
+
+impl PlacementControls<'_> {
+    /// Turns `rotation` by `angle` around the vertical, or around the preview's own
+    /// X (pitch) or Z (roll) axis while their modifier key is held.
+    pub fn rotate(&self, rotation: Quat, angle: f32) -> Quat {
+        let keyboard = &self.game_settings.keyboard;
+        // Foundations are leveled against the ground, they only turn around the vertical,
+        // and so do groups with foundations among them
+        let handle = &self.preview_building_handle;
+        let free = handle.footprint.is_none()
+            && handle.group.iter().all(|piece| piece.footprint.is_none());
+        if free && self.keys.pressed(keyboard.pitch_modifier) {
+            rotation * Quat::from_rotation_x(angle)
+        } else if free && self.keys.pressed(keyboard.roll_modifier) {
+            rotation * Quat::from_rotation_z(angle)
+        } else {
+            Quat::from_rotation_y(angle) * rotation
+        }
+    }
+}
+
+/// Cycles the grid size, toggles snapping and nudges the preview.
+/// Nudges go along the world axis nearest to the direction the camera looks in.
+pub fn adjust_placement(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    camera: Single<&Transform, With<UniCamController>>,
+    mut building_settings: ResMut<BuildingSettings>,
+    mut nudge: ResMut<PreviewNudge>,
+) {
+    let keyboard = &game_settings.keyboard;
+    if keys.just_pressed(keyboard.cycle_grid_size) {
+        let next = GRID_SIZES
+            .iter()
+            .position(|size| *size == building_settings.grid_size)
+            .map_or(0, |index| (index + 1) % GRID_SIZES.len());
+        building_settings.grid_size = GRID_SIZES[next];
+        info!("Grid size: {} m", building_settings.grid_size);
+    }
+    if keys.just_pressed(keyboard.toggle_snapping) {
+        building_settings.snapping = !building_settings.snapping;
+        info!("Snapping: {}", building_settings.snapping);
+    }
+
+    let forward = camera.forward().with_y(0.0);
+    let forward = if forward.x.abs() > forward.z.abs() {
+        Vec3::X * forward.x.signum()
+    } else {
+        Vec3::Z * forward.z.signum()
+    };
+    let right = forward.cross(Vec3::Y);
+    let step = building_settings.grid_size;
+    for (key, direction) in [
+        (keyboard.nudge_forward, forward),
+        (keyboard.nudge_backward, -forward),
+        (keyboard.nudge_right, right),
+        (keyboard.nudge_left, -right),
+        (keyboard.nudge_up, Vec3::Y),
+        (keyboard.nudge_down, Vec3::NEG_Y),
+    ] {
+        if keys.just_pressed(key) {
+            nudge.0 += direction * step;
+        }
+    }
+}
+
+/// Shows the grid, snapping and rotation step the preview is placed with.
+pub fn placement_hud(
+    mut contexts: EguiContexts,
+    building_settings: Res<BuildingSettings>,
+    game_settings: Res<GameSettings>,
+) {
+    let keyboard = &game_settings.keyboard;
+    let snapping = if building_settings.snapping {
+        format!("grid {} m", building_settings.grid_size)
+    } else {
+        "snapping off".to_string()
+    };
+    egui::Area::new(egui::Id::new("placement_hud"))
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{snapping} ({:?} cycles, {:?} toggles), turning {}° per scroll",
+                keyboard.cycle_grid_size, keyboard.toggle_snapping, building_settings.rotation_step
+            ));
+            ui.label(format!(
+                "Scroll with {:?} to pitch, {:?} to roll",
+                keyboard.pitch_modifier, keyboard.roll_modifier
+            ));
+            ui.label(format!(
+                "{:?} {:?} {:?} {:?} {:?} {:?} nudge by a grid step",
+                keyboard.nudge_forward,
+                keyboard.nudge_backward,
+                keyboard.nudge_left,
+                keyboard.nudge_right,
+                keyboard.nudge_up,
+                keyboard.nudge_down
+            ));
+        });
+}
@@ -0,0 +1,187 @@
+use super::building_assets::{BuildingAssets, CollisionMeshes};
+use super::PlacedBuilding;
+use bevy::animation::AnimationPlayer;
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::ecs::world::EntityRef;
+use bevy::prelude::*;
+use bevy::render::mesh::morph::MeshMorphWeights;
+use bevy::render::mesh::skinning::SkinnedMesh;
+
+pub const PLACED_BUILDINGS: DiagnosticPath = DiagnosticPath::const_new("building/placed");
+/// Entities of all placed buildings, their roots and everything spawned below them.
+pub const BUILDING_ENTITIES: DiagnosticPath = DiagnosticPath::const_new("building/entities");
+pub const BUILDING_ENTITIES_PER_PIECE: DiagnosticPath =
+    DiagnosticPath::const_new("building/entities_per_piece");
+
+pub fn register_building_diagnostics(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(PLACED_BUILDINGS))
+        .register_diagnostic(Diagnostic::new(BUILDING_ENTITIES))
+        .register_diagnostic(Diagnostic::new(BUILDING_ENTITIES_PER_PIECE));
+}
+
+/// Replaces the glTF scenes of the buildings by flat ones once they are loaded:
+/// one entity per mesh, directly below the building, instead of the node hierarchy of the glTF.
+/// The meshes keep the mesh and material handles of the glTF, so all pieces of a kind
+/// share them and are batched into few draw calls.
+/// Also collects the meshes the collider of the building is built from.
+pub fn flatten_building_scenes(
+    mut building_assets: ResMut<BuildingAssets>,
+    mut scenes: ResMut<Assets<Scene>>,
+) {
+    // Imported buildings can be added any time
+    if building_assets.iter().all(|pack| pack.flattened) {
+        return;
+    }
+    for pack in building_assets.iter_mut().filter(|pack| !pack.flattened) {
+        let Some(scene) = scenes.get(&pack.scene) else {
+            continue;
+        };
+        pack.collision = Some(collision_meshes(scene));
+        pack.flattened = true;
+        let before = scene.world.entities().len();
+        let Some(flat) = flatten_scene(scene) else {
+            info!(
+                "Kept the scene of {} as it is, it needs its hierarchy",
+                pack.name
+            );
+            if let Some(scene) = scenes.get_mut(&pack.scene) {
+                hide_collision_meshes(scene);
+            }
+            continue;
+        };
+        info!(
+            "Flattened the scene of {}: {before} -> {} entities",
+            pack.name,
+            flat.world.entities().len()
+        );
+        pack.scene = scenes.add(flat);
+    }
+}
+
+/// Nodes named like this in a glTF are collision meshes, e.g. "Wall-col" or "UCX_Wall".
+fn is_collision_name(name: &str) -> bool {
+    name.ends_with("-col") || name.starts_with("UCX_") || name.to_lowercase().contains("collision")
+}
+
+/// Whether the entity or one of its ancestors is a collision mesh node.
+fn is_collision_mesh(world: &World, entity: EntityRef) -> bool {
+    let mut current = Some(entity.id());
+    while let Some(entity) = current {
+        if world
+            .get::<Name>(entity)
+            .is_some_and(|name| is_collision_name(name.as_str()))
+        {
+            return true;
+        }
+        current = world.get::<Parent>(entity).map(Parent::get);
+    }
+    false
+}
+
+/// The collision meshes authored in the scene, or all its meshes if it has none.
+fn collision_meshes(scene: &Scene) -> CollisionMeshes {
+    let mut authored = Vec::new();
+    let mut rendered = Vec::new();
+    for entity in scene.world.iter_entities() {
+        let Some(mesh) = entity.get::<Mesh3d>() else {
+            continue;
+        };
+        let shape = (
+            mesh.0.clone(),
+            root_relative_transform(&scene.world, entity.id()),
+        );
+        if is_collision_mesh(&scene.world, entity) {
+            authored.push(shape);
+        } else {
+            rendered.push(shape);
+        }
+    }
+    CollisionMeshes {
+        authored: !authored.is_empty(),
+        meshes: if authored.is_empty() {
+            rendered
+        } else {
+            authored
+        },
+    }
+}
+
+/// A scene with only the meshes of `scene`, positioned relative to its root.
+/// Collision meshes aren't drawn and left out.
+/// `None` for scenes that can't do without their hierarchy, e.g. animated or skinned ones.
+fn flatten_scene(scene: &Scene) -> Option<Scene> {
+    let needs_hierarchy = scene.world.iter_entities().any(|entity| {
+        entity.contains::<AnimationPlayer>()
+            || entity.contains::<SkinnedMesh>()
+            || entity.contains::<MeshMorphWeights>()
+            || entity.contains::<PointLight>()
+            || entity.contains::<SpotLight>()
+            || entity.contains::<DirectionalLight>()
+    });
+    if needs_hierarchy {
+        return None;
+    }
+
+    let mut world = World::new();
+    for entity in scene.world.iter_entities() {
+        let (Some(mesh), Some(material)) = (
+            entity.get::<Mesh3d>(),
+            entity.get::<MeshMaterial3d<StandardMaterial>>(),
+        ) else {
+            continue;
+        };
+        if is_collision_mesh(&scene.world, entity) {
+            continue;
+        }
+        world.spawn((
+            mesh.clone(),
+            material.clone(),
+            root_relative_transform(&scene.world, entity.id()),
+        ));
+    }
+    Some(Scene::new(world))
+}
+
+/// Hides the collision meshes of a scene which isn't flattened, they only shape the collider.
+fn hide_collision_meshes(scene: &mut Scene) {
+    let collision: Vec<Entity> = scene
+        .world
+        .iter_entities()
+        .filter(|entity| entity.contains::<Mesh3d>() && is_collision_mesh(&scene.world, *entity))
+        .map(|entity| entity.id())
+        .collect();
+    for entity in collision {
+        scene.world.entity_mut(entity).insert(Visibility::Hidden);
+    }
+}
+
+fn root_relative_transform(world: &World, entity: Entity) -> Transform {
+    let local = |entity: Entity| {
+        GlobalTransform::from(world.get::<Transform>(entity).copied().unwrap_or_default())
+    };
+    let mut transform = local(entity);
+    let mut current = entity;
+    while let Some(parent) = world.get::<Parent>(current) {
+        current = parent.get();
+        transform = local(current) * transform;
+    }
+    transform.compute_transform()
+}
+
+/// Counts the entities placed buildings are made of.
+pub fn measure_buildings(
+    mut diagnostics: Diagnostics,
+    buildings: Query<Entity, With<PlacedBuilding>>,
+    children: Query<&Children>,
+) {
+    let count = buildings.iter().count();
+    let entities: usize = buildings
+        .iter()
+        .map(|building| 1 + children.iter_descendants(building).count())
+        .sum();
+    diagnostics.add_measurement(&PLACED_BUILDINGS, || count as f64);
+    diagnostics.add_measurement(&BUILDING_ENTITIES, || entities as f64);
+    diagnostics.add_measurement(&BUILDING_ENTITIES_PER_PIECE, || {
+        entities as f64 / count.max(1) as f64
+    });
+}
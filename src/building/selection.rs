@@ -0,0 +1,457 @@
+use super::blueprint::SaveBlueprintEvent;
+use super::building::spawn_placed_building;
+use super::building_assets::{BuildingAssets, PreviewBuildingHandle, PreviewPiece};
+use super::building_cost::BuildingEconomy;
+use super::building_tier::BuildingTier;
+use super::foundation::{
+    group_foundations, terraform_group_pads, FoundationLeveling, LeveledFoundations,
+};
+use super::gltf_export::{ExportBuildingsEvent, ExportScope};
+use super::material_variant::MaterialVariant;
+use super::opening::Openable;
+use super::{BuildingMode, BuildingSettings, ChangeBuildingModeEvent, PlacedBuilding};
+use crate::file_names::is_valid_file_stem;
+use crate::settings::GameSettings;
+use crate::terrain::{Terrain, TerrainSettings};
+use crate::universal_camera_controller::UniCamController;
+use crate::world_streaming::WorldChunks;
+use bevy::ecs::system::SystemParam;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, RayCastSettings};
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+
+/// Pixels the cursor has to move while pressed before a click becomes a box selection.
+const DRAG_THRESHOLD: f32 = 4.0;
+
+const HIGHLIGHT_COLOR: Color = Color::srgb(0.3, 0.8, 1.0);
+
+/// Placed buildings selected to be moved, copied, rotated or deleted together.
+#[derive(Resource, Default)]
+pub struct BuildingSelection(pub Vec<Entity>);
+
+/// A building of a selection being moved. It stays hidden until the selection is placed
+/// somewhere else, and comes back if the building mode is left without placing it.
+#[derive(Component)]
+pub struct MovingBuilding;
+
+#[derive(Event, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionAction {
+    Move,
+    Duplicate,
+    /// Turns the selection by 90° around its center, where it is.
+    /// Checked like a moved selection being placed, it isn't turned where that would be blocked.
+    Rotate,
+    Delete,
+}
+
+/// Input of the selection, the cursor is free while selecting.
+#[derive(SystemParam)]
+pub struct SelectionInput<'w, 's> {
+    buttons: Res<'w, ButtonInput<MouseButton>>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    window: Single<'w, &'static Window, With<PrimaryWindow>>,
+    contexts: EguiContexts<'w, 's>,
+}
+
+/// Finds placed buildings on the screen.
+#[derive(SystemParam)]
+pub struct BuildingPicker<'w, 's> {
+    camera: Single<'w, (&'static Camera, &'static GlobalTransform), With<UniCamController>>,
+    ray_cast: MeshRayCast<'w, 's>,
+    parents: Query<'w, 's, &'static Parent>,
+    placed_buildings: Query<'w, 's, (Entity, &'static GlobalTransform), With<PlacedBuilding>>,
+}
+
+impl BuildingPicker<'_, '_> {
+    /// The placed building under the cursor.
+    fn building_at(&mut self, cursor: Vec2) -> Option<Entity> {
+        let (camera, camera_transform) = *self.camera;
+        let ray = camera.viewport_to_world(camera_transform, cursor).ok()?;
+        let parents = &self.parents;
+        let placed_buildings = &self.placed_buildings;
+        let building_of = |entity: Entity| {
+            std::iter::once(entity)
+                .chain(parents.iter_ancestors(entity))
+                .find(|ancestor| placed_buildings.contains(*ancestor))
+        };
+        let filter = |entity: Entity| building_of(entity).is_some();
+        let settings = RayCastSettings::default().with_filter(&filter);
+        self.ray_cast
+            .cast_ray(ray, &settings)
+            .first()
+            .and_then(|(entity, _)| building_of(*entity))
+    }
+
+    /// Placed buildings in front of the camera whose origin is within `rect` on the screen.
+    fn buildings_in(&self, rect: Rect) -> Vec<Entity> {
+        let (camera, camera_transform) = *self.camera;
+        let camera_position = camera_transform.translation();
+        let forward = camera_transform.forward();
+        self.placed_buildings
+            .iter()
+            .filter(|(_, transform)| {
+                let position = transform.translation();
+                forward.dot(position - camera_position) > 0.0
+                    && camera
+                        .world_to_viewport(camera_transform, position)
+                        .is_ok_and(|point| rect.contains(point))
+            })
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+}
+
+/// Selects buildings by clicking them or dragging a box around them.
+/// Holding shift adds to the selection instead of replacing it, a shift-click on a selected building removes it.
+pub fn select_buildings(
+    mut input: SelectionInput,
+    mut picker: BuildingPicker,
+    mut selection: ResMut<BuildingSelection>,
+    mut drag_start: Local<Option<Vec2>>,
+) {
+    selection
+        .0
+        .retain(|entity| picker.placed_buildings.contains(*entity));
+    let Some(cursor) = input.window.cursor_position() else {
+        return;
+    };
+    let ctx = input.contexts.ctx_mut();
+    if input.buttons.just_pressed(MouseButton::Left) && !ctx.is_pointer_over_area() {
+        *drag_start = Some(cursor);
+    }
+    let Some(start) = *drag_start else {
+        return;
+    };
+    let dragged = start.distance(cursor) > DRAG_THRESHOLD;
+
+    if input.buttons.pressed(MouseButton::Left) {
+        if dragged {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("selection_box"),
+            ));
+            let corners = [
+                egui::pos2(start.x, start.y),
+                egui::pos2(cursor.x, start.y),
+                egui::pos2(cursor.x, cursor.y),
+                egui::pos2(start.x, cursor.y),
+            ];
+            painter.add(egui::Shape::closed_line(
+                corners.to_vec(),
+                egui::Stroke::new(1.0, egui::Color32::WHITE),
+            ));
+        }
+        return;
+    }
+
+    *drag_start = None;
+    let additive =
+        input.keys.pressed(KeyCode::ShiftLeft) || input.keys.pressed(KeyCode::ShiftRight);
+    if dragged {
+        if !additive {
+            selection.0.clear();
+        }
+        for entity in picker.buildings_in(Rect::from_corners(start, cursor)) {
+            if !selection.0.contains(&entity) {
+                selection.0.push(entity);
+            }
+        }
+        return;
+    }
+    let Some(entity) = picker.building_at(cursor) else {
+        if !additive {
+            selection.0.clear();
+        }
+        return;
+    };
+    match selection.0.iter().position(|selected| *selected == entity) {
+        Some(index) if additive => {
+            selection.0.remove(index);
+        }
+        None if additive => selection.0.push(entity),
+        _ => selection.0 = vec![entity],
+    }
+}
+
+/// Outlines the selected buildings.
+pub fn highlight_selection(
+    selection: Res<BuildingSelection>,
+    children: Query<&Children>,
+    bounds: Query<(&Aabb, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    for root in selection.0.iter().copied() {
+        let corners = children
+            .iter_descendants(root)
+            .filter_map(|entity| bounds.get(entity).ok())
+            .flat_map(|(aabb, transform)| {
+                let (center, half) = (Vec3::from(aabb.center), Vec3::from(aabb.half_extents));
+                [-1.0, 1.0].into_iter().flat_map(move |x| {
+                    [-1.0, 1.0].into_iter().flat_map(move |y| {
+                        [-1.0, 1.0].into_iter().map(move |z| {
+                            transform.transform_point(center + half * Vec3::new(x, y, z))
+                        })
+                    })
+                })
+            });
+        let (min, max) = corners.fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), corner| (min.min(corner), max.max(corner)),
+        );
+        if min.cmple(max).all() {
+            gizmos.cuboid(
+                Transform::from_translation((min + max) / 2.0).with_scale(max - min),
+                HIGHLIGHT_COLOR,
+            );
+        }
+    }
+}
+
+/// What the selection window asks for.
+#[derive(SystemParam)]
+pub struct SelectionRequests<'w> {
+    actions: EventWriter<'w, SelectionAction>,
+    save_blueprint: EventWriter<'w, SaveBlueprintEvent>,
+    export: EventWriter<'w, ExportBuildingsEvent>,
+}
+
+/// Window listing what can be done with the selection.
+pub fn selection_menu(
+    mut contexts: EguiContexts,
+    selection: Res<BuildingSelection>,
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut requests: SelectionRequests,
+    mut name: Local<String>,
+) {
+    egui::Window::new("Selection").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!("{} pieces selected", selection.0.len()));
+        ui.label("Click or drag a box to select, hold Shift to add");
+        ui.add_enabled_ui(!selection.0.is_empty(), |ui| {
+            ui.horizontal(|ui| {
+                for (label, action) in [
+                    ("Move", SelectionAction::Move),
+                    ("Duplicate", SelectionAction::Duplicate),
+                    ("Rotate", SelectionAction::Rotate),
+                    ("Delete", SelectionAction::Delete),
+                ] {
+                    if ui.button(label).clicked() {
+                        requests.actions.send(action);
+                    }
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut *name);
+            });
+            let name = name.trim();
+            ui.add_enabled_ui(is_valid_file_stem(name), |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Save as blueprint").clicked() {
+                        requests
+                            .save_blueprint
+                            .send(SaveBlueprintEvent(name.to_string()));
+                    }
+                    if ui.button("Export to glTF").clicked() {
+                        requests.export.send(ExportBuildingsEvent {
+                            name: name.to_string(),
+                            scope: ExportScope::Selection,
+                        });
+                    }
+                });
+            });
+        });
+    });
+    // Not while the name is typed, Delete erases a character there
+    let typing = contexts.ctx_mut().wants_keyboard_input();
+    if !typing
+        && keys.just_pressed(game_settings.keyboard.delete_selection)
+        && !selection.0.is_empty()
+    {
+        requests.actions.send(SelectionAction::Delete);
+    }
+}
+
+/// The selected buildings.
+#[derive(SystemParam)]
+pub struct SelectedBuildings<'w, 's> {
+    selection: ResMut<'w, BuildingSelection>,
+    buildings: Query<
+        'w,
+        's,
+        (
+            &'static PlacedBuilding,
+            &'static Transform,
+            &'static MaterialVariant,
+            &'static BuildingTier,
+            Option<&'static Openable>,
+        ),
+    >,
+    building_assets: Res<'w, BuildingAssets>,
+}
+
+/// Anchor of buildings at the given positions: at their center, level with the lowest of them.
+/// `None` without any buildings.
+pub fn group_anchor(positions: impl Iterator<Item = Vec3>) -> Option<Vec3> {
+    let positions: Vec<Vec3> = positions.collect();
+    let lowest = positions
+        .iter()
+        .map(|position| position.y)
+        .reduce(f32::min)?;
+    let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+    Some(center.with_y(lowest))
+}
+
+impl SelectedBuildings<'_, '_> {
+    /// The selection as pieces positioned relative to an anchor at its center, level with its lowest piece,
+    /// and the position of that anchor.
+    pub fn pieces(&self) -> (Vec3, Vec<PreviewPiece>) {
+        let selected: Vec<_> = self
+            .selection
+            .0
+            .iter()
+            .filter_map(|entity| self.buildings.get(*entity).ok())
+            .collect();
+        let Some(anchor) = group_anchor(
+            selected
+                .iter()
+                .map(|(_, transform, _, _, _)| transform.translation),
+        ) else {
+            return (Vec3::ZERO, Vec::new());
+        };
+
+        let pieces = selected
+            .into_iter()
+            .filter_map(|(building, transform, material, tier, openable)| {
+                let pack = self.building_assets.get(&building.name)?;
+                Some(PreviewPiece {
+                    name: building.name.clone(),
+                    scene: pack.scene.clone(),
+                    material: material.0.clone(),
+                    tier: *tier,
+                    transform: transform.with_translation(transform.translation - anchor),
+                    footprint: pack.footprint,
+                    open: openable.is_some_and(|openable| openable.open),
+                })
+            })
+            .collect();
+        (anchor, pieces)
+    }
+}
+
+/// What a group of pieces placed without the preview is checked against, like the preview is.
+#[derive(SystemParam)]
+pub struct GroupPlacement<'w> {
+    terrain: Option<ResMut<'w, Terrain>>,
+    building_settings: Res<'w, BuildingSettings>,
+    world_chunks: Res<'w, WorldChunks>,
+    terrain_settings: Res<'w, TerrainSettings>,
+}
+
+impl GroupPlacement<'_> {
+    /// Levels the foundations among `pieces` placed relative to `anchor` and flattens the ground
+    /// under them with terraform leveling. Returns the leveled anchor, or why the group can't be placed.
+    fn place(&mut self, mut anchor: Transform, pieces: &[PreviewPiece]) -> Result<Transform, &str> {
+        let leveling = self.building_settings.foundation_leveling;
+        let foundations = group_foundations(pieces);
+        if let (Some(terrain), false) = (self.terrain.as_deref(), foundations.is_empty()) {
+            let leveled = LeveledFoundations::level(terrain, &anchor, &foundations, leveling)
+                .ok_or("a foundation would be off the terrain")?;
+            if leveled.too_steep(self.building_settings.max_foundation_slope) {
+                return Err("the ground is too steep for a foundation");
+            }
+            anchor.translation.y += leveled.offset;
+        }
+        let positions = pieces
+            .iter()
+            .map(|piece| anchor.transform_point(piece.transform.translation));
+        if !self
+            .world_chunks
+            .all_loaded(positions, self.terrain_settings.chunk_size)
+        {
+            return Err("a piece would be in a chunk which isn't loaded");
+        }
+        if let (Some(terrain), FoundationLeveling::Terraform) =
+            (self.terrain.as_deref_mut(), leveling)
+        {
+            terraform_group_pads(terrain, &anchor, pieces);
+        }
+        Ok(anchor)
+    }
+}
+
+/// Moves, copies, rotates or deletes the selection.
+/// Moved and copied selections become the preview of the building mode, so they are placed like a single building.
+pub fn apply_selection_actions(
+    mut commands: Commands,
+    mut actions: EventReader<SelectionAction>,
+    mut selected: SelectedBuildings,
+    mut preview_building_handle: ResMut<PreviewBuildingHandle>,
+    mut change_mode: EventWriter<ChangeBuildingModeEvent>,
+    mut economy: BuildingEconomy,
+    mut placement: GroupPlacement,
+) {
+    for action in actions.read().copied() {
+        match action {
+            SelectionAction::Move | SelectionAction::Duplicate => {
+                let (_, pieces) = selected.pieces();
+                if pieces.is_empty() {
+                    continue;
+                }
+                preview_building_handle.select_group(format!("{} pieces", pieces.len()), pieces);
+                if action == SelectionAction::Move {
+                    for entity in selected.selection.0.drain(..) {
+                        commands
+                            .entity(entity)
+                            .insert((MovingBuilding, Visibility::Hidden));
+                    }
+                }
+                change_mode.send(ChangeBuildingModeEvent(BuildingMode::Building));
+            }
+            SelectionAction::Rotate => {
+                let (anchor, mut pieces) = selected.pieces();
+                if pieces.is_empty() {
+                    continue;
+                }
+                for piece in &mut pieces {
+                    piece
+                        .transform
+                        .rotate_around(Vec3::ZERO, Quat::from_rotation_y(90_f32.to_radians()));
+                }
+                let anchor = match placement.place(Transform::from_translation(anchor), &pieces) {
+                    Ok(anchor) => anchor,
+                    Err(reason) => {
+                        warn!("The selection can't be rotated where it is, {reason}");
+                        continue;
+                    }
+                };
+                for entity in selected.selection.0.drain(..) {
+                    commands.entity(entity).despawn_recursive();
+                }
+                // Respawned, so pillars and lights are placed anew
+                for piece in pieces {
+                    let entity = spawn_placed_building(
+                        &mut commands,
+                        &piece.name,
+                        piece.scene,
+                        anchor.mul_transform(piece.transform),
+                        piece.material,
+                        piece.tier,
+                        piece.open,
+                    );
+                    selected.selection.0.push(entity);
+                }
+            }
+            SelectionAction::Delete => {
+                let (_, pieces) = selected.pieces();
+                economy.refund(pieces.iter().map(|piece| (piece.name.as_str(), piece.tier)));
+                for entity in selected.selection.0.drain(..) {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::keyboard_focus::shortcuts_enabled;
+use crate::settings::GameSettings;
+use bevy::diagnostic::{
+    DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+/// Whether the window with the diagnostics is shown.
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DebugOverlayState {
+    #[default]
+    Hidden,
+    Shown,
+}
+
+/// Shows every registered diagnostic (frame time, entity count, terrain meshes, ...) in a window.
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin))
+            .init_state::<DebugOverlayState>()
+            .add_systems(Update, debug_overlay_watchdog.run_if(shortcuts_enabled))
+            .add_systems(
+                Update,
+                debug_overlay.run_if(in_state(DebugOverlayState::Shown)),
+            );
+    }
+}
+
+fn debug_overlay_watchdog(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    current_state: Res<State<DebugOverlayState>>,
+    mut next_state: ResMut<NextState<DebugOverlayState>>,
+) {
+    if keys.just_pressed(game_settings.keyboard.debug_overlay) {
+        match current_state.get() {
+            DebugOverlayState::Hidden => next_state.set(DebugOverlayState::Shown),
+            DebugOverlayState::Shown => next_state.set(DebugOverlayState::Hidden),
+        }
+    }
+}
+
+fn debug_overlay(mut contexts: EguiContexts, diagnostics: Res<DiagnosticsStore>) {
+    let mut diagnostics: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.is_enabled)
+        .collect();
+    diagnostics.sort_by(|a, b| a.path().as_str().cmp(b.path().as_str()));
+
+    egui::Window::new("Diagnostics")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("diagnostics").striped(true).show(ui, |ui| {
+                for diagnostic in diagnostics {
+                    ui.label(diagnostic.path().as_str());
+                    match diagnostic.smoothed() {
+                        Some(value) => ui.label(format!("{value:.1}{}", diagnostic.suffix)),
+                        None => ui.label("-"),
+                    };
+                    ui.end_row();
+                }
+            });
+        });
+}
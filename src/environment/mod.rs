@@ -0,0 +1,285 @@
+mod precipitation;
+mod random;
+mod sky;
+mod time_of_day;
+mod weather;
+
+use crate::keyboard_focus::shortcuts_enabled;
+use crate::settings::GameSettings;
+use crate::universal_camera_controller::UniCamController;
+use bevy::pbr::{CascadeShadowConfigBuilder, FogFalloff, NotShadowCaster, NotShadowReceiver};
+use bevy::prelude::*;
+use bevy::render::render_resource::Face;
+use precipitation::{spawn_precipitation, update_precipitation};
+use sky::{paint_sky_dome, SkyColors};
+
+pub use random::Random;
+pub use time_of_day::TimeOfDay;
+pub use weather::{Weather, Wind};
+
+/// Radius of the sky dome, inside of the camera's far plane.
+const SKY_DOME_RADIUS: f32 = 900.0;
+
+/// Distance of the moon disc from the camera, in front of the sky dome.
+const MOON_DISTANCE: f32 = 850.0;
+const MOON_RADIUS: f32 = 20.0;
+
+const SUN_ILLUMINANCE: f32 = light_consts::lux::AMBIENT_DAYLIGHT;
+/// Much brighter than a real moon, so the world stays playable at night.
+const MOON_ILLUMINANCE: f32 = 300.0;
+const DAY_AMBIENT_BRIGHTNESS: f32 = 400.0;
+const NIGHT_AMBIENT_BRIGHTNESS: f32 = 40.0;
+/// Share of the sunlight a fully clouded sky lets through.
+const OVERCAST_SUNLIGHT: f32 = 0.25;
+
+/// Which light a `DirectionalLight` of the environment is.
+#[derive(Component, PartialEq, Eq)]
+enum CelestialLight {
+    Sun,
+    Moon,
+}
+
+/// Part of the sky, kept centered on the camera.
+#[derive(Component)]
+struct SkyObject;
+
+#[derive(Component)]
+struct SkyDome;
+
+#[derive(Component)]
+struct MoonDisc;
+
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>()
+            .init_resource::<Weather>()
+            .init_resource::<Wind>()
+            .insert_resource(AmbientLight::default())
+            .add_systems(Startup, spawn_environment)
+            .add_systems(
+                Update,
+                (
+                    advance_time_of_day,
+                    cycle_weather.run_if(shortcuts_enabled),
+                    advance_weather,
+                    update_celestial_lights,
+                    update_sky,
+                    update_fog,
+                    follow_camera,
+                    update_precipitation,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn spawn_environment(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        CelestialLight::Sun,
+        DirectionalLight {
+            color: Color::srgb(1.0, 0.96, 0.9),
+            shadows_enabled: true,
+            ..default()
+        },
+        CascadeShadowConfigBuilder {
+            maximum_distance: 100.0,
+            ..default()
+        }
+        .build(),
+    ));
+    commands.spawn((
+        CelestialLight::Moon,
+        DirectionalLight {
+            color: Color::srgb(0.6, 0.7, 1.0),
+            shadows_enabled: false,
+            ..default()
+        },
+    ));
+
+    // Unlit and drawn from the inside, colored per vertex by `update_sky`
+    commands.spawn((
+        SkyObject,
+        SkyDome,
+        Mesh3d(meshes.add(Sphere::new(SKY_DOME_RADIUS).mesh().uv(48, 24))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            unlit: true,
+            fog_enabled: false,
+            cull_mode: Some(Face::Front),
+            ..default()
+        })),
+        NotShadowCaster,
+        NotShadowReceiver,
+    ));
+    commands.spawn((
+        SkyObject,
+        MoonDisc,
+        Mesh3d(meshes.add(Sphere::new(MOON_RADIUS))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            emissive: LinearRgba::rgb(0.9, 0.92, 1.0) * 2.0,
+            fog_enabled: false,
+            ..default()
+        })),
+        NotShadowCaster,
+        NotShadowReceiver,
+    ));
+    spawn_precipitation(&mut commands, &mut meshes, &mut materials);
+}
+
+fn advance_time_of_day(
+    mut time_of_day: ResMut<TimeOfDay>,
+    game_settings: Res<GameSettings>,
+    time: Res<Time>,
+) {
+    let game_seconds = time.delta_secs() * game_settings.environment.time_scale;
+    if game_seconds > 0.0 {
+        time_of_day.advance(game_seconds / 3600.0);
+    }
+}
+
+/// Switches to the next kind of weather by hand.
+fn cycle_weather(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut weather: ResMut<Weather>,
+) {
+    if keys.just_pressed(game_settings.keyboard.cycle_weather) {
+        let next = weather.kind().next();
+        weather.change_to(next);
+        info!("Changing weather: {next:?}");
+    }
+}
+
+fn advance_weather(
+    mut weather: ResMut<Weather>,
+    mut wind: ResMut<Wind>,
+    game_settings: Res<GameSettings>,
+    time: Res<Time>,
+) {
+    let environment = &game_settings.environment;
+    let game_hours = time.delta_secs() * environment.time_scale / 3600.0;
+    weather.advance(time.delta_secs(), game_hours, environment.dynamic_weather);
+    wind.update(&weather.conditions(), time.delta_secs());
+}
+
+/// Points the sun and the moon and sets their brightness and the ambient light.
+fn update_celestial_lights(
+    time_of_day: Res<TimeOfDay>,
+    weather: Res<Weather>,
+    mut lights: Query<(&mut Transform, &mut DirectionalLight, &CelestialLight)>,
+    mut ambient_light: ResMut<AmbientLight>,
+) {
+    if !time_of_day.is_changed() && !weather.is_changed() {
+        return;
+    }
+    let daylight = time_of_day.daylight();
+    let conditions = weather.conditions();
+    let sunlight = 1.0.lerp(OVERCAST_SUNLIGHT, conditions.cloud_cover);
+
+    for (mut transform, mut light, celestial_light) in lights.iter_mut() {
+        let (direction, illuminance) = match celestial_light {
+            CelestialLight::Sun => (
+                time_of_day.sun_direction(),
+                SUN_ILLUMINANCE * daylight * sunlight,
+            ),
+            CelestialLight::Moon => (
+                time_of_day.moon_direction(),
+                MOON_ILLUMINANCE * (1.0 - daylight) * sunlight,
+            ),
+        };
+        *transform = Transform::default().looking_to(-direction, Vec3::Y);
+        light.illuminance = illuminance;
+        if *celestial_light == CelestialLight::Sun {
+            // Clouds scatter the sunlight too much for sharp shadows
+            light.shadows_enabled = daylight > 0.0 && conditions.cloud_cover < 0.9;
+        }
+    }
+
+    let colors = SkyColors::at(&time_of_day, &conditions);
+    ambient_light.color = colors.average().into();
+    ambient_light.brightness = NIGHT_AMBIENT_BRIGHTNESS.lerp(DAY_AMBIENT_BRIGHTNESS, daylight);
+}
+
+fn update_sky(
+    time_of_day: Res<TimeOfDay>,
+    weather: Res<Weather>,
+    sky_dome: Single<&Mesh3d, With<SkyDome>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if !time_of_day.is_changed() && !weather.is_changed() {
+        return;
+    }
+    let colors = SkyColors::at(&time_of_day, &weather.conditions());
+    if let Some(mesh) = meshes.get_mut(&sky_dome.0) {
+        paint_sky_dome(mesh, &colors, time_of_day.sun_direction());
+    }
+    clear_color.0 = colors.average().into();
+}
+
+/// Thickens the fog on the camera with the weather. The fog is added on the first run.
+fn update_fog(
+    mut commands: Commands,
+    time_of_day: Res<TimeOfDay>,
+    weather: Res<Weather>,
+    camera: Single<(Entity, Option<&mut DistanceFog>), With<UniCamController>>,
+) {
+    let (entity, fog) = camera.into_inner();
+    let conditions = weather.conditions();
+    let colors = SkyColors::at(&time_of_day, &conditions);
+    let color = Color::from(colors.fog());
+    let falloff = FogFalloff::from_visibility(conditions.visibility);
+
+    match fog {
+        Some(mut fog) => {
+            fog.color = color;
+            fog.falloff = falloff;
+        }
+        None => {
+            commands.entity(entity).insert(DistanceFog {
+                color,
+                falloff,
+                ..default()
+            });
+        }
+    }
+}
+
+type SkyObjects<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        &'static mut Visibility,
+        Has<MoonDisc>,
+    ),
+    (With<SkyObject>, Without<UniCamController>),
+>;
+
+/// Keeps the sky dome and the moon centered on the camera, so they are never reached.
+/// The moon is only shown at night.
+fn follow_camera(
+    time_of_day: Res<TimeOfDay>,
+    camera: Single<&Transform, With<UniCamController>>,
+    mut sky_objects: SkyObjects,
+) {
+    for (mut transform, mut visibility, is_moon) in sky_objects.iter_mut() {
+        if is_moon {
+            transform.translation =
+                camera.translation + time_of_day.moon_direction() * MOON_DISTANCE;
+            *visibility = if time_of_day.is_night() {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        } else {
+            transform.translation = camera.translation;
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use super::random::Random;
+use super::weather::{Weather, Wind};
+use crate::terrain::Terrain;
+use crate::universal_camera_controller::UniCamController;
+use bevy::pbr::{NotShadowCaster, NotShadowReceiver};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::NoFrustumCulling;
+
+/// Particles falling at full intensity.
+const MAX_PARTICLES: usize = 6000;
+/// Half of the side of the square area around the camera particles fall in.
+const AREA_RADIUS: f32 = 20.0;
+/// Height above and below the camera particles fall from and to.
+const AREA_HEIGHT: f32 = 15.0;
+
+const RAIN_SPEED: f32 = 9.0;
+const RAIN_LENGTH: f32 = 0.4;
+const RAIN_WIDTH: f32 = 0.01;
+const RAIN_COLOR: [f32; 4] = [0.7, 0.75, 0.8, 0.35];
+/// Raindrops are heavy, the wind moves them less than snowflakes.
+const RAIN_WIND_FACTOR: f32 = 0.4;
+
+const SNOW_SPEED: f32 = 1.2;
+const SNOW_SIZE: f32 = 0.035;
+const SNOW_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+/// How far snowflakes swing from side to side, in m/s.
+const SNOW_SWAY: f32 = 0.3;
+
+struct Particle {
+    position: Vec3,
+    snow: bool,
+    /// Offset of the snowflake sway, so they don't swing in sync.
+    phase: f32,
+}
+
+/// Rain and snow around the camera, simulated on the CPU and drawn as one mesh.
+#[derive(Component)]
+pub struct Precipitation {
+    particles: Vec<Particle>,
+    random: Random,
+}
+
+pub fn spawn_precipitation(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    let mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<Vec3>::new())
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new())
+    .with_inserted_indices(Indices::U32(Vec::new()));
+    commands.spawn((
+        Precipitation {
+            particles: Vec::new(),
+            random: Random::new(0x7a1d),
+        },
+        Mesh3d(meshes.add(mesh)),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            unlit: true,
+            alpha_mode: AlphaMode::Blend,
+            cull_mode: None,
+            ..default()
+        })),
+        // The mesh is rebuilt in world space every frame, its bounds never fit
+        NoFrustumCulling,
+        NotShadowCaster,
+        NotShadowReceiver,
+    ));
+}
+
+/// Moves the particles, respawns the ones that hit the ground and rebuilds the mesh.
+pub fn update_precipitation(
+    weather: Res<Weather>,
+    wind: Res<Wind>,
+    terrain: Option<Res<Terrain>>,
+    time: Res<Time>,
+    camera: Single<&GlobalTransform, With<UniCamController>>,
+    precipitation: Single<(&mut Precipitation, &Mesh3d)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let (mut precipitation, mesh) = precipitation.into_inner();
+    let Precipitation { particles, random } = &mut *precipitation;
+    let conditions = weather.conditions();
+    let intensity = (conditions.rain + conditions.snow).min(1.0);
+    let snow_share = conditions.snow / (conditions.rain + conditions.snow).max(f32::EPSILON);
+    let count = (MAX_PARTICLES as f32 * intensity) as usize;
+
+    let center = camera.translation();
+    let delta = time.delta_secs();
+    let elapsed = time.elapsed_secs();
+    let ground = |position: Vec3| {
+        terrain
+            .as_ref()
+            .and_then(|terrain| terrain.height_at(position.x, position.z))
+            .unwrap_or(f32::NEG_INFINITY)
+    };
+
+    // New particles start anywhere in the area, so a starting shower doesn't arrive as one layer
+    while particles.len() < count {
+        let snow = random.next_f32() < snow_share;
+        let position = center
+            + Vec3::new(
+                random.range(-AREA_RADIUS, AREA_RADIUS),
+                random.range(-AREA_HEIGHT, AREA_HEIGHT),
+                random.range(-AREA_RADIUS, AREA_RADIUS),
+            );
+        let phase = random.range(0.0, std::f32::consts::TAU);
+        particles.push(Particle {
+            position,
+            snow,
+            phase,
+        });
+    }
+    particles.truncate(count);
+
+    let mut positions = Vec::with_capacity(count * 4);
+    let mut colors = Vec::with_capacity(count * 4);
+    let mut indices = Vec::with_capacity(count * 6);
+    let camera_right = camera.right().as_vec3();
+    let camera_up = camera.up().as_vec3();
+
+    for particle in particles.iter_mut() {
+        let velocity = if particle.snow {
+            let sway = elapsed + particle.phase;
+            Vec3::new(sway.sin(), 0.0, (sway * 0.8).cos()) * SNOW_SWAY - Vec3::Y * SNOW_SPEED
+                + wind.sample(particle.position)
+        } else {
+            wind.velocity() * RAIN_WIND_FACTOR - Vec3::Y * RAIN_SPEED
+        };
+        particle.position += velocity * delta;
+
+        // Keep the particles around the camera as it moves
+        let offset = particle.position - center;
+        particle.position.x = center.x + wrap(offset.x, AREA_RADIUS);
+        particle.position.z = center.z + wrap(offset.z, AREA_RADIUS);
+        if particle.position.y < center.y - AREA_HEIGHT
+            || particle.position.y < ground(particle.position)
+        {
+            particle.snow = random.next_f32() < snow_share;
+            particle.position = center
+                + Vec3::new(
+                    random.range(-AREA_RADIUS, AREA_RADIUS),
+                    AREA_HEIGHT,
+                    random.range(-AREA_RADIUS, AREA_RADIUS),
+                );
+        } else if particle.position.y > center.y + AREA_HEIGHT {
+            particle.position.y -= 2.0 * AREA_HEIGHT;
+        }
+
+        // Rain is drawn as streaks along its fall, snow as flakes facing the camera
+        let (along, across, color) = if particle.snow {
+            (camera_up * SNOW_SIZE, camera_right * SNOW_SIZE, SNOW_COLOR)
+        } else {
+            let direction = velocity.normalize_or_zero();
+            let side = direction
+                .cross(particle.position - center)
+                .normalize_or_zero();
+            (direction * RAIN_LENGTH / 2.0, side * RAIN_WIDTH, RAIN_COLOR)
+        };
+        let first = positions.len() as u32;
+        positions.extend([
+            particle.position - along - across,
+            particle.position - along + across,
+            particle.position + along - across,
+            particle.position + along + across,
+        ]);
+        colors.extend([color; 4]);
+        indices.extend_from_slice(&[first, first + 1, first + 2, first + 2, first + 1, first + 3]);
+    }
+
+    if let Some(mesh) = meshes.get_mut(&mesh.0) {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_indices(Indices::U32(indices));
+    }
+}
+
+/// Wraps `offset` into `-radius..radius`.
+fn wrap(offset: f32, radius: f32) -> f32 {
+    (offset + radius).rem_euclid(2.0 * radius) - radius
+}
@@ -0,0 +1,84 @@
+use super::time_of_day::{smoothstep, TimeOfDay};
+use super::weather::WeatherConditions;
+use bevy::prelude::*;
+
+const NIGHT_ZENITH: LinearRgba = LinearRgba::rgb(0.002, 0.003, 0.012);
+const NIGHT_HORIZON: LinearRgba = LinearRgba::rgb(0.01, 0.012, 0.03);
+const DAY_ZENITH: LinearRgba = LinearRgba::rgb(0.08, 0.25, 0.8);
+const DAY_HORIZON: LinearRgba = LinearRgba::rgb(0.5, 0.65, 0.9);
+const TWILIGHT_HORIZON: LinearRgba = LinearRgba::rgb(0.9, 0.35, 0.12);
+const SUN_GLOW: LinearRgba = LinearRgba::rgb(1.0, 0.85, 0.6);
+/// Color of a fully clouded sky at noon.
+const CLOUDS: LinearRgba = LinearRgba::rgb(0.45, 0.47, 0.5);
+
+/// Colors of the gradient sky at a time of day.
+pub struct SkyColors {
+    zenith: LinearRgba,
+    horizon: LinearRgba,
+    /// How much the sky around the sun is brightened.
+    glow: f32,
+}
+
+impl SkyColors {
+    pub fn at(time_of_day: &TimeOfDay, weather: &WeatherConditions) -> Self {
+        let daylight = time_of_day.daylight();
+        let sun_height = time_of_day.sun_direction().y;
+        // Strongest when the sun is at the horizon
+        let twilight = 1.0 - smoothstep(0.0, 0.3, sun_height.abs());
+
+        let zenith = NIGHT_ZENITH.mix(&DAY_ZENITH, daylight);
+        let horizon = NIGHT_HORIZON
+            .mix(&DAY_HORIZON, daylight)
+            .mix(&TWILIGHT_HORIZON, twilight * 0.7);
+
+        // Clouds cover the gradient, fog washes it out into the color at the horizon
+        let clouds = NIGHT_HORIZON.mix(&CLOUDS, daylight);
+        let zenith = zenith.mix(&clouds, weather.cloud_cover);
+        let horizon = horizon.mix(&clouds, weather.cloud_cover);
+        let haze = 1.0 - smoothstep(50.0, 1000.0, weather.visibility);
+        Self {
+            zenith: zenith.mix(&horizon, haze),
+            horizon,
+            glow: (0.3 * daylight + 0.6 * twilight) * (1.0 - weather.cloud_cover),
+        }
+    }
+
+    /// Sky color in the `direction` seen from the ground.
+    pub fn color(&self, direction: Vec3, sun_direction: Vec3) -> LinearRgba {
+        let height = direction.y.max(0.0).powf(0.5);
+        let mut color = self.horizon.mix(&self.zenith, height);
+        let towards_sun = direction.dot(sun_direction).max(0.0).powi(8);
+        color += SUN_GLOW * (towards_sun * self.glow);
+        color
+    }
+
+    /// Color of the fog, which blends into the sky at the horizon.
+    pub fn fog(&self) -> LinearRgba {
+        self.horizon
+    }
+
+    /// Average color, used for the ambient light and the background.
+    pub fn average(&self) -> LinearRgba {
+        self.horizon.mix(&self.zenith, 0.5)
+    }
+}
+
+/// Recolors the vertices of the sky dome. The mesh is a sphere, so its normals are the view directions.
+pub fn paint_sky_dome(mesh: &mut Mesh, colors: &SkyColors, sun_direction: Vec3) {
+    let Some(normals) = mesh
+        .attribute(Mesh::ATTRIBUTE_NORMAL)
+        .and_then(|normals| normals.as_float3())
+    else {
+        return;
+    };
+    let vertex_colors: Vec<[f32; 4]> = normals
+        .iter()
+        .map(|normal| {
+            // Seen from inside of the sphere
+            colors
+                .color(Vec3::from_array(*normal), sun_direction)
+                .to_f32_array()
+        })
+        .collect();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vertex_colors);
+}
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+pub const HOURS_PER_DAY: f32 = 24.0;
+
+/// How far the sun's path is tilted away from the zenith (towards -Z).
+const SUN_PATH_TILT: f32 = 0.5;
+
+/// The in-game clock. The sun rises in the east (+X) at 6:00 and sets in the west at 18:00.
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct TimeOfDay {
+    /// 0..24
+    hours: f32,
+    /// Days passed since the world was started.
+    day: u32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self { hours: 9.0, day: 0 }
+    }
+}
+
+impl TimeOfDay {
+    #[allow(dead_code)]
+    pub fn hours(&self) -> f32 {
+        self.hours
+    }
+
+    #[allow(dead_code)]
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    /// Moves the clock forward, counting the days passed.
+    pub fn advance(&mut self, hours: f32) {
+        let total = self.hours + hours;
+        self.day += (total / HOURS_PER_DAY).floor().max(0.0) as u32;
+        self.hours = total.rem_euclid(HOURS_PER_DAY);
+    }
+
+    /// Unit vector pointing from the ground towards the sun.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.hours - 6.0) / HOURS_PER_DAY * TAU;
+        let height = angle.sin();
+        Vec3::new(
+            angle.cos(),
+            height * SUN_PATH_TILT.cos(),
+            -height * SUN_PATH_TILT.sin(),
+        )
+    }
+
+    /// Unit vector pointing from the ground towards the moon, opposite of the sun.
+    pub fn moon_direction(&self) -> Vec3 {
+        -self.sun_direction()
+    }
+
+    /// 0 at night, 1 in full daylight, in between around sunrise and sunset.
+    pub fn daylight(&self) -> f32 {
+        smoothstep(-0.1, 0.2, self.sun_direction().y)
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.sun_direction().y < 0.0
+    }
+}
+
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
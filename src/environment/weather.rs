@@ -0,0 +1,221 @@
+use super::random::Random;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Real seconds the weather takes to change from one kind to the next.
+const TRANSITION_SECS: f32 = 20.0;
+
+/// In-game hours a weather lasts before it changes by itself.
+const MIN_WEATHER_HOURS: f32 = 2.0;
+const MAX_WEATHER_HOURS: f32 = 8.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Overcast,
+    Rain,
+    Snow,
+    Fog,
+}
+
+impl WeatherKind {
+    pub const ALL: [Self; 5] = [
+        Self::Clear,
+        Self::Overcast,
+        Self::Rain,
+        Self::Snow,
+        Self::Fog,
+    ];
+
+    /// The next kind in `ALL`, used to cycle the weather by hand.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// How often the weather changes to this kind by itself.
+    fn weight(self) -> f32 {
+        match self {
+            Self::Clear => 4.0,
+            Self::Overcast => 3.0,
+            Self::Rain => 2.0,
+            Self::Snow => 1.0,
+            Self::Fog => 1.0,
+        }
+    }
+
+    pub fn conditions(self) -> WeatherConditions {
+        let clear = WeatherConditions {
+            cloud_cover: 0.0,
+            rain: 0.0,
+            snow: 0.0,
+            visibility: 2000.0,
+            wind_speed: 2.0,
+        };
+        match self {
+            Self::Clear => clear,
+            Self::Overcast => WeatherConditions {
+                cloud_cover: 0.8,
+                visibility: 800.0,
+                wind_speed: 4.0,
+                ..clear
+            },
+            Self::Rain => WeatherConditions {
+                cloud_cover: 1.0,
+                rain: 1.0,
+                visibility: 300.0,
+                wind_speed: 6.0,
+                ..clear
+            },
+            Self::Snow => WeatherConditions {
+                cloud_cover: 0.9,
+                snow: 1.0,
+                visibility: 200.0,
+                wind_speed: 3.0,
+                ..clear
+            },
+            Self::Fog => WeatherConditions {
+                cloud_cover: 0.6,
+                visibility: 40.0,
+                wind_speed: 0.5,
+                ..clear
+            },
+        }
+    }
+}
+
+/// What the weather looks like at a moment, blended during transitions.
+#[derive(Clone, Copy)]
+pub struct WeatherConditions {
+    /// 0 for a clear sky, 1 when the sun is hidden completely.
+    pub cloud_cover: f32,
+    /// Intensity of the rain, 0..1.
+    pub rain: f32,
+    /// Intensity of the snowfall, 0..1.
+    pub snow: f32,
+    /// Distance in meters after which the fog hides everything.
+    pub visibility: f32,
+    /// Average wind speed in m/s.
+    pub wind_speed: f32,
+}
+
+impl WeatherConditions {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self {
+            cloud_cover: self.cloud_cover.lerp(other.cloud_cover, t),
+            rain: self.rain.lerp(other.rain, t),
+            snow: self.snow.lerp(other.snow, t),
+            // Fog thickens and clears evenly on a logarithmic scale
+            visibility: self.visibility.ln().lerp(other.visibility.ln(), t).exp(),
+            wind_speed: self.wind_speed.lerp(other.wind_speed, t),
+        }
+    }
+}
+
+/// The current weather and the transition to the next one.
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct Weather {
+    from: WeatherKind,
+    to: WeatherKind,
+    /// 0..1, how far the weather has changed from `from` to `to`.
+    progress: f32,
+    /// In-game hours until the weather changes by itself.
+    hours_left: f32,
+    random: Random,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            from: WeatherKind::Clear,
+            to: WeatherKind::Clear,
+            progress: 1.0,
+            hours_left: MAX_WEATHER_HOURS,
+            random: Random::new(0x5eed),
+        }
+    }
+}
+
+impl Weather {
+    /// The kind the weather is changing to, or is already.
+    pub fn kind(&self) -> WeatherKind {
+        self.to
+    }
+
+    pub fn conditions(&self) -> WeatherConditions {
+        self.from
+            .conditions()
+            .lerp(self.to.conditions(), self.progress)
+    }
+
+    /// Starts changing the weather to `kind`, which then lasts a random number of hours.
+    pub fn change_to(&mut self, kind: WeatherKind) {
+        // A transition still in its first half starts over from where it came from
+        if self.progress >= 0.5 {
+            self.from = self.to;
+        }
+        self.to = kind;
+        self.progress = if self.from == kind { 1.0 } else { 0.0 };
+        self.hours_left = self.random.range(MIN_WEATHER_HOURS, MAX_WEATHER_HOURS);
+    }
+
+    /// Moves the transition forward and, if `dynamic`, picks the next weather when this one is over.
+    pub fn advance(&mut self, real_seconds: f32, game_hours: f32, dynamic: bool) {
+        if self.progress < 1.0 {
+            self.progress = (self.progress + real_seconds / TRANSITION_SECS).min(1.0);
+        }
+        if !dynamic {
+            return;
+        }
+        self.hours_left -= game_hours;
+        if self.hours_left <= 0.0 {
+            let next = self.pick_random();
+            self.change_to(next);
+        }
+    }
+
+    fn pick_random(&mut self) -> WeatherKind {
+        let total: f32 = WeatherKind::ALL.iter().map(|kind| kind.weight()).sum();
+        let mut choice = self.random.range(0.0, total);
+        for kind in WeatherKind::ALL {
+            if choice < kind.weight() {
+                return kind;
+            }
+            choice -= kind.weight();
+        }
+        WeatherKind::Clear
+    }
+}
+
+/// Wind blowing over the whole world. Other systems sample it, e.g. to move particles or plants.
+#[derive(Resource, Default)]
+pub struct Wind {
+    /// Average wind in m/s, horizontal.
+    velocity: Vec3,
+    /// Seconds since startup, for the gusts.
+    elapsed: f32,
+}
+
+impl Wind {
+    /// Average wind without the gusts.
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    /// Wind at a world position, with gusts travelling along the wind.
+    pub fn sample(&self, position: Vec3) -> Vec3 {
+        let along = position.dot(self.velocity.normalize_or_zero());
+        let phase = self.elapsed * 0.8 - along * 0.05;
+        let gust = 1.0 + 0.4 * phase.sin() * (phase * 0.37 + 1.3).sin();
+        self.velocity * gust
+    }
+
+    /// Turns the wind slowly and adapts its speed to the weather.
+    pub fn update(&mut self, conditions: &WeatherConditions, delta_secs: f32) {
+        self.elapsed += delta_secs;
+        let angle = 0.6 + 0.8 * (self.elapsed * 0.01).sin();
+        let target = Vec3::new(angle.cos(), 0.0, angle.sin()) * conditions.wind_speed;
+        self.velocity = self.velocity.lerp(target, (delta_secs * 0.5).min(1.0));
+    }
+}
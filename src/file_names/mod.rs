@@ -0,0 +1,71 @@
+/// Device names Windows reserves in every directory, with any extension.
+const RESERVED_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+/// Numbered device names Windows reserves, `COM1` to `COM9` and `LPT1` to `LPT9`.
+const RESERVED_NUMBERED_NAMES: [&str; 2] = ["COM", "LPT"];
+
+/// Names the player types which become file or directory names: save slots, blueprints and exports.
+/// They must not be able to escape the directory they are written to, and must be usable on
+/// every platform, so a save made on Linux can be copied to Windows.
+pub fn is_valid_file_stem(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        // Windows drops trailing dots and spaces, "save." would be the file "save"
+        && !name.ends_with(['.', ' '])
+        && !name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|'])
+        && !name.contains(char::is_control)
+        && !is_reserved_name(name)
+}
+
+/// Whether Windows reserves the name for a device, ignoring case and extension: "con.ron" too.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .to_ascii_uppercase();
+    RESERVED_NAMES.contains(&stem.as_str())
+        || RESERVED_NUMBERED_NAMES.iter().any(|prefix| {
+            stem.strip_prefix(prefix)
+                .is_some_and(|digit| matches!(digit.as_bytes(), [b'1'..=b'9']))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_names() {
+        assert!(is_valid_file_stem("My castle"));
+        assert!(is_valid_file_stem("castle.v2"));
+        assert!(is_valid_file_stem("console"));
+        assert!(is_valid_file_stem("COM10"));
+    }
+
+    #[test]
+    fn rejects_paths() {
+        assert!(!is_valid_file_stem(""));
+        assert!(!is_valid_file_stem(".."));
+        assert!(!is_valid_file_stem("../saves"));
+        assert!(!is_valid_file_stem("C:castle"));
+        assert!(!is_valid_file_stem("cas\u{7}tle"));
+    }
+
+    #[test]
+    fn rejects_windows_reserved_names() {
+        assert!(!is_valid_file_stem("CON"));
+        assert!(!is_valid_file_stem("nul"));
+        assert!(!is_valid_file_stem("Aux.ron"));
+        assert!(!is_valid_file_stem("com1"));
+        assert!(!is_valid_file_stem("LPT9.glb"));
+    }
+
+    #[test]
+    fn rejects_trailing_dots() {
+        assert!(!is_valid_file_stem("castle."));
+        assert!(!is_valid_file_stem("castle ."));
+    }
+}
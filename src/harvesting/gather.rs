@@ -0,0 +1,130 @@
+use super::nodes::HarvestNodeKind;
+use super::{HarvestNode, HarvestedNodes};
+use crate::inventory::Inventory;
+use crate::settings::GameSettings;
+use crate::universal_camera_controller::UniCamController;
+use bevy::ecs::system::SystemParam;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, RayCastSettings};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+/// Farthest a node can be gathered from, in meters.
+const GATHER_DISTANCE: f32 = 6.0;
+
+/// The node in the middle of the view and how long it has been gathered.
+#[derive(Resource, Default)]
+pub struct Gathering {
+    target: Option<(Entity, HarvestNodeKind)>,
+    secs: f32,
+}
+
+/// Finds the node in the middle of the view.
+#[derive(SystemParam)]
+pub struct NodePicker<'w, 's> {
+    camera: Single<'w, &'static GlobalTransform, With<UniCamController>>,
+    ray_cast: MeshRayCast<'w, 's>,
+    parents: Query<'w, 's, &'static Parent>,
+    nodes: Query<'w, 's, &'static HarvestNode>,
+}
+
+impl NodePicker<'_, '_> {
+    fn targeted(&mut self) -> Option<(Entity, HarvestNodeKind)> {
+        let parents = &self.parents;
+        let nodes = &self.nodes;
+        // The ray passes anything which isn't part of a node, like the preview of a building
+        let node_of = |entity: Entity| {
+            std::iter::once(entity)
+                .chain(parents.iter_ancestors(entity))
+                .find(|ancestor| nodes.contains(*ancestor))
+        };
+        let filter = |entity: Entity| node_of(entity).is_some();
+        let settings = RayCastSettings::default().with_filter(&filter);
+        let ray = Ray3d::new(self.camera.translation(), self.camera.forward());
+        let node = self
+            .ray_cast
+            .cast_ray(ray, &settings)
+            .first()
+            .filter(|(_, hit)| hit.distance <= GATHER_DISTANCE)
+            .and_then(|(entity, _)| node_of(*entity))?;
+        Some((node, nodes.get(node).ok()?.kind))
+    }
+}
+
+/// Gathers the node in the middle of the view while the gather key or the left mouse button is held.
+/// Letting go or looking away starts over.
+pub fn gather_targeted_node(
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    game_settings: Res<GameSettings>,
+    time: Res<Time>,
+    mut picker: NodePicker,
+    mut gathering: ResMut<Gathering>,
+    mut gathered_events: EventWriter<NodeGatheredEvent>,
+) {
+    let target = picker.targeted();
+    let held = keys.pressed(game_settings.keyboard.gather) || buttons.pressed(MouseButton::Left);
+    if target != gathering.target || !held {
+        gathering.secs = 0.0;
+    }
+    gathering.target = target;
+    let Some((entity, kind)) = target.filter(|_| held) else {
+        return;
+    };
+
+    gathering.secs += time.delta_secs();
+    if gathering.secs >= kind.gather_secs() {
+        gathering.secs = 0.0;
+        gathered_events.send(NodeGatheredEvent(entity));
+    }
+}
+
+/// A node has been gathered.
+#[derive(Event)]
+pub struct NodeGatheredEvent(pub Entity);
+
+/// Puts the yield of gathered nodes into the inventory and removes them until they grow back.
+pub fn finish_gathering(
+    mut commands: Commands,
+    mut gathered_events: EventReader<NodeGatheredEvent>,
+    nodes: Query<&HarvestNode>,
+    mut inventory: ResMut<Inventory>,
+    mut harvested: ResMut<HarvestedNodes>,
+) {
+    for event in gathered_events.read() {
+        let Ok(node) = nodes.get(event.0) else {
+            continue;
+        };
+        inventory.add(&node.kind.yields());
+        harvested.0.insert(node.id, node.kind.respawn_secs());
+        commands.entity(event.0).despawn_recursive();
+        info!("Gathered {:?}: {}", node.kind, node.kind.yields());
+    }
+}
+
+/// Names the node in the middle of the view and shows how far gathering it is.
+pub fn gather_progress_ui(
+    mut contexts: EguiContexts,
+    gathering: Res<Gathering>,
+    game_settings: Res<GameSettings>,
+) {
+    let Some((_, kind)) = gathering.target else {
+        return;
+    };
+    egui::Area::new(egui::Id::new("gathering"))
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 60.0])
+        .show(contexts.ctx_mut(), |ui| {
+            if gathering.secs > 0.0 {
+                ui.add(
+                    egui::ProgressBar::new(gathering.secs / kind.gather_secs())
+                        .desired_width(160.0)
+                        .text(format!("{kind:?}")),
+                );
+            } else {
+                ui.label(format!(
+                    "Hold {:?} to gather {kind:?} ({})",
+                    game_settings.keyboard.gather,
+                    kind.yields()
+                ));
+            }
+        });
+}
@@ -0,0 +1,205 @@
+mod gather;
+mod nodes;
+
+use crate::building::{BuildingMode, BuildingReadinessState};
+use crate::keyboard_focus::shortcuts_enabled;
+use crate::terrain::{Terrain, TerrainSettings, TerrainToolsState};
+use crate::world_streaming::WorldChunks;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use gather::{
+    finish_gathering, gather_progress_ui, gather_targeted_node, Gathering, NodeGatheredEvent,
+};
+use nodes::{scatter_nodes, HarvestNodeAssets, HarvestNodeKind, ScatteredNode};
+use serde::{Deserialize, Serialize};
+
+pub use nodes::HarvestNodeId;
+
+/// A tree, rock or deposit resources can be gathered from, scattered on the terrain.
+#[derive(Component)]
+pub struct HarvestNode {
+    pub id: HarvestNodeId,
+    pub kind: HarvestNodeKind,
+}
+
+/// Gathered nodes with the seconds until they grow back.
+/// They keep growing while their chunk isn't loaded.
+#[derive(Resource, Default)]
+pub struct HarvestedNodes(pub HashMap<HarvestNodeId, f32>);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HarvestedNodeSave {
+    pub id: HarvestNodeId,
+    pub respawn_secs: f32,
+}
+
+impl HarvestedNodes {
+    pub fn saves(&self) -> Vec<HarvestedNodeSave> {
+        self.0
+            .iter()
+            .map(|(id, respawn_secs)| HarvestedNodeSave {
+                id: *id,
+                respawn_secs: *respawn_secs,
+            })
+            .collect()
+    }
+
+    pub fn restore(&mut self, saves: &[HarvestedNodeSave]) {
+        self.0 = saves
+            .iter()
+            .map(|save| (save.id, save.respawn_secs))
+            .collect();
+    }
+}
+
+/// Chunks whose nodes are spawned.
+#[derive(Resource, Default)]
+struct NodeChunks(HashSet<IVec2>);
+
+pub struct HarvestingPlugin;
+
+impl Plugin for HarvestingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HarvestedNodes>()
+            .init_resource::<NodeChunks>()
+            .init_resource::<HarvestNodeAssets>()
+            .init_resource::<Gathering>()
+            .add_event::<NodeGatheredEvent>()
+            .add_systems(
+                Update,
+                (
+                    despawn_unloaded_nodes,
+                    spawn_loaded_nodes,
+                    regrow_harvested_nodes,
+                )
+                    .chain()
+                    .run_if(
+                        resource_exists::<Terrain>.and(in_state(BuildingReadinessState::Ready)),
+                    ),
+            )
+            // Clicks and the cursor belong to the building and terrain tools while they are open
+            .add_systems(
+                Update,
+                (
+                    gather_targeted_node.run_if(shortcuts_enabled),
+                    finish_gathering,
+                    gather_progress_ui,
+                )
+                    .chain()
+                    .run_if(
+                        in_state(BuildingMode::Disabled).and(in_state(TerrainToolsState::Disabled)),
+                    ),
+            );
+    }
+}
+
+/// Spawns nodes on the terrain.
+#[derive(SystemParam)]
+struct NodeSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    terrain: Res<'w, Terrain>,
+    terrain_settings: Res<'w, TerrainSettings>,
+    assets: Res<'w, HarvestNodeAssets>,
+}
+
+impl NodeSpawner<'_, '_> {
+    fn nodes(&self, chunk: IVec2) -> Vec<ScatteredNode> {
+        scatter_nodes(chunk, self.terrain_settings.chunk_size)
+    }
+
+    fn spawn(&mut self, node: &ScatteredNode) {
+        let Some(ground) = self.terrain.height_at(node.position.x, node.position.y) else {
+            return;
+        };
+        self.commands
+            .spawn((
+                HarvestNode {
+                    id: node.id,
+                    kind: node.kind,
+                },
+                Transform::from_xyz(node.position.x, ground, node.position.y)
+                    .with_rotation(Quat::from_rotation_y(node.yaw))
+                    .with_scale(Vec3::splat(node.scale)),
+                Visibility::default(),
+            ))
+            .with_children(|parent| {
+                for (mesh, material, transform) in self.assets.parts(node.kind) {
+                    parent.spawn((
+                        Mesh3d(mesh.clone()),
+                        MeshMaterial3d(material.clone()),
+                        *transform,
+                    ));
+                }
+            });
+    }
+}
+
+fn despawn_unloaded_nodes(
+    mut commands: Commands,
+    world_chunks: Res<WorldChunks>,
+    mut node_chunks: ResMut<NodeChunks>,
+    nodes: Query<(Entity, &HarvestNode)>,
+) {
+    let unloaded: HashSet<IVec2> = node_chunks
+        .0
+        .iter()
+        .filter(|chunk| !world_chunks.is_loaded(**chunk))
+        .copied()
+        .collect();
+    if unloaded.is_empty() {
+        return;
+    }
+    for (entity, node) in nodes.iter() {
+        if unloaded.contains(&node.id.chunk) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    node_chunks.0.retain(|chunk| !unloaded.contains(chunk));
+}
+
+/// Scatters the nodes of newly loaded chunks, except the ones still growing back.
+fn spawn_loaded_nodes(
+    world_chunks: Res<WorldChunks>,
+    mut node_chunks: ResMut<NodeChunks>,
+    harvested: Res<HarvestedNodes>,
+    mut spawner: NodeSpawner,
+) {
+    for chunk in world_chunks.loaded() {
+        if !node_chunks.0.insert(chunk) {
+            continue;
+        }
+        for node in spawner.nodes(chunk) {
+            if !harvested.0.contains_key(&node.id) {
+                spawner.spawn(&node);
+            }
+        }
+    }
+}
+
+fn regrow_harvested_nodes(
+    time: Res<Time>,
+    mut harvested: ResMut<HarvestedNodes>,
+    node_chunks: Res<NodeChunks>,
+    mut spawner: NodeSpawner,
+) {
+    let delta = time.delta_secs();
+    let mut grown = Vec::new();
+    harvested.0.retain(|id, respawn_secs| {
+        *respawn_secs -= delta;
+        if *respawn_secs > 0.0 {
+            return true;
+        }
+        grown.push(*id);
+        false
+    });
+    // Nodes of chunks which aren't loaded are spawned with their chunk
+    for id in grown
+        .into_iter()
+        .filter(|id| node_chunks.0.contains(&id.chunk))
+    {
+        if let Some(node) = spawner.nodes(id.chunk).get(id.index as usize) {
+            spawner.spawn(node);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::environment::Random;
+use crate::inventory::{ResourceCost, ResourceKind};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Something in the world resources can be gathered from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HarvestNodeKind {
+    Tree,
+    Rock,
+    ClayDeposit,
+    IronOre,
+}
+
+impl HarvestNodeKind {
+    pub const ALL: [HarvestNodeKind; 4] = [
+        HarvestNodeKind::Tree,
+        HarvestNodeKind::Rock,
+        HarvestNodeKind::ClayDeposit,
+        HarvestNodeKind::IronOre,
+    ];
+
+    /// What gathering the node once yields.
+    pub fn yields(self) -> ResourceCost {
+        match self {
+            HarvestNodeKind::Tree => ResourceCost::new(&[(ResourceKind::Wood, 5)]),
+            HarvestNodeKind::Rock => ResourceCost::new(&[(ResourceKind::Stone, 4)]),
+            HarvestNodeKind::ClayDeposit => ResourceCost::new(&[(ResourceKind::Clay, 3)]),
+            HarvestNodeKind::IronOre => ResourceCost::new(&[(ResourceKind::Iron, 2)]),
+        }
+    }
+
+    /// Seconds the gather key has to be held.
+    pub fn gather_secs(self) -> f32 {
+        match self {
+            HarvestNodeKind::Tree => 3.0,
+            HarvestNodeKind::Rock => 4.0,
+            HarvestNodeKind::ClayDeposit => 2.5,
+            HarvestNodeKind::IronOre => 6.0,
+        }
+    }
+
+    /// Seconds until a gathered node grows back.
+    pub fn respawn_secs(self) -> f32 {
+        match self {
+            HarvestNodeKind::Tree => 300.0,
+            HarvestNodeKind::Rock => 600.0,
+            HarvestNodeKind::ClayDeposit => 420.0,
+            HarvestNodeKind::IronOre => 900.0,
+        }
+    }
+
+    /// Nodes per chunk on average.
+    fn density(self) -> f32 {
+        match self {
+            HarvestNodeKind::Tree => 2.0,
+            HarvestNodeKind::Rock => 0.8,
+            HarvestNodeKind::ClayDeposit => 0.25,
+            HarvestNodeKind::IronOre => 0.1,
+        }
+    }
+}
+
+/// A node by the chunk it is scattered in and its index among the chunk's nodes.
+/// Nodes are scattered the same way every time, so this identifies them across saves.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HarvestNodeId {
+    pub chunk: IVec2,
+    pub index: u32,
+}
+
+/// Where a node is placed in its chunk, before it is put on the terrain.
+pub struct ScatteredNode {
+    pub id: HarvestNodeId,
+    pub kind: HarvestNodeKind,
+    pub position: Vec2,
+    pub yaw: f32,
+    pub scale: f32,
+}
+
+/// The nodes of a chunk, always the same ones for the same chunk.
+pub fn scatter_nodes(chunk: IVec2, chunk_size: f32) -> Vec<ScatteredNode> {
+    // Any odd multipliers mix the coordinates well enough for scattering
+    let seed = (chunk.x as u32)
+        .wrapping_mul(0x9e37_79b1)
+        .wrapping_add((chunk.y as u32).wrapping_mul(0x85eb_ca77))
+        ^ 0x4a7e_5eed;
+    let mut random = Random::new(seed);
+    let origin = chunk.as_vec2() * chunk_size;
+
+    let mut nodes = Vec::new();
+    for kind in HarvestNodeKind::ALL {
+        // The fraction of the density is the chance of one more node
+        let density = kind.density();
+        let count = density.floor() as u32 + u32::from(random.next_f32() < density.fract());
+        for _ in 0..count {
+            nodes.push(ScatteredNode {
+                id: HarvestNodeId {
+                    chunk,
+                    index: nodes.len() as u32,
+                },
+                kind,
+                position: origin
+                    + Vec2::new(random.range(0.0, chunk_size), random.range(0.0, chunk_size)),
+                yaw: random.range(0.0, std::f32::consts::TAU),
+                scale: random.range(0.8, 1.25),
+            });
+        }
+    }
+    nodes
+}
+
+/// Mesh, material and transform of one part of a node, before it's added to the assets.
+type NodeShape = (Mesh, StandardMaterial, Transform);
+/// A primitive shape of a node, positioned relative to the node's origin on the ground.
+type NodePart = (Handle<Mesh>, Handle<StandardMaterial>, Transform);
+
+/// Meshes and materials of the nodes, one set of primitive shapes per kind.
+#[derive(Resource)]
+pub struct HarvestNodeAssets {
+    parts: Vec<(HarvestNodeKind, Vec<NodePart>)>,
+}
+
+impl HarvestNodeAssets {
+    pub fn parts(&self, kind: HarvestNodeKind) -> &[NodePart] {
+        self.parts
+            .iter()
+            .find(|(part_kind, _)| *part_kind == kind)
+            .map(|(_, parts)| parts.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+impl FromWorld for HarvestNodeAssets {
+    fn from_world(world: &mut World) -> Self {
+        let rough = |color: Color| StandardMaterial {
+            base_color: color,
+            perceptual_roughness: 0.95,
+            ..default()
+        };
+        let shapes: [(HarvestNodeKind, Vec<NodeShape>); 4] = [
+            (
+                HarvestNodeKind::Tree,
+                vec![
+                    (
+                        Cylinder::new(0.2, 3.0).into(),
+                        rough(Color::srgb(0.33, 0.22, 0.13)),
+                        Transform::from_xyz(0.0, 1.5, 0.0),
+                    ),
+                    (
+                        Cone::new(1.4, 3.5).into(),
+                        rough(Color::srgb(0.13, 0.35, 0.15)),
+                        Transform::from_xyz(0.0, 4.2, 0.0),
+                    ),
+                ],
+            ),
+            (
+                HarvestNodeKind::Rock,
+                vec![(
+                    Sphere::new(0.8).into(),
+                    rough(Color::srgb(0.45, 0.45, 0.43)),
+                    Transform::from_xyz(0.0, 0.3, 0.0).with_scale(Vec3::new(1.3, 0.7, 1.0)),
+                )],
+            ),
+            (
+                HarvestNodeKind::ClayDeposit,
+                vec![(
+                    Sphere::new(1.0).into(),
+                    rough(Color::srgb(0.6, 0.33, 0.2)),
+                    Transform::from_xyz(0.0, -0.1, 0.0).with_scale(Vec3::new(1.4, 0.35, 1.2)),
+                )],
+            ),
+            (
+                HarvestNodeKind::IronOre,
+                vec![
+                    (
+                        Sphere::new(0.7).into(),
+                        rough(Color::srgb(0.3, 0.28, 0.27)),
+                        Transform::from_xyz(0.0, 0.25, 0.0).with_scale(Vec3::new(1.2, 0.8, 1.0)),
+                    ),
+                    (
+                        Cuboid::new(0.25, 0.25, 0.25).into(),
+                        StandardMaterial {
+                            base_color: Color::srgb(0.55, 0.35, 0.25),
+                            metallic: 0.8,
+                            perceptual_roughness: 0.4,
+                            ..default()
+                        },
+                        Transform::from_xyz(0.3, 0.7, 0.1)
+                            .with_rotation(Quat::from_rotation_z(0.6)),
+                    ),
+                ],
+            ),
+        ];
+
+        let mut parts = Vec::new();
+        for (kind, kind_shapes) in shapes {
+            let kind_parts = kind_shapes
+                .into_iter()
+                .map(|(mesh, material, transform)| {
+                    let mesh = world.resource_mut::<Assets<Mesh>>().add(mesh);
+                    let material = world
+                        .resource_mut::<Assets<StandardMaterial>>()
+                        .add(material);
+                    (mesh, material, transform)
+                })
+                .collect();
+            parts.push((kind, kind_parts));
+        }
+        Self { parts }
+    }
+}
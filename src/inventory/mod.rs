@@ -0,0 +1,83 @@
+mod resources;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+pub use resources::{ResourceCost, ResourceKind};
+
+/// Whether buildings cost resources. Chosen when a world is created and stored with it.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    /// Building is free and unlimited.
+    #[default]
+    Creative,
+    /// Buildings cost resources from the inventory, demolishing them refunds a part.
+    Survival,
+}
+
+/// Resources the player carries. Only used in survival mode, stored with the world.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct Inventory(pub ResourceCost);
+
+impl Default for Inventory {
+    /// Enough for a small shelter to start with.
+    fn default() -> Self {
+        Self(ResourceCost::new(&[
+            (ResourceKind::Wood, 40),
+            (ResourceKind::Stone, 20),
+            (ResourceKind::Clay, 10),
+        ]))
+    }
+}
+
+impl Inventory {
+    pub fn count(&self, kind: ResourceKind) -> u32 {
+        self.0.amount(kind)
+    }
+
+    pub fn can_afford(&self, cost: &ResourceCost) -> bool {
+        self.0.covers(cost)
+    }
+
+    /// Takes the cost out of the inventory. Nothing is taken if it can't be afforded.
+    pub fn spend(&mut self, cost: &ResourceCost) -> bool {
+        if !self.can_afford(cost) {
+            return false;
+        }
+        self.0.subtract(cost);
+        true
+    }
+
+    pub fn add(&mut self, resources: &ResourceCost) {
+        self.0.add(resources);
+    }
+}
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .init_resource::<Inventory>()
+            .add_systems(
+                Update,
+                inventory_window.run_if(resource_equals(GameMode::Survival)),
+            );
+    }
+}
+
+fn inventory_window(mut contexts: EguiContexts, inventory: Res<Inventory>) {
+    egui::Window::new("Inventory")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("inventory").show(ui, |ui| {
+                for kind in ResourceKind::ALL {
+                    ui.label(format!("{kind:?}"));
+                    ui.label(inventory.count(kind).to_string());
+                    ui.end_row();
+                }
+            });
+        });
+}
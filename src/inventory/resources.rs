@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Raw materials buildings are made of.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourceKind {
+    Wood,
+    Stone,
+    Clay,
+    Iron,
+}
+
+impl ResourceKind {
+    pub const ALL: [ResourceKind; 4] = [
+        ResourceKind::Wood,
+        ResourceKind::Stone,
+        ResourceKind::Clay,
+        ResourceKind::Iron,
+    ];
+}
+
+/// Amounts of resources, e.g. what a building costs.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct ResourceCost(pub BTreeMap<ResourceKind, u32>);
+
+impl ResourceCost {
+    pub fn new(amounts: &[(ResourceKind, u32)]) -> Self {
+        let mut cost = Self::default();
+        for (kind, amount) in amounts {
+            *cost.0.entry(*kind).or_default() += amount;
+        }
+        cost
+    }
+
+    pub fn amount(&self, kind: ResourceKind) -> u32 {
+        self.0.get(&kind).copied().unwrap_or_default()
+    }
+
+    /// Whether there is at least as much of every resource as in `other`.
+    pub fn covers(&self, other: &ResourceCost) -> bool {
+        other
+            .0
+            .iter()
+            .all(|(kind, amount)| self.amount(*kind) >= *amount)
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.0.values().all(|amount| *amount == 0)
+    }
+
+    pub fn add(&mut self, other: &ResourceCost) {
+        for (kind, amount) in &other.0 {
+            *self.0.entry(*kind).or_default() += amount;
+        }
+    }
+
+    /// Takes `other` away, resources running out stay at 0.
+    pub fn subtract(&mut self, other: &ResourceCost) {
+        for (kind, amount) in &other.0 {
+            let current = self.0.entry(*kind).or_default();
+            *current = current.saturating_sub(*amount);
+        }
+    }
+
+    /// The amounts multiplied by `fraction`, rounded down.
+    pub fn scaled(&self, fraction: f32) -> ResourceCost {
+        ResourceCost(
+            self.0
+                .iter()
+                .map(|(kind, amount)| (*kind, (*amount as f32 * fraction) as u32))
+                .collect(),
+        )
+    }
+}
+
+impl Display for ResourceCost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_free() {
+            return write!(f, "free");
+        }
+        let amounts: Vec<String> = self
+            .0
+            .iter()
+            .filter(|(_, amount)| **amount > 0)
+            .map(|(kind, amount)| format!("{amount} {kind:?}"))
+            .collect();
+        write!(f, "{}", amounts.join(", "))
+    }
+}
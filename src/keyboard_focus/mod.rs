@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPreUpdateSet};
+
+/// Whether an egui text field has the keyboard focus, e.g. the name of a save being typed.
+/// Updated at the start of every frame, before any shortcut is handled.
+#[derive(Resource, Default)]
+pub struct TextInputFocus(bool);
+
+/// Run condition of the systems handling keyboard shortcuts, so typing doesn't trigger them.
+pub fn shortcuts_enabled(focus: Res<TextInputFocus>) -> bool {
+    !focus.0
+}
+
+pub struct KeyboardFocusPlugin;
+
+impl Plugin for KeyboardFocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextInputFocus>().add_systems(
+            PreUpdate,
+            track_text_input_focus.after(EguiPreUpdateSet::BeginPass),
+        );
+    }
+}
+
+fn track_text_input_focus(mut contexts: EguiContexts, mut focus: ResMut<TextInputFocus>) {
+    let typing = contexts
+        .try_ctx_mut()
+        .is_some_and(|ctx| ctx.wants_keyboard_input());
+    focus.0 = typing;
+}
@@ -1,11 +1,21 @@
 mod building;
+mod debug_overlay;
+mod environment;
+mod file_names;
+mod harvesting;
+mod inventory;
+mod keyboard_focus;
 mod main_menu;
+mod material_library;
+mod physics;
 mod settings;
+mod terrain;
 mod universal_camera_controller;
+mod world_save;
+mod world_streaming;
 
 use crate::universal_camera_controller::SphericalCamera;
 use bevy::core_pipeline::{bloom::Bloom, motion_blur::MotionBlur};
-use bevy::image::ImageLoaderSettings;
 use bevy::prelude::*;
 use bevy::render::{
     settings::{Backends, RenderCreation, WgpuSettings},
@@ -14,10 +24,19 @@ use bevy::render::{
 use bevy::window::*;
 use bevy_egui::EguiPlugin;
 use building::BuildingPlugin;
+use debug_overlay::DebugOverlayPlugin;
+use environment::EnvironmentPlugin;
+use harvesting::HarvestingPlugin;
+use inventory::InventoryPlugin;
+use keyboard_focus::KeyboardFocusPlugin;
 use main_menu::MainMenuPlugin;
+use material_library::{MaterialLibrary, MaterialLibraryPlugin};
+use physics::GamePhysicsPlugin;
 use settings::GameSettingsPlugin;
-use std::path::PathBuf;
+use terrain::{TerrainMaterials, TerrainPlugin, TerrainSettings};
 use universal_camera_controller::{UniCamController, UniCamPlugin};
+use world_save::WorldSavePlugin;
+use world_streaming::WorldStreamingPlugin;
 
 fn main() {
     App::new()
@@ -45,9 +64,19 @@ fn main() {
         )
         .add_plugins(GameSettingsPlugin)
         .add_plugins(EguiPlugin)
+        .add_plugins(KeyboardFocusPlugin)
         .add_plugins(MainMenuPlugin)
+        .add_plugins(MaterialLibraryPlugin)
         .add_plugins(UniCamPlugin)
+        .add_plugins(EnvironmentPlugin)
+        .add_plugins(TerrainPlugin)
+        .add_plugins(InventoryPlugin)
+        .add_plugins(HarvestingPlugin)
         .add_plugins(BuildingPlugin)
+        .add_plugins(WorldSavePlugin)
+        .add_plugins(WorldStreamingPlugin)
+        .add_plugins(DebugOverlayPlugin)
+        .add_plugins(GamePhysicsPlugin)
         .add_systems(Startup, setup_tmp_world_env)
         .add_systems(Startup, spawn_wall)
         .run();
@@ -55,80 +84,21 @@ fn main() {
 
 fn setup_tmp_world_env(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
+    material_library: Res<MaterialLibrary>,
+    terrain_settings: Res<TerrainSettings>,
 ) {
-    let material_dir = PathBuf::from("materials").join("Pond Side Grassy and Muddy Land 2k");
-
-    let color = material_dir.join("color.ktx2"); // toktx --t2 --genmipmap --encode uastc --uastc_quality 3 --filter lanczos4 --convert_oetf srgb --assign_oetf srgb --zcmp 20 color.ktx2 color.png
-    let normal = material_dir.join("normal_opengl.ktx2"); // toktx --t2 --genmipmap --encode uastc --uastc_quality 3 --filter lanczos4 --convert_oetf srgb --assign_oetf linear --zcmp 20 normal_opengl.ktx2 normal_opengl.png
-    let ao = material_dir.join("ao.ktx2"); // toktx --t2 --genmipmap --encode uastc --uastc_quality 3 --filter lanczos4 --convert_oetf linear --assign_oetf linear --zcmp 20 ao.ktx2 ao.png
-    let metallic_roughness = material_dir.join("metallic_roughness.ktx2"); // toktx --t2 --genmipmap --encode uastc --uastc_quality 3 --filter lanczos4 --convert_oetf linear --assign_oetf linear --zcmp 20 metallic_roughness.ktx2 metallic_roughness.png
-
-    let material = materials.add(StandardMaterial {
-        base_color_texture: Some(
-            asset_server.load_with_settings(color, |settings: &mut ImageLoaderSettings| {
-                settings.is_srgb = true
-            }),
-        ),
-        occlusion_texture: Some(
-            asset_server.load_with_settings(ao, |settings: &mut ImageLoaderSettings| {
-                settings.is_srgb = false
-            }),
-        ),
-        normal_map_texture: Some(
-            asset_server.load_with_settings(normal, |settings: &mut ImageLoaderSettings| {
-                settings.is_srgb = true
-            }),
-        ),
-        metallic_roughness_texture: Some(
-            asset_server
-                .load_with_settings(metallic_roughness, |settings: &mut ImageLoaderSettings| {
-                    settings.is_srgb = false
-                }),
-        ),
-        metallic: 1.0,
-        perceptual_roughness: 1.0,
-        ..default()
-    });
-
-    let mut mesh = Plane3d::default().mesh().size(1.0, 1.0).build();
-    mesh.generate_tangents()
-        .expect("Failed to generate tangents");
-
-    let mesh_handle = meshes.add(mesh);
-
-    // Ground
-    commands.spawn((
-        Mesh3d(mesh_handle.clone()),
-        MeshMaterial3d(material.clone()),
-        Transform::from_translation(Vec3::splat(0.0)).with_scale(Vec3::splat(2.0)),
-    ));
-
-    let map_size = 50 / 2; // 50 m. 1px = 1m.
-    for x in -map_size..map_size {
-        for z in -map_size..map_size {
-            // Ground
-            commands.spawn((
-                Mesh3d(mesh_handle.clone()),
-                MeshMaterial3d(material.clone()),
-                Transform::from_translation(Vec3::new(x as f32 * 2.0, 0.0, z as f32 * 2.0))
-                    .with_scale(Vec3::splat(2.0)),
-            ));
-        }
-    }
-
-    // Light
-    commands.spawn((
-        PointLight {
-            shadows_enabled: true,
-            intensity: 5_000_000.0,
-            color: Color::srgb(1.0, 0.95, 0.92),
-            ..default()
-        },
-        Transform::from_xyz(3.0, 6.0, 1.0),
-    ));
+    let terrain_materials = terrain_settings
+        .layers
+        .iter()
+        .map(|name| {
+            material_library.get(name).unwrap_or_else(|| {
+                warn!("Terrain material \"{name}\" not found in the material library");
+                materials.add(StandardMaterial::default())
+            })
+        })
+        .collect();
+    commands.insert_resource(TerrainMaterials(terrain_materials));
 
     // Camera
     commands.spawn((
@@ -1,5 +1,8 @@
-use crate::main_menu::ShowSettingsUiState;
+use crate::inventory::GameMode;
+use crate::main_menu::{MainMenuActivityState, ShowSaveSlotsUiState, ShowSettingsUiState};
 use crate::universal_camera_controller::{UniCamChangeStateEvent, UniCamState};
+use crate::world_save::NewWorldEvent;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 use bevy_egui::{egui, EguiContexts};
@@ -15,20 +18,64 @@ pub fn enter_main_menu(
 
 pub fn exit_main_menu() {}
 
+/// Starts a new world with the game mode picked for it.
+#[derive(SystemParam)]
+pub struct NewWorldForm<'w, 's> {
+    game_mode: Local<'s, GameMode>,
+    evw_new_world: EventWriter<'w, NewWorldEvent>,
+    main_menu_state: ResMut<'w, NextState<MainMenuActivityState>>,
+}
+
+impl NewWorldForm<'_, '_> {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("New world").clicked() {
+                self.evw_new_world.send(NewWorldEvent(*self.game_mode));
+                self.main_menu_state.set(MainMenuActivityState::Inactive);
+            }
+            let mut survival = *self.game_mode == GameMode::Survival;
+            if ui
+                .checkbox(&mut survival, "Survival mode")
+                .on_hover_text(
+                    "Buildings cost resources. Can't be changed once the world is created",
+                )
+                .changed()
+            {
+                *self.game_mode = if survival {
+                    GameMode::Survival
+                } else {
+                    GameMode::Creative
+                };
+            }
+        });
+    }
+}
+
 pub fn main_menu(
     mut contexts: EguiContexts,
     mut exit_events: EventWriter<AppExit>,
     current_settings_state: Res<State<ShowSettingsUiState>>,
     mut settings_state: ResMut<NextState<ShowSettingsUiState>>,
+    current_save_slots_state: Res<State<ShowSaveSlotsUiState>>,
+    mut save_slots_state: ResMut<NextState<ShowSaveSlotsUiState>>,
+    mut new_world_form: NewWorldForm,
 ) {
     let settings_btn_state = match current_settings_state.get() {
         ShowSettingsUiState::Inactive => true,
         ShowSettingsUiState::Active => false,
     };
 
+    let saves_btn_state = match current_save_slots_state.get() {
+        ShowSaveSlotsUiState::Inactive => true,
+        ShowSaveSlotsUiState::Active => false,
+    };
+
     egui::Window::new("Main menu").show(contexts.ctx_mut(), |ui| {
-        ui.button("Start").clicked().then(|| {
-            // later...
+        new_world_form.show(ui);
+        ui.add_enabled_ui(saves_btn_state, |ui| {
+            if ui.button("Saves").clicked() {
+                save_slots_state.set(ShowSaveSlotsUiState::Active)
+            }
         });
         ui.add_enabled_ui(settings_btn_state, |ui| {
             if ui.button("Settings").clicked() {
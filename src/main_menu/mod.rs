@@ -1,12 +1,16 @@
 mod main_menu;
+mod save_slots_menu;
 mod settings_menu;
 
+use crate::keyboard_focus::shortcuts_enabled;
 use bevy::prelude::*;
 use main_menu::{enter_main_menu, exit_main_menu, main_menu};
+use save_slots_menu::{enter_save_slots_ui, exit_save_slots_ui, save_slots_ui};
 use settings_menu::{enter_settings_ui, exit_settings_ui, settings_ui};
 
+/// Whether the main menu is open over the world.
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
-enum MainMenuActivityState {
+pub enum MainMenuActivityState {
     #[default]
     Inactive,
     Active,
@@ -19,13 +23,21 @@ enum ShowSettingsUiState {
     Active,
 }
 
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+enum ShowSaveSlotsUiState {
+    #[default]
+    Inactive,
+    Active,
+}
+
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<MainMenuActivityState>();
         app.init_state::<ShowSettingsUiState>();
-        app.add_systems(Update, watchdog);
+        app.init_state::<ShowSaveSlotsUiState>();
+        app.add_systems(Update, watchdog.run_if(shortcuts_enabled));
         app.add_systems(OnEnter(MainMenuActivityState::Active), enter_main_menu);
         app.add_systems(
             Update,
@@ -45,6 +57,14 @@ impl Plugin for MainMenuPlugin {
             OnExit(ShowSettingsUiState::Active),
             exit_settings_ui.run_if(in_state(MainMenuActivityState::Active)),
         );
+        app.add_systems(OnEnter(ShowSaveSlotsUiState::Active), enter_save_slots_ui);
+        app.add_systems(
+            Update,
+            save_slots_ui.run_if(
+                in_state(MainMenuActivityState::Active).and(in_state(ShowSaveSlotsUiState::Active)),
+            ),
+        );
+        app.add_systems(OnExit(ShowSaveSlotsUiState::Active), exit_save_slots_ui);
         app.add_systems(OnExit(MainMenuActivityState::Active), exit_main_menu);
     }
 }
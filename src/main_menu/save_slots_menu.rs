@@ -0,0 +1,334 @@
+use crate::main_menu::ShowSaveSlotsUiState;
+use crate::world_save::{
+    delete_slot, duplicate_slot, is_valid_save_name, list_save_slots, rename_slot, slot_dir,
+    slot_exists, LoadWorldEvent, SaveSlotInfo, SaveWorldEvent,
+};
+use crate::world_streaming::WorldChunks;
+use bevy::ecs::system::SystemParam;
+use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy_egui::egui::Ui;
+use bevy_egui::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const THUMBNAIL_SIZE: [f32; 2] = [128.0, 72.0];
+
+/// An action that has to be confirmed before it is executed.
+enum Confirmation {
+    Overwrite(String),
+    Delete(String),
+}
+
+#[derive(Resource, Default)]
+pub struct SaveSlotsUi {
+    slots: Vec<SaveSlotInfo>,
+    thumbnails: HashMap<PathBuf, (Handle<Image>, egui::TextureId)>,
+    new_save_name: String,
+    /// Slot being renamed and the name typed so far.
+    renaming: Option<(String, String)>,
+    confirmation: Option<Confirmation>,
+    error: Option<String>,
+    /// Re-read the slots and their thumbnails on the next frame.
+    refresh: bool,
+}
+
+pub fn enter_save_slots_ui(mut commands: Commands) {
+    commands.insert_resource(SaveSlotsUi {
+        slots: list_save_slots(),
+        ..default()
+    });
+}
+
+pub fn exit_save_slots_ui(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    ui: Res<SaveSlotsUi>,
+) {
+    ui.thumbnails.values().for_each(|(handle, _)| {
+        contexts.remove_image(handle);
+    });
+    commands.remove_resource::<SaveSlotsUi>();
+}
+
+#[derive(SystemParam)]
+pub struct SaveSlotsUiBridge<'w> {
+    ui: ResMut<'w, SaveSlotsUi>,
+    images: ResMut<'w, Assets<Image>>,
+    evw_save: EventWriter<'w, SaveWorldEvent>,
+    evw_load: EventWriter<'w, LoadWorldEvent>,
+    save_slots_ui_state: ResMut<'w, NextState<ShowSaveSlotsUiState>>,
+    world_chunks: Res<'w, WorldChunks>,
+}
+
+impl SaveSlotsUiBridge<'_> {
+    /// Whether the chunks of the current world are streamed in from the slot.
+    fn is_streamed_from(&self, name: &str) -> bool {
+        self.world_chunks
+            .source()
+            .is_some_and(|source| source.dir == slot_dir(name))
+    }
+}
+
+pub fn save_slots_ui(mut contexts: EguiContexts, mut bridge: SaveSlotsUiBridge) {
+    if bridge.ui.refresh {
+        refresh_slots(&mut contexts, &mut bridge);
+    }
+    load_missing_thumbnails(&mut contexts, &mut bridge);
+
+    egui::Window::new("Saves").show(contexts.ctx_mut(), |ui| {
+        form_new_save(ui, &mut bridge);
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                let slot_names: Vec<String> = bridge
+                    .ui
+                    .slots
+                    .iter()
+                    .map(|slot| slot.name.clone())
+                    .collect();
+                for name in slot_names {
+                    show_slot(ui, &mut bridge, &name);
+                    ui.separator();
+                }
+            });
+        form_confirmation(ui, &mut bridge);
+        if let Some(error) = &bridge.ui.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                bridge.ui.refresh = true;
+            }
+            if ui.button("Close").clicked() {
+                bridge
+                    .save_slots_ui_state
+                    .set(ShowSaveSlotsUiState::Inactive);
+            }
+        });
+    });
+}
+
+fn refresh_slots(contexts: &mut EguiContexts, bridge: &mut SaveSlotsUiBridge) {
+    bridge.ui.thumbnails.drain().for_each(|(_, (handle, _))| {
+        contexts.remove_image(&handle);
+    });
+    bridge.ui.slots = list_save_slots();
+    bridge.ui.refresh = false;
+}
+
+/// Thumbnails live outside the assets directory, so they are decoded by hand.
+fn load_missing_thumbnails(contexts: &mut EguiContexts, bridge: &mut SaveSlotsUiBridge) {
+    let missing: Vec<PathBuf> = bridge
+        .ui
+        .slots
+        .iter()
+        .filter_map(|slot| slot.thumbnail.clone())
+        .filter(|path| !bridge.ui.thumbnails.contains_key(path))
+        .collect();
+
+    for path in missing {
+        match read_thumbnail(&path) {
+            Some(image) => {
+                let handle = bridge.images.add(image);
+                let texture_id = contexts.add_image(handle.clone_weak());
+                bridge.ui.thumbnails.insert(path, (handle, texture_id));
+            }
+            None => {
+                warn!("Failed to read save thumbnail {}", path.display());
+                bridge
+                    .ui
+                    .slots
+                    .iter_mut()
+                    .filter(|slot| slot.thumbnail.as_ref() == Some(&path))
+                    .for_each(|slot| slot.thumbnail = None);
+            }
+        }
+    }
+}
+
+fn read_thumbnail(path: &Path) -> Option<Image> {
+    let bytes = std::fs::read(path).ok()?;
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    )
+    .ok()
+}
+
+fn form_new_save(ui: &mut Ui, bridge: &mut SaveSlotsUiBridge) {
+    ui.horizontal(|ui| {
+        ui.label("Name");
+        ui.text_edit_singleline(&mut bridge.ui.new_save_name);
+        let name = bridge.ui.new_save_name.trim().to_string();
+        ui.add_enabled_ui(is_valid_save_name(&name), |ui| {
+            if ui.button("Save").clicked() {
+                if slot_exists(&name) {
+                    bridge.ui.confirmation = Some(Confirmation::Overwrite(name));
+                } else {
+                    save(bridge, name);
+                }
+            }
+        });
+    });
+}
+
+fn show_slot(ui: &mut Ui, bridge: &mut SaveSlotsUiBridge, name: &str) {
+    let Some(slot) = bridge.ui.slots.iter().find(|slot| slot.name == name) else {
+        return;
+    };
+    let texture_id = slot
+        .thumbnail
+        .as_ref()
+        .and_then(|path| bridge.ui.thumbnails.get(path))
+        .map(|(_, texture_id)| *texture_id);
+    let modified = slot.modified.map(format_age).unwrap_or_default();
+    let details = match &slot.meta {
+        Some(meta) => format!(
+            "Played {}, {} pieces",
+            format_play_time(Duration::from_secs_f64(meta.play_time_secs)),
+            meta.piece_count
+        ),
+        None => "Damaged save".to_string(),
+    };
+
+    ui.horizontal(|ui| {
+        match texture_id {
+            Some(texture_id) => {
+                ui.add(egui::Image::new(egui::load::SizedTexture::new(
+                    texture_id,
+                    THUMBNAIL_SIZE,
+                )));
+            }
+            None => {
+                ui.add_sized(THUMBNAIL_SIZE, egui::Label::new("No preview"));
+            }
+        }
+
+        ui.vertical(|ui| {
+            match &mut bridge.ui.renaming {
+                Some((slot_name, new_name)) if slot_name == name => {
+                    ui.text_edit_singleline(new_name);
+                }
+                _ => {
+                    ui.strong(name);
+                }
+            }
+            ui.label(modified);
+            ui.label(details);
+            slot_buttons(ui, bridge, name);
+        });
+    });
+}
+
+fn slot_buttons(ui: &mut Ui, bridge: &mut SaveSlotsUiBridge, name: &str) {
+    ui.horizontal(|ui| {
+        if ui.button("Load").clicked() {
+            bridge.evw_load.send(LoadWorldEvent(name.to_string()));
+            bridge
+                .save_slots_ui_state
+                .set(ShowSaveSlotsUiState::Inactive);
+        }
+        if ui.button("Overwrite").clicked() {
+            bridge.ui.confirmation = Some(Confirmation::Overwrite(name.to_string()));
+        }
+
+        // Renaming or deleting the slot would pull the unloaded chunks from under the world
+        let streamed_from = bridge.is_streamed_from(name);
+        let in_use = "The current world is streamed in from this save";
+        let is_renaming = matches!(&bridge.ui.renaming, Some((slot_name, _)) if slot_name == name);
+        if is_renaming {
+            if ui.button("Apply name").clicked() {
+                if let Some((from, to)) = bridge.ui.renaming.take() {
+                    let to = to.trim().to_string();
+                    if !is_valid_save_name(&to) {
+                        bridge.ui.error = Some(format!("\"{to}\" is not a valid save name"));
+                    } else if to != from {
+                        report(bridge, rename_slot(&from, &to));
+                    }
+                }
+            }
+        } else if ui
+            .add_enabled(!streamed_from, egui::Button::new("Rename"))
+            .on_disabled_hover_text(in_use)
+            .clicked()
+        {
+            bridge.ui.renaming = Some((name.to_string(), name.to_string()));
+        }
+
+        if ui.button("Duplicate").clicked() {
+            report(bridge, duplicate_slot(name).map(|_| ()));
+        }
+        if ui
+            .add_enabled(!streamed_from, egui::Button::new("Delete"))
+            .on_disabled_hover_text(in_use)
+            .clicked()
+        {
+            bridge.ui.confirmation = Some(Confirmation::Delete(name.to_string()));
+        }
+    });
+}
+
+fn form_confirmation(ui: &mut Ui, bridge: &mut SaveSlotsUiBridge) {
+    let Some(confirmation) = &bridge.ui.confirmation else {
+        return;
+    };
+    let question = match confirmation {
+        Confirmation::Overwrite(name) => format!("Overwrite save \"{name}\"?"),
+        Confirmation::Delete(name) => format!("Delete save \"{name}\"?"),
+    };
+
+    ui.separator();
+    ui.label(question);
+    ui.horizontal(|ui| {
+        if ui.button("Yes").clicked() {
+            match bridge.ui.confirmation.take() {
+                Some(Confirmation::Overwrite(name)) => save(bridge, name),
+                Some(Confirmation::Delete(name)) => report(bridge, delete_slot(&name)),
+                None => {}
+            }
+        }
+        if ui.button("No").clicked() {
+            bridge.ui.confirmation = None;
+        }
+    });
+}
+
+fn save(bridge: &mut SaveSlotsUiBridge, name: String) {
+    bridge.evw_save.send(SaveWorldEvent(name));
+    bridge.ui.new_save_name.clear();
+    bridge
+        .save_slots_ui_state
+        .set(ShowSaveSlotsUiState::Inactive);
+}
+
+/// Shows the result of a file operation and re-reads the slots it may have changed.
+fn report(bridge: &mut SaveSlotsUiBridge, result: std::io::Result<()>) {
+    bridge.ui.error = result.err().map(|err| err.to_string());
+    bridge.ui.refresh = true;
+}
+
+fn format_age(modified: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default()
+        .as_secs();
+    match secs {
+        0..60 => "Just now".to_string(),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+fn format_play_time(play_time: Duration) -> String {
+    let minutes = play_time.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
@@ -1,5 +1,5 @@
 use crate::main_menu::ShowSettingsUiState;
-use crate::settings::{GameSettings, GameSettingsBridge};
+use crate::settings::{GameSettings, GameSettingsBridge, GraphicsQuality};
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_egui::egui::Ui;
@@ -31,6 +31,8 @@ pub fn settings_ui(mut contexts: EguiContexts, mut bridge: SettingsUiBridge) {
         submenu_keyboard(ui, &mut bridge);
         submenu_mouse(ui, &mut bridge);
         submenu_video(ui, &mut bridge);
+        submenu_autosave(ui, &mut bridge);
+        submenu_environment(ui, &mut bridge);
         ui.separator();
         form_save_or_cancel_or_defaults(ui, &mut bridge);
     });
@@ -61,10 +63,46 @@ fn submenu_keyboard(ui: &mut Ui, bridge: &mut SettingsUiBridge) {
             btn_settings(ui, "Right", &mut keyboard.right);
             btn_settings(ui, "Jump", &mut keyboard.jump);
             btn_settings(ui, "Crouch", &mut keyboard.crouch);
+            btn_settings(ui, "Walk / fly", &mut keyboard.walk);
+            btn_settings(ui, "Run (hold)", &mut keyboard.run);
+        });
+        ui.collapsing("Gathering", |ui| {
+            btn_settings(ui, "Gather", &mut keyboard.gather);
+            btn_settings(ui, "Open / close", &mut keyboard.interact);
         });
         ui.collapsing("Building", |ui| {
             btn_settings(ui, "Start building", &mut keyboard.start_building);
             btn_settings(ui, "Stop building", &mut keyboard.stop_building);
+            btn_settings(ui, "Cycle material", &mut keyboard.cycle_material);
+            btn_settings(ui, "Pitch (hold)", &mut keyboard.pitch_modifier);
+            btn_settings(ui, "Roll (hold)", &mut keyboard.roll_modifier);
+            btn_settings(ui, "Cycle grid size", &mut keyboard.cycle_grid_size);
+            btn_settings(ui, "Toggle snapping", &mut keyboard.toggle_snapping);
+            btn_settings(ui, "Nudge forward", &mut keyboard.nudge_forward);
+            btn_settings(ui, "Nudge backward", &mut keyboard.nudge_backward);
+            btn_settings(ui, "Nudge left", &mut keyboard.nudge_left);
+            btn_settings(ui, "Nudge right", &mut keyboard.nudge_right);
+            btn_settings(ui, "Nudge up", &mut keyboard.nudge_up);
+            btn_settings(ui, "Nudge down", &mut keyboard.nudge_down);
+            btn_settings(ui, "Demolish", &mut keyboard.demolish);
+            btn_settings(ui, "Upgrade building", &mut keyboard.upgrade_building);
+            btn_settings(
+                ui,
+                "Undo upgrade (this session)",
+                &mut keyboard.undo_upgrade,
+            );
+            btn_settings(ui, "Select buildings", &mut keyboard.select_buildings);
+            btn_settings(ui, "Delete selection", &mut keyboard.delete_selection);
+        });
+        ui.collapsing("Terrain", |ui| {
+            btn_settings(ui, "Terrain tools", &mut keyboard.terrain_tools);
+        });
+        ui.collapsing("Environment", |ui| {
+            btn_settings(ui, "Cycle weather", &mut keyboard.cycle_weather);
+        });
+        ui.collapsing("Debug", |ui| {
+            btn_settings(ui, "Diagnostics", &mut keyboard.debug_overlay);
+            btn_settings(ui, "Colliders", &mut keyboard.debug_colliders);
         });
     });
 }
@@ -87,8 +125,50 @@ fn submenu_mouse(ui: &mut Ui, bridge: &mut SettingsUiBridge) {
     });
 }
 
-fn submenu_video(ui: &mut Ui, _bridge: &mut SettingsUiBridge) {
-    ui.collapsing("Video", |_| {});
+fn submenu_video(ui: &mut Ui, bridge: &mut SettingsUiBridge) {
+    let video = &mut bridge.tmp_settings.0.video;
+    ui.collapsing("Video", |ui| {
+        ui.add(Slider::new(&mut video.max_shadowed_lights, 0..=16).text("Shadowed lights"));
+        add_slider(
+            ui,
+            "View distance (m)",
+            &mut video.view_distance,
+            32.0..=1024.0,
+        );
+        egui::ComboBox::from_label("Building detail")
+            .selected_text(format!("{:?}", video.quality))
+            .show_ui(ui, |ui| {
+                for quality in GraphicsQuality::ALL {
+                    ui.selectable_value(&mut video.quality, quality, format!("{quality:?}"));
+                }
+            });
+    });
+}
+
+fn submenu_autosave(ui: &mut Ui, bridge: &mut SettingsUiBridge) {
+    let autosave = &mut bridge.tmp_settings.0.autosave;
+    ui.collapsing("Autosave", |ui| {
+        ui.checkbox(&mut autosave.enabled, "Enabled");
+        add_slider(
+            ui,
+            "Interval (s)",
+            &mut autosave.interval_secs,
+            30.0..=1800.0,
+        );
+        ui.add(Slider::new(&mut autosave.slots, 1..=10).text("Slots"));
+    });
+}
+
+fn submenu_environment(ui: &mut Ui, bridge: &mut SettingsUiBridge) {
+    let environment = &mut bridge.tmp_settings.0.environment;
+    ui.collapsing("Environment", |ui| {
+        ui.add(
+            Slider::new(&mut environment.time_scale, 0.0..=3600.0)
+                .logarithmic(true)
+                .text("Time scale"),
+        );
+        ui.checkbox(&mut environment.dynamic_weather, "Dynamic weather");
+    });
 }
 
 fn form_save_or_cancel_or_defaults(ui: &mut Ui, bridge: &mut SettingsUiBridge) {
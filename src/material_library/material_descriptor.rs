@@ -0,0 +1,179 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::image::ImageLoaderSettings;
+use bevy::prelude::*;
+use bevy::render::render_resource::Face;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+/// Describes how a `StandardMaterial` is built from the textures in its folder.
+/// Texture paths are relative to the descriptor. Maps the folder doesn't have are `None`,
+/// a listed map which can't be loaded fails the whole material.
+///
+/// A map with only roughness (grayscale) can be used as `metallic_roughness_texture`
+/// together with `metallic: 0.0`.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct MaterialDescriptor {
+    pub base_color_texture: Option<String>,
+    pub normal_map_texture: Option<String>,
+    pub occlusion_texture: Option<String>,
+    /// Green channel is roughness, blue channel is metallic.
+    pub metallic_roughness_texture: Option<String>,
+    /// Linear RGBA multiplied with the base color texture.
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub perceptual_roughness: f32,
+    pub double_sided: bool,
+}
+
+impl Default for MaterialDescriptor {
+    fn default() -> Self {
+        Self {
+            base_color_texture: None,
+            normal_map_texture: None,
+            occlusion_texture: None,
+            metallic_roughness_texture: None,
+            base_color: [1.0; 4],
+            metallic: 1.0,
+            perceptual_roughness: 1.0,
+            double_sided: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MaterialDescriptorError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    /// A listed texture is missing or broken.
+    Texture(String, String),
+}
+
+impl Display for MaterialDescriptorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaterialDescriptorError::Io(err) => write!(f, "I/O error: {err}"),
+            MaterialDescriptorError::Parse(err) => write!(f, "damaged descriptor: {err}"),
+            MaterialDescriptorError::Texture(file, err) => write!(f, "texture \"{file}\": {err}"),
+        }
+    }
+}
+
+impl std::error::Error for MaterialDescriptorError {}
+
+impl From<std::io::Error> for MaterialDescriptorError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for MaterialDescriptorError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+/// Loads `*.material.ron` descriptors as `StandardMaterial`s.
+#[derive(Default)]
+pub struct MaterialDescriptorLoader;
+
+impl AssetLoader for MaterialDescriptorLoader {
+    type Asset = StandardMaterial;
+    type Settings = ();
+    type Error = MaterialDescriptorError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<StandardMaterial, MaterialDescriptorError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let descriptor: MaterialDescriptor = ron::de::from_bytes(&bytes)?;
+
+        // Color maps are sRGB, data maps (normals, occlusion, metallic/roughness) are linear
+        let mut textures = HashMap::new();
+        let base_color_texture = load_texture(
+            load_context,
+            &mut textures,
+            &descriptor.base_color_texture,
+            true,
+        )
+        .await?;
+        let normal_map_texture = load_texture(
+            load_context,
+            &mut textures,
+            &descriptor.normal_map_texture,
+            false,
+        )
+        .await?;
+        let occlusion_texture = load_texture(
+            load_context,
+            &mut textures,
+            &descriptor.occlusion_texture,
+            false,
+        )
+        .await?;
+        let metallic_roughness_texture = load_texture(
+            load_context,
+            &mut textures,
+            &descriptor.metallic_roughness_texture,
+            false,
+        )
+        .await?;
+
+        let [red, green, blue, alpha] = descriptor.base_color;
+        Ok(StandardMaterial {
+            base_color: Color::linear_rgba(red, green, blue, alpha),
+            base_color_texture,
+            normal_map_texture,
+            occlusion_texture,
+            metallic_roughness_texture,
+            metallic: descriptor.metallic,
+            perceptual_roughness: descriptor.perceptual_roughness,
+            double_sided: descriptor.double_sided,
+            cull_mode: (!descriptor.double_sided).then_some(Face::Back),
+            ..default()
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["material.ron"]
+    }
+}
+
+/// Loads a texture of the descriptor as a labeled asset.
+/// A file used by several maps (like a packed occlusion/roughness/metallic texture) is loaded once.
+async fn load_texture(
+    load_context: &mut LoadContext<'_>,
+    textures: &mut HashMap<String, Handle<Image>>,
+    file: &Option<String>,
+    is_srgb: bool,
+) -> Result<Option<Handle<Image>>, MaterialDescriptorError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    if let Some(handle) = textures.get(file) {
+        return Ok(Some(handle.clone()));
+    }
+    let texture_error =
+        |err: &dyn Display| MaterialDescriptorError::Texture(file.clone(), err.to_string());
+    let path = load_context
+        .asset_path()
+        .resolve_embed(file)
+        .map_err(|err| texture_error(&err))?;
+
+    let image = load_context
+        .loader()
+        .with_settings(move |settings: &mut ImageLoaderSettings| settings.is_srgb = is_srgb)
+        .immediate()
+        .load::<Image>(path)
+        .await
+        .map_err(|err| texture_error(&err))?;
+    let handle = load_context.add_loaded_labeled_asset(file.clone(), image);
+    textures.insert(file.clone(), handle.clone());
+    Ok(Some(handle))
+}
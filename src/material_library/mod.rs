@@ -0,0 +1,59 @@
+mod material_descriptor;
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use material_descriptor::MaterialDescriptorLoader;
+
+/// Directory inside of the assets with one subdirectory per material.
+const MATERIALS_DIR: &str = "materials";
+
+/// Descriptor file every material subdirectory has to contain.
+const DESCRIPTOR_FILE: &str = "pbr.material.ron";
+
+/// Materials from `assets/materials/`, by the name of their folder.
+#[derive(Resource, Default)]
+pub struct MaterialLibrary(HashMap<String, Handle<StandardMaterial>>);
+
+impl MaterialLibrary {
+    pub fn get(&self, name: &str) -> Option<Handle<StandardMaterial>> {
+        self.0.get(name).cloned()
+    }
+}
+
+pub struct MaterialLibraryPlugin;
+
+impl Plugin for MaterialLibraryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset_loader::<MaterialDescriptorLoader>()
+            .add_systems(PreStartup, load_material_library);
+    }
+}
+
+/// Starts loading every material folder which has a descriptor.
+fn load_material_library(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let mut library = MaterialLibrary::default();
+    let dir = FileAssetReader::get_base_path()
+        .join("assets")
+        .join(MATERIALS_DIR);
+
+    match std::fs::read_dir(&dir) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                if !entry.path().join(DESCRIPTOR_FILE).is_file() {
+                    warn!("Material \"{name}\" has no {DESCRIPTOR_FILE}, skipped");
+                    continue;
+                }
+                let handle = asset_server.load(format!("{MATERIALS_DIR}/{name}/{DESCRIPTOR_FILE}"));
+                library.0.insert(name, handle);
+            }
+        }
+        Err(err) => error!("Failed to read materials from {}: {err}", dir.display()),
+    }
+
+    info!("Material library: {} materials", library.0.len());
+    commands.insert_resource(library);
+}
@@ -0,0 +1,219 @@
+use crate::settings::GameSettings;
+use crate::terrain::Terrain;
+use crate::universal_camera_controller::{
+    FlyingCamera, UniCamChangeStateEvent, UniCamController, UniCamState,
+};
+use avian3d::prelude::{Collider, RigidBody, ShapeCastConfig, SpatialQuery, SpatialQueryFilter};
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+
+const CAPSULE_RADIUS: f32 = 0.3;
+/// Length of the straight part of the capsule, between its two half spheres.
+const CAPSULE_LENGTH: f32 = 1.2;
+/// Height of the camera above the feet.
+const EYE_HEIGHT: f32 = 1.65;
+const WALK_SPEED: f32 = 4.0;
+const RUN_SPEED: f32 = 7.0;
+const JUMP_SPEED: f32 = 5.0;
+const GRAVITY: f32 = 9.81;
+/// Gap kept between the capsule and what it touches, so it doesn't start the next move inside it.
+const SKIN_WIDTH: f32 = 0.02;
+/// Surfaces with a normal steeper than this (its y component) can be stood on.
+const MIN_GROUND_NORMAL_Y: f32 = 0.7;
+/// Moves the capsule slides along obstacles with per frame.
+const MAX_SLIDES: usize = 4;
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CharacterState {
+    #[default]
+    Flying,
+    Walking,
+}
+
+/// The walking player, a kinematic capsule the camera is attached to.
+#[derive(Component, Default)]
+pub struct CharacterController {
+    velocity: Vec3,
+    grounded: bool,
+    pitch: f32,
+    yaw: f32,
+}
+
+/// Distance between the center of the capsule and the feet.
+fn feet_offset() -> f32 {
+    CAPSULE_LENGTH / 2.0 + CAPSULE_RADIUS
+}
+
+/// Switches between flying the camera and walking with the character.
+pub fn toggle_character(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut next_state: ResMut<NextState<CharacterState>>,
+    mut camera: Single<(&Transform, &mut UniCamController)>,
+    characters: Query<Entity, With<CharacterController>>,
+    mut uni_cam_events: EventWriter<UniCamChangeStateEvent>,
+    mut commands: Commands,
+) {
+    if !keys.just_pressed(game_settings.keyboard.walk) {
+        return;
+    }
+    if characters.is_empty() {
+        let transform = camera.0;
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let center = transform.translation - Vec3::Y * (EYE_HEIGHT - feet_offset());
+        commands.spawn((
+            CharacterController {
+                yaw,
+                pitch,
+                ..default()
+            },
+            Transform::from_translation(center),
+            RigidBody::Kinematic,
+            Collider::capsule(CAPSULE_RADIUS, CAPSULE_LENGTH),
+        ));
+        uni_cam_events.send(UniCamChangeStateEvent(UniCamState::Disabled));
+        next_state.set(CharacterState::Walking);
+    } else {
+        for entity in characters.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        // A fresh flying camera starts from where the character stood instead of where it was left
+        *camera.1 = FlyingCamera::default().into();
+        uni_cam_events.send(UniCamChangeStateEvent(UniCamState::Enabled));
+        next_state.set(CharacterState::Flying);
+    }
+}
+
+/// Input the character is moved with.
+#[derive(SystemParam)]
+pub struct CharacterInput<'w, 's> {
+    time: Res<'w, Time>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    game_settings: Res<'w, GameSettings>,
+    mouse_motion: EventReader<'w, 's, MouseMotion>,
+}
+
+type Character<'w> = Single<
+    'w,
+    (
+        Entity,
+        &'static mut CharacterController,
+        &'static mut Transform,
+        &'static Collider,
+    ),
+>;
+
+/// Walks the character with the movement keys and looks around with the mouse,
+/// sliding along buildings and the terrain.
+pub fn move_character(
+    mut input: CharacterInput,
+    spatial_query: SpatialQuery,
+    terrain: Option<Res<Terrain>>,
+    character: Character,
+    mut camera: Single<&mut Transform, (With<UniCamController>, Without<CharacterController>)>,
+) {
+    let (entity, mut controller, mut transform, collider) = character.into_inner();
+    let delta = input.time.delta_secs();
+    let keyboard = &input.game_settings.keyboard;
+    let mouse = &input.game_settings.mouse;
+
+    let motion: Vec2 = input.mouse_motion.read().map(|event| event.delta).sum();
+    controller.yaw -= mouse.sensitivity_horizontal * motion.x;
+    controller.pitch = (controller.pitch - mouse.sensitivity_vertical * motion.y)
+        .clamp(-89.0_f32.to_radians(), 89.0_f32.to_radians());
+    let yaw_rotation = Quat::from_rotation_y(controller.yaw);
+
+    let mut direction = Vec3::ZERO;
+    if input.keys.pressed(keyboard.forward) {
+        direction += Vec3::NEG_Z;
+    }
+    if input.keys.pressed(keyboard.backward) {
+        direction += Vec3::Z;
+    }
+    if input.keys.pressed(keyboard.left) {
+        direction += Vec3::NEG_X;
+    }
+    if input.keys.pressed(keyboard.right) {
+        direction += Vec3::X;
+    }
+    let speed = if input.keys.pressed(keyboard.run) {
+        RUN_SPEED
+    } else {
+        WALK_SPEED
+    };
+    let walk = yaw_rotation * direction.normalize_or_zero() * speed;
+    controller.velocity.x = walk.x;
+    controller.velocity.z = walk.z;
+    if controller.grounded && input.keys.just_pressed(keyboard.jump) {
+        controller.velocity.y = JUMP_SPEED;
+    } else {
+        controller.velocity.y -= GRAVITY * delta;
+    }
+
+    let filter = SpatialQueryFilter::from_excluded_entities([entity]);
+    let (mut position, mut grounded) = move_and_slide(
+        &spatial_query,
+        collider,
+        &filter,
+        transform.translation,
+        controller.velocity * delta,
+    );
+    // Chunks whose colliders aren't built yet still hold the character up
+    if let Some(ground) = terrain
+        .as_ref()
+        .and_then(|terrain| terrain.height_at(position.x, position.z))
+    {
+        if position.y - feet_offset() < ground {
+            position.y = ground + feet_offset();
+            grounded = true;
+        }
+    }
+    if grounded && controller.velocity.y < 0.0 {
+        controller.velocity.y = 0.0;
+    }
+    controller.grounded = grounded;
+    transform.translation = position;
+
+    camera.translation = position + Vec3::Y * (EYE_HEIGHT - feet_offset());
+    camera.rotation = yaw_rotation * Quat::from_rotation_x(controller.pitch);
+}
+
+/// Moves the collider by `motion`, sliding along what it hits.
+/// Returns the new position and whether it stands on something.
+fn move_and_slide(
+    spatial_query: &SpatialQuery,
+    collider: &Collider,
+    filter: &SpatialQueryFilter,
+    mut position: Vec3,
+    motion: Vec3,
+) -> (Vec3, bool) {
+    let mut remaining = motion;
+    let mut grounded = false;
+    for _ in 0..MAX_SLIDES {
+        let Ok((direction, distance)) = Dir3::new_and_length(remaining) else {
+            break;
+        };
+        let config = ShapeCastConfig::from_max_distance(distance + SKIN_WIDTH);
+        let Some(hit) = spatial_query.cast_shape(
+            collider,
+            position,
+            Quat::IDENTITY,
+            direction,
+            &config,
+            filter,
+        ) else {
+            position += remaining;
+            break;
+        };
+        let travel = (hit.distance - SKIN_WIDTH).clamp(0.0, distance);
+        position += direction * travel;
+        let normal = hit.normal1;
+        if normal.y >= MIN_GROUND_NORMAL_Y {
+            grounded = true;
+        }
+        remaining = direction * (distance - travel);
+        remaining -= normal * remaining.dot(normal);
+    }
+    (position, grounded)
+}
@@ -0,0 +1,165 @@
+use crate::building::{BuildingAssets, PlacedBuilding};
+use crate::harvesting::HarvestNode;
+use crate::settings::GameSettings;
+use crate::terrain::TerrainChunk;
+use avian3d::prelude::{Collider, ColliderConstructor, PhysicsGizmos, RigidBody};
+use bevy::prelude::*;
+use bevy::render::mesh::{PrimitiveTopology, VertexAttributeValues};
+use bevy::utils::HashMap;
+
+/// Colliders of the buildings by name, built once per `BuildingAssetsPack` and shared by all its pieces.
+/// `None` for buildings without any geometry to collide with.
+#[derive(Resource, Default)]
+pub struct BuildingColliders(HashMap<String, Option<Collider>>);
+
+/// Builds the colliders of buildings whose collision meshes are known and loaded:
+/// convex hulls of authored collision meshes, or one triangle mesh merged from the drawn ones.
+/// Triangle meshes can't be part of a compound collider, so they are never put in one.
+pub fn build_building_colliders(
+    building_assets: Res<BuildingAssets>,
+    meshes: Res<Assets<Mesh>>,
+    mut building_colliders: ResMut<BuildingColliders>,
+) {
+    for pack in building_assets.iter() {
+        if building_colliders.0.contains_key(&pack.name) {
+            continue;
+        }
+        let Some(collision) = &pack.collision else {
+            continue;
+        };
+        let Some(collision_meshes) = collision
+            .meshes
+            .iter()
+            .map(|(handle, transform)| meshes.get(handle).map(|mesh| (mesh, transform)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+
+        let triangles = collision_meshes
+            .into_iter()
+            .filter_map(|(mesh, transform)| mesh_triangles(mesh, transform));
+        let mut shapes: Vec<Collider> = if collision.authored {
+            triangles
+                .filter_map(|(vertices, _)| Collider::convex_hull(vertices))
+                .collect()
+        } else {
+            let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+            for (mesh_vertices, mesh_indices) in triangles {
+                let base = vertices.len() as u32;
+                vertices.extend(mesh_vertices);
+                indices.extend(
+                    mesh_indices
+                        .into_iter()
+                        .map(|triangle| triangle.map(|index| base + index)),
+                );
+            }
+            if indices.is_empty() {
+                Vec::new()
+            } else {
+                vec![Collider::trimesh(vertices, indices)]
+            }
+        };
+
+        let collider = match shapes.len() {
+            0 => {
+                warn!("No collider could be built for {}", pack.name);
+                None
+            }
+            1 => shapes.pop(),
+            count => {
+                info!("Built the collider of {} from {count} meshes", pack.name);
+                Some(Collider::compound(
+                    shapes
+                        .into_iter()
+                        .map(|shape| (Vec3::ZERO, Quat::IDENTITY, shape))
+                        .collect(),
+                ))
+            }
+        };
+        building_colliders.0.insert(pack.name.clone(), collider);
+    }
+}
+
+/// Vertices of a triangle list mesh positioned by `transform`, and its triangles.
+/// `None` for meshes of other topologies or without positions.
+fn mesh_triangles(mesh: &Mesh, transform: &Transform) -> Option<(Vec<Vec3>, Vec<[u32; 3]>)> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    let vertices: Vec<Vec3> = positions
+        .iter()
+        .map(|position| transform.transform_point(Vec3::from(*position)))
+        .collect();
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(indices) => indices.iter().map(|index| index as u32).collect(),
+        None => (0..vertices.len() as u32).collect(),
+    };
+    let triangles = indices
+        .chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .collect();
+    Some((vertices, triangles))
+}
+
+/// Gives placed buildings the shared collider of their kind as soon as it is built.
+pub fn add_building_colliders(
+    mut commands: Commands,
+    placed_buildings: Query<(Entity, &PlacedBuilding), Without<Collider>>,
+    building_colliders: Res<BuildingColliders>,
+) {
+    for (entity, building) in placed_buildings.iter() {
+        if let Some(Some(collider)) = building_colliders.0.get(&building.name) {
+            commands
+                .entity(entity)
+                .insert((RigidBody::Static, collider.clone()));
+        }
+    }
+}
+
+/// Adds colliders to the layer meshes of terrain chunks, which are respawned whenever a chunk is rebuilt.
+pub fn add_terrain_colliders(
+    mut commands: Commands,
+    layers: Query<(Entity, &Parent), Added<Mesh3d>>,
+    chunks: Query<(), With<TerrainChunk>>,
+) {
+    for (entity, parent) in layers.iter() {
+        if chunks.contains(parent.get()) {
+            commands
+                .entity(entity)
+                .insert((RigidBody::Static, ColliderConstructor::TrimeshFromMesh));
+        }
+    }
+}
+
+/// Adds colliders to the meshes of trees, rocks and deposits, so the character can't walk through them.
+pub fn add_harvest_node_colliders(
+    mut commands: Commands,
+    parts: Query<(Entity, &Parent), Added<Mesh3d>>,
+    nodes: Query<(), With<HarvestNode>>,
+) {
+    for (entity, parent) in parts.iter() {
+        if nodes.contains(parent.get()) {
+            commands
+                .entity(entity)
+                .insert((RigidBody::Static, ColliderConstructor::ConvexHullFromMesh));
+        }
+    }
+}
+
+/// Shows or hides the outlines of all colliders.
+pub fn toggle_collider_gizmos(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    mut gizmo_configs: ResMut<GizmoConfigStore>,
+) {
+    if keys.just_pressed(game_settings.keyboard.debug_colliders) {
+        let (config, _) = gizmo_configs.config_mut::<PhysicsGizmos>();
+        config.enabled = !config.enabled;
+    }
+}
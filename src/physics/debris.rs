@@ -0,0 +1,57 @@
+use crate::building::CollapseBuildingEvent;
+use avian3d::prelude::{ColliderConstructor, LinearVelocity, RigidBody};
+use bevy::prelude::*;
+
+/// Seconds debris lies around before it disappears.
+const DEBRIS_LIFETIME: f32 = 15.0;
+/// Speed in m/s the pieces of a collapsing building are pushed away from its center with.
+const DEBRIS_BURST_SPEED: f32 = 1.5;
+
+/// A piece of a collapsed building, falling freely.
+#[derive(Component)]
+pub struct Debris(Timer);
+
+/// Replaces collapsed buildings by dynamic bodies, one for every mesh of the building.
+pub fn spawn_collapse_debris(
+    mut commands: Commands,
+    mut collapse_events: EventReader<CollapseBuildingEvent>,
+    buildings: Query<&GlobalTransform>,
+    children: Query<&Children>,
+    meshes: Query<(&Mesh3d, &MeshMaterial3d<StandardMaterial>, &GlobalTransform)>,
+) {
+    for event in collapse_events.read() {
+        let Ok(building) = buildings.get(event.0) else {
+            continue;
+        };
+        let center = building.translation();
+        for entity in children.iter_descendants(event.0) {
+            let Ok((mesh, material, transform)) = meshes.get(entity) else {
+                continue;
+            };
+            let transform = transform.compute_transform();
+            let burst = (transform.translation - center).normalize_or_zero() * DEBRIS_BURST_SPEED;
+            commands.spawn((
+                mesh.clone(),
+                material.clone(),
+                transform,
+                RigidBody::Dynamic,
+                ColliderConstructor::ConvexHullFromMesh,
+                LinearVelocity(burst),
+                Debris(Timer::from_seconds(DEBRIS_LIFETIME, TimerMode::Once)),
+            ));
+        }
+        commands.entity(event.0).despawn_recursive();
+    }
+}
+
+pub fn despawn_expired_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut debris: Query<(Entity, &mut Debris)>,
+) {
+    for (entity, mut debris) in debris.iter_mut() {
+        if debris.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
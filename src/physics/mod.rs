@@ -0,0 +1,59 @@
+#[cfg(feature = "physics")]
+mod character;
+#[cfg(feature = "physics")]
+mod colliders;
+#[cfg(feature = "physics")]
+mod debris;
+
+use bevy::prelude::*;
+
+/// Physics of the world: static colliders for buildings, terrain and harvestable nodes,
+/// debris of collapsed buildings and a walking character.
+/// Does nothing unless the game is built with the "physics" feature.
+pub struct GamePhysicsPlugin;
+
+impl Plugin for GamePhysicsPlugin {
+    #[cfg(feature = "physics")]
+    fn build(&self, app: &mut App) {
+        use crate::keyboard_focus::shortcuts_enabled;
+        use avian3d::prelude::{PhysicsDebugPlugin, PhysicsGizmos, PhysicsPlugins};
+        use character::{move_character, toggle_character, CharacterState};
+        use colliders::{
+            add_building_colliders, add_harvest_node_colliders, add_terrain_colliders,
+            build_building_colliders, toggle_collider_gizmos, BuildingColliders,
+        };
+        use debris::{despawn_expired_debris, spawn_collapse_debris};
+
+        app.add_plugins((PhysicsPlugins::default(), PhysicsDebugPlugin::default()))
+            // Collider outlines are hidden until toggled
+            .insert_gizmo_config(
+                PhysicsGizmos::default(),
+                GizmoConfig {
+                    enabled: false,
+                    ..default()
+                },
+            )
+            .init_resource::<BuildingColliders>()
+            .init_state::<CharacterState>()
+            .add_systems(
+                Update,
+                (
+                    build_building_colliders,
+                    add_building_colliders,
+                    add_terrain_colliders,
+                    add_harvest_node_colliders,
+                    toggle_collider_gizmos.run_if(shortcuts_enabled),
+                    spawn_collapse_debris,
+                    despawn_expired_debris,
+                    toggle_character.run_if(shortcuts_enabled),
+                ),
+            )
+            .add_systems(
+                Update,
+                move_character.run_if(in_state(CharacterState::Walking)),
+            );
+    }
+
+    #[cfg(not(feature = "physics"))]
+    fn build(&self, _app: &mut App) {}
+}
@@ -0,0 +1,18 @@
+#[derive(Clone)]
+pub struct AutosaveSettings {
+    pub enabled: bool,
+    /// Time between autosaves.
+    pub interval_secs: f32,
+    /// Number of autosave slots rotated through. The oldest one is overwritten.
+    pub slots: u32,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 300.0,
+            slots: 3,
+        }
+    }
+}
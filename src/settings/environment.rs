@@ -0,0 +1,17 @@
+#[derive(Clone)]
+pub struct EnvironmentSettings {
+    /// In-game seconds passing per real second. 0 stops the clock.
+    pub time_scale: f32,
+    /// Whether the weather changes by itself over time.
+    pub dynamic_weather: bool,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        // A day lasts 24 minutes
+        Self {
+            time_scale: 60.0,
+            dynamic_weather: true,
+        }
+    }
+}
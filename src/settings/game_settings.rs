@@ -1,3 +1,5 @@
+use super::autosave::AutosaveSettings;
+use super::environment::EnvironmentSettings;
 use super::keyboard::KeyboardBindings;
 use super::mouse::MouseSensitivity;
 use super::video::VideoSettings;
@@ -11,6 +13,8 @@ pub struct GameSettings {
     pub keyboard: KeyboardBindings,
     pub mouse: MouseSensitivity,
     pub video: VideoSettings,
+    pub autosave: AutosaveSettings,
+    pub environment: EnvironmentSettings,
 }
 
 impl GameSettings {
@@ -9,9 +9,47 @@ pub struct KeyboardBindings {
     pub right: KeyCode,
     pub jump: KeyCode,
     pub crouch: KeyCode,
+    /// Switches between flying and walking, only with the "physics" feature.
+    pub walk: KeyCode,
+    /// Held to run while walking. Not Shift or Alt, those pitch and roll while building.
+    pub run: KeyCode,
+    // Gathering
+    /// Held to gather the tree, rock or deposit in the middle of the view.
+    pub gather: KeyCode,
+    /// Opens and closes the door or window in the middle of the view.
+    pub interact: KeyCode,
     // Building
     pub start_building: KeyCode,
     pub stop_building: KeyCode,
+    pub cycle_material: KeyCode,
+    /// Held while scrolling to pitch the preview instead of turning it.
+    pub pitch_modifier: KeyCode,
+    /// Held while scrolling to roll the preview instead of turning it.
+    pub roll_modifier: KeyCode,
+    pub cycle_grid_size: KeyCode,
+    pub toggle_snapping: KeyCode,
+    /// Move the preview by a grid step, relative to the view.
+    pub nudge_forward: KeyCode,
+    pub nudge_backward: KeyCode,
+    pub nudge_left: KeyCode,
+    pub nudge_right: KeyCode,
+    pub nudge_up: KeyCode,
+    pub nudge_down: KeyCode,
+    pub demolish: KeyCode,
+    /// Upgrades the building in the middle of the view to the next tier.
+    pub upgrade_building: KeyCode,
+    /// Undoes the latest upgrade made since the world was loaded.
+    pub undo_upgrade: KeyCode,
+    pub select_buildings: KeyCode,
+    pub delete_selection: KeyCode,
+    // Terrain
+    pub terrain_tools: KeyCode,
+    // Environment
+    pub cycle_weather: KeyCode,
+    // Debug
+    pub debug_overlay: KeyCode,
+    /// Outlines of the colliders, only with the "physics" feature.
+    pub debug_colliders: KeyCode,
 }
 
 impl Default for KeyboardBindings {
@@ -24,9 +62,37 @@ impl Default for KeyboardBindings {
             right: KeyCode::KeyD,
             jump: KeyCode::Space,
             crouch: KeyCode::ControlLeft,
+            walk: KeyCode::KeyV,
+            run: KeyCode::KeyR,
+            // Gathering
+            gather: KeyCode::KeyE,
+            interact: KeyCode::KeyF,
             // Building
             start_building: KeyCode::KeyB,
             stop_building: KeyCode::KeyN,
+            cycle_material: KeyCode::KeyM,
+            pitch_modifier: KeyCode::ShiftLeft,
+            roll_modifier: KeyCode::AltLeft,
+            cycle_grid_size: KeyCode::KeyG,
+            toggle_snapping: KeyCode::KeyH,
+            nudge_forward: KeyCode::ArrowUp,
+            nudge_backward: KeyCode::ArrowDown,
+            nudge_left: KeyCode::ArrowLeft,
+            nudge_right: KeyCode::ArrowRight,
+            nudge_up: KeyCode::PageUp,
+            nudge_down: KeyCode::PageDown,
+            demolish: KeyCode::KeyX,
+            upgrade_building: KeyCode::KeyU,
+            undo_upgrade: KeyCode::KeyZ,
+            select_buildings: KeyCode::KeyC,
+            delete_selection: KeyCode::Delete,
+            // Terrain
+            terrain_tools: KeyCode::KeyT,
+            // Environment
+            cycle_weather: KeyCode::KeyL,
+            // Debug
+            debug_overlay: KeyCode::F9,
+            debug_colliders: KeyCode::F6,
         }
     }
 }
@@ -1,3 +1,5 @@
+mod autosave;
+mod environment;
 mod game_settings;
 mod keyboard;
 mod mouse;
@@ -9,6 +11,7 @@ use bevy::prelude::{Single, Window};
 
 pub use game_settings::GameSettings;
 pub use plugin::GameSettingsPlugin;
+pub use video::GraphicsQuality;
 
 #[derive(SystemParam)]
 pub struct GameSettingsBridge<'w> {
@@ -9,6 +9,12 @@ pub struct VideoSettings {
     pub window_height: u32,
     pub present_mode: PresentMode,
     // Graphics
+    /// Lights nearest to the camera which may cast shadows, the others don't.
+    pub max_shadowed_lights: usize,
+    /// Distance in meters around the camera in which the world is loaded.
+    pub view_distance: f32,
+    /// Distances at which placed buildings are simplified and hidden.
+    pub quality: GraphicsQuality,
 }
 
 impl Default for VideoSettings {
@@ -18,6 +24,38 @@ impl Default for VideoSettings {
             window_width: 1920,
             window_height: 1200,
             present_mode: PresentMode::AutoVsync,
+            max_shadowed_lights: 4,
+            view_distance: 160.0,
+            quality: GraphicsQuality::Medium,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GraphicsQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl GraphicsQuality {
+    pub const ALL: [Self; 3] = [Self::Low, Self::Medium, Self::High];
+
+    /// Distance in meters after which placed buildings are drawn simplified.
+    pub fn lod_distance(self) -> f32 {
+        match self {
+            Self::Low => 30.0,
+            Self::Medium => 60.0,
+            Self::High => 120.0,
+        }
+    }
+
+    /// Distance in meters after which placed buildings aren't drawn at all.
+    pub fn cull_distance(self) -> f32 {
+        match self {
+            Self::Low => 150.0,
+            Self::Medium => 300.0,
+            Self::High => 600.0,
         }
     }
 }
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use std::sync::Arc;
+
+/// Where the terrain heights come from.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub enum HeightSource {
+    Flat,
+    /// Fractal value noise.
+    Noise {
+        seed: u32,
+        /// Maximum height in meters.
+        amplitude: f32,
+        /// Features per meter of the first octave.
+        frequency: f32,
+        octaves: u32,
+    },
+    /// Grayscale image (red channel) stretched over the whole world.
+    /// Black is 0 and white is `height_scale` meters.
+    Image {
+        path: String,
+        height_scale: f32,
+    },
+}
+
+/// A height source ready to be sampled at any world position.
+/// Cheap to clone, so chunks can be generated on other threads.
+#[derive(Clone)]
+pub enum HeightSampler {
+    Flat,
+    Noise {
+        seed: u32,
+        amplitude: f32,
+        frequency: f32,
+        octaves: u32,
+    },
+    Image {
+        image: Arc<Image>,
+        height_scale: f32,
+        world_size: f32,
+    },
+}
+
+impl HeightSampler {
+    pub fn sample(&self, position: Vec2) -> f32 {
+        match self {
+            HeightSampler::Flat => 0.0,
+            HeightSampler::Noise {
+                seed,
+                amplitude,
+                frequency,
+                octaves,
+            } => amplitude * fractal_noise(*seed, position * *frequency, *octaves),
+            HeightSampler::Image {
+                image,
+                height_scale,
+                world_size,
+            } => height_scale * sample_image(image, position / *world_size + 0.5),
+        }
+    }
+}
+
+/// Bilinear sample of the red channel, `uv` in 0..1.
+fn sample_image(image: &Image, uv: Vec2) -> f32 {
+    let size = image.size();
+    if size.x == 0 || size.y == 0 {
+        return 0.0;
+    }
+    let max = (size - UVec2::ONE).as_vec2();
+    let pixel = (uv.clamp(Vec2::ZERO, Vec2::ONE)) * max;
+    let base = pixel.floor();
+    let t = pixel - base;
+
+    let value_at = |x: f32, y: f32| {
+        image
+            .get_color_at(x.min(max.x) as u32, y.min(max.y) as u32)
+            .map(|color| color.to_linear().red)
+            .unwrap_or(0.0)
+    };
+
+    let top = value_at(base.x, base.y).lerp(value_at(base.x + 1.0, base.y), t.x);
+    let bottom = value_at(base.x, base.y + 1.0).lerp(value_at(base.x + 1.0, base.y + 1.0), t.x);
+    top.lerp(bottom, t.y)
+}
+
+/// Sum of value noise octaves, normalized to 0..1.
+fn fractal_noise(seed: u32, position: Vec2, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut weight_sum = 0.0;
+    let mut weight = 1.0;
+    let mut position = position;
+    for octave in 0..octaves.max(1) {
+        sum += value_noise(seed.wrapping_add(octave), position) * weight;
+        weight_sum += weight;
+        weight *= 0.5;
+        position *= 2.0;
+    }
+    sum / weight_sum
+}
+
+/// Smoothly interpolated random values on an integer lattice, in 0..1.
+fn value_noise(seed: u32, position: Vec2) -> f32 {
+    let cell = position.floor();
+    let t = position - cell;
+    let t = t * t * (Vec2::splat(3.0) - 2.0 * t);
+    let cell = cell.as_ivec2();
+
+    let a = lattice_value(seed, cell);
+    let b = lattice_value(seed, cell + IVec2::X);
+    let c = lattice_value(seed, cell + IVec2::Y);
+    let d = lattice_value(seed, cell + IVec2::ONE);
+    a.lerp(b, t.x).lerp(c.lerp(d, t.x), t.y)
+}
+
+fn lattice_value(seed: u32, cell: IVec2) -> f32 {
+    let mut hash =
+        seed ^ (cell.x as u32).wrapping_mul(0x27d4eb2d) ^ (cell.y as u32).wrapping_mul(0x165667b1);
+    hash = (hash ^ (hash >> 15)).wrapping_mul(0x85ebca6b);
+    hash = (hash ^ (hash >> 13)).wrapping_mul(0xc2b2ae35);
+    hash ^= hash >> 16;
+    hash as f32 / u32::MAX as f32
+}
@@ -0,0 +1,262 @@
+mod height_source;
+mod terrain;
+mod terrain_diagnostics;
+mod terrain_mesh;
+mod terrain_tools;
+
+use crate::keyboard_focus::shortcuts_enabled;
+use crate::settings::GameSettings;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use height_source::HeightSampler;
+use std::sync::Arc;
+use terrain_diagnostics::{measure_terrain, register_terrain_diagnostics};
+use terrain_mesh::build_chunk_meshes;
+use terrain_tools::{
+    apply_terrain_brush, enter_terrain_tools, exit_terrain_tools, terrain_tools_camera_control,
+    terrain_tools_ui, TerrainBrush,
+};
+
+pub use height_source::HeightSource;
+pub use terrain::{Terrain, TerrainChunkSave, MAX_TERRAIN_LAYERS};
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TerrainReadinessState {
+    #[default]
+    Generating,
+    Ready,
+}
+
+/// Whether the sculpting and painting tools are open.
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TerrainToolsState {
+    #[default]
+    Disabled,
+    Enabled,
+}
+
+#[derive(Resource)]
+pub struct TerrainSettings {
+    /// Side of the square world in meters, centered on the origin.
+    /// Only the chunks around the camera are loaded.
+    pub world_size: f32,
+    pub chunk_size: f32,
+    /// Quads along each side of a chunk.
+    pub chunk_resolution: u32,
+    /// Distance in meters after which the ground material repeats.
+    pub uv_tile_size: f32,
+    pub height_source: HeightSource,
+    /// Materials from the `MaterialLibrary` the terrain can be painted with.
+    /// The first one is the ground everywhere the terrain isn't painted.
+    pub layers: Vec<String>,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            world_size: 4096.0,
+            chunk_size: 16.0,
+            chunk_resolution: 32,
+            uv_tile_size: 2.0,
+            height_source: HeightSource::Noise {
+                seed: 0,
+                amplitude: 2.0,
+                frequency: 0.02,
+                octaves: 4,
+            },
+            layers: vec![
+                "Pond Side Grassy and Muddy Land 2k".to_string(),
+                "Sandy Trail Texture with Rocks Pebbles and Plants 2k".to_string(),
+            ],
+        }
+    }
+}
+
+/// Materials of the terrain layers, in the order of `TerrainSettings::layers`.
+/// The terrain is spawned once it is inserted.
+#[derive(Resource)]
+pub struct TerrainMaterials(pub Vec<Handle<StandardMaterial>>);
+
+/// A spawned terrain chunk. Its children hold one mesh per material layer.
+#[derive(Component)]
+pub struct TerrainChunk;
+
+/// Heightmap image of `HeightSource::Image`, kept to generate chunks anew.
+#[derive(Resource)]
+struct HeightmapHandle(Handle<Image>);
+
+/// Spawned chunk entities by chunk coordinate.
+#[derive(Resource, Default)]
+struct TerrainChunkEntities(HashMap<IVec2, Entity>);
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<TerrainReadinessState>()
+            .init_state::<TerrainToolsState>()
+            .init_resource::<TerrainSettings>()
+            .init_resource::<TerrainChunkEntities>()
+            .init_resource::<TerrainBrush>()
+            .add_systems(
+                OnEnter(TerrainReadinessState::Generating),
+                load_height_source,
+            )
+            .add_systems(
+                Update,
+                generate_terrain.run_if(in_state(TerrainReadinessState::Generating)),
+            )
+            .add_systems(
+                Update,
+                (update_terrain_chunks, measure_terrain).chain().run_if(
+                    in_state(TerrainReadinessState::Ready).and(resource_exists::<TerrainMaterials>),
+                ),
+            )
+            // ---------- Sculpting and painting tools
+            .add_systems(
+                Update,
+                terrain_tools_watchdog
+                    .run_if(in_state(TerrainReadinessState::Ready).and(shortcuts_enabled)),
+            )
+            .add_systems(OnEnter(TerrainToolsState::Enabled), enter_terrain_tools)
+            .add_systems(
+                Update,
+                (
+                    terrain_tools_camera_control,
+                    terrain_tools_ui,
+                    apply_terrain_brush,
+                )
+                    .chain()
+                    .run_if(in_state(TerrainToolsState::Enabled)),
+            )
+            .add_systems(OnExit(TerrainToolsState::Enabled), exit_terrain_tools);
+        register_terrain_diagnostics(app);
+    }
+}
+
+/// Starts loading the heightmap image, if the terrain is generated from one.
+fn load_height_source(
+    mut commands: Commands,
+    settings: Res<TerrainSettings>,
+    asset_server: Res<AssetServer>,
+) {
+    if let HeightSource::Image { path, .. } = &settings.height_source {
+        let handle = asset_server.load_with_settings(
+            path.clone(),
+            |settings: &mut bevy::image::ImageLoaderSettings| settings.is_srgb = false,
+        );
+        commands.insert_resource(HeightmapHandle(handle));
+    }
+}
+
+/// Everything needed to sample the configured height source.
+#[derive(SystemParam)]
+struct HeightSourceBridge<'w> {
+    settings: Res<'w, TerrainSettings>,
+    heightmap: Option<Res<'w, HeightmapHandle>>,
+    images: Res<'w, Assets<Image>>,
+}
+
+impl HeightSourceBridge<'_> {
+    /// `None` while the heightmap image is still loading.
+    fn sampler(&self) -> Option<HeightSampler> {
+        Some(match &self.settings.height_source {
+            HeightSource::Flat => HeightSampler::Flat,
+            HeightSource::Noise {
+                seed,
+                amplitude,
+                frequency,
+                octaves,
+            } => HeightSampler::Noise {
+                seed: *seed,
+                amplitude: *amplitude,
+                frequency: *frequency,
+                octaves: *octaves,
+            },
+            HeightSource::Image { height_scale, .. } => HeightSampler::Image {
+                image: Arc::new(self.images.get(&self.heightmap.as_ref()?.0)?.clone()),
+                height_scale: *height_scale,
+                world_size: self.settings.world_size,
+            },
+        })
+    }
+}
+
+/// Creates the terrain once the height source is available. Its chunks are streamed in later.
+/// Sets TerrainReadinessState::Ready when finished
+fn generate_terrain(
+    mut commands: Commands,
+    bridge: HeightSourceBridge,
+    mut terrain_readiness_state: ResMut<NextState<TerrainReadinessState>>,
+) {
+    let Some(sampler) = bridge.sampler() else {
+        return; // heightmap is still loading
+    };
+
+    commands.insert_resource(Terrain::new(
+        bridge.settings.chunk_size,
+        bridge.settings.chunk_resolution,
+        sampler,
+    ));
+    terrain_readiness_state.set(TerrainReadinessState::Ready);
+    info!("TerrainReadinessState::Ready");
+}
+
+/// Opens and closes the terrain tools.
+fn terrain_tools_watchdog(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_settings: Res<GameSettings>,
+    current_state: Res<State<TerrainToolsState>>,
+    mut next_state: ResMut<NextState<TerrainToolsState>>,
+) {
+    if keys.just_pressed(game_settings.keyboard.terrain_tools) {
+        match current_state.get() {
+            TerrainToolsState::Disabled => next_state.set(TerrainToolsState::Enabled),
+            TerrainToolsState::Enabled => next_state.set(TerrainToolsState::Disabled),
+        }
+    }
+}
+
+/// (Re)builds the meshes of the chunks whose heights or splat map changed
+/// and despawns the ones of unloaded chunks.
+fn update_terrain_chunks(
+    mut commands: Commands,
+    mut terrain: ResMut<Terrain>,
+    mut chunk_entities: ResMut<TerrainChunkEntities>,
+    settings: Res<TerrainSettings>,
+    materials: Res<TerrainMaterials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for coord in terrain.take_removed() {
+        if let Some(entity) = chunk_entities.0.remove(&coord) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for coord in terrain.take_dirty() {
+        let origin = terrain.chunk_origin(coord);
+        let entity = *chunk_entities.0.entry(coord).or_insert_with(|| {
+            commands
+                .spawn((
+                    Transform::from_xyz(origin.x, 0.0, origin.y),
+                    Visibility::default(),
+                    TerrainChunk,
+                ))
+                .id()
+        });
+
+        let layer_meshes = build_chunk_meshes(&terrain, coord, settings.uv_tile_size);
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for (layer, mesh) in layer_meshes {
+                    let Some(material) = materials.0.get(layer).or(materials.0.first()) else {
+                        continue;
+                    };
+                    parent.spawn((Mesh3d(meshes.add(mesh)), MeshMaterial3d(material.clone())));
+                }
+            });
+    }
+}
@@ -0,0 +1,336 @@
+use super::height_source::HeightSampler;
+use bevy::math::Ray3d;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+/// Maximal number of material layers the terrain can be painted with.
+pub const MAX_TERRAIN_LAYERS: usize = 4;
+
+/// Weight of each material layer at a vertex, 0..255.
+pub type SplatWeights = [u8; MAX_TERRAIN_LAYERS];
+
+/// Heights and splat map of one chunk on a `(resolution + 1)²` vertex grid.
+/// Vertices on the chunk borders are stored by both neighbouring chunks.
+pub struct TerrainChunkData {
+    heights: Vec<f32>,
+    splat: Vec<SplatWeights>,
+}
+
+/// An edited chunk as stored in the world save.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TerrainChunkSave {
+    pub coord: IVec2,
+    pub heights: Vec<f32>,
+    pub splat: Vec<SplatWeights>,
+}
+
+/// Generates the heights of chunks from the height source. Can be sent to other threads.
+#[derive(Clone)]
+pub struct ChunkGenerator {
+    chunk_size: f32,
+    resolution: u32,
+    sampler: HeightSampler,
+}
+
+impl ChunkGenerator {
+    pub fn generate(&self, coord: IVec2) -> TerrainChunkSave {
+        let vertices = self.resolution as i32 + 1;
+        let first_vertex = coord * self.resolution as i32;
+        let cell_size = self.chunk_size / self.resolution as f32;
+        let heights = (0..vertices * vertices)
+            .map(|i| {
+                let vertex = first_vertex + IVec2::new(i % vertices, i / vertices);
+                self.sampler.sample(vertex.as_vec2() * cell_size)
+            })
+            .collect();
+        let mut base_layer = SplatWeights::default();
+        base_layer[0] = u8::MAX;
+        let splat = vec![base_layer; (vertices * vertices) as usize];
+        TerrainChunkSave {
+            coord,
+            heights,
+            splat,
+        }
+    }
+}
+
+/// The terrain heights, split into square chunks which are streamed in and out around the camera.
+/// Chunk `(x, z)` covers `x * chunk_size..(x + 1) * chunk_size` on both axes.
+#[derive(Resource)]
+pub struct Terrain {
+    chunk_size: f32,
+    /// Quads along each side of a chunk.
+    resolution: u32,
+    sampler: HeightSampler,
+    chunks: HashMap<IVec2, TerrainChunkData>,
+    /// Chunks whose mesh has to be (re)built.
+    dirty: HashSet<IVec2>,
+    /// Chunks unloaded since their meshes were last updated.
+    removed: HashSet<IVec2>,
+    /// Chunks changed by the player, which have to be saved.
+    edited: HashSet<IVec2>,
+}
+
+impl Terrain {
+    /// A terrain without chunks. They are added as the world streams in.
+    pub fn new(chunk_size: f32, resolution: u32, sampler: HeightSampler) -> Self {
+        Self {
+            chunk_size,
+            resolution: resolution.max(1),
+            sampler,
+            chunks: HashMap::default(),
+            dirty: HashSet::default(),
+            removed: HashSet::default(),
+            edited: HashSet::default(),
+        }
+    }
+
+    pub fn chunk_generator(&self) -> ChunkGenerator {
+        ChunkGenerator {
+            chunk_size: self.chunk_size,
+            resolution: self.resolution,
+            sampler: self.sampler.clone(),
+        }
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// Distance between two neighbouring vertices.
+    pub fn cell_size(&self) -> f32 {
+        self.chunk_size / self.resolution as f32
+    }
+
+    pub fn chunk_origin(&self, coord: IVec2) -> Vec2 {
+        coord.as_vec2() * self.chunk_size
+    }
+
+    /// Returns the chunks whose mesh has to be rebuilt and forgets about them.
+    pub fn take_dirty(&mut self) -> Vec<IVec2> {
+        self.dirty.drain().collect()
+    }
+
+    /// Returns the chunks whose mesh has to be removed and forgets about them.
+    pub fn take_removed(&mut self) -> Vec<IVec2> {
+        self.removed.drain().collect()
+    }
+
+    /// World position (x, z) of a vertex of the global vertex grid.
+    pub fn vertex_position(&self, vertex: IVec2) -> Vec2 {
+        vertex.as_vec2() * self.cell_size()
+    }
+
+    /// Chunks which store a vertex of the global vertex grid with its index inside of them.
+    /// Vertices on chunk borders are stored by up to four chunks.
+    fn vertex_locations(&self, vertex: IVec2) -> impl Iterator<Item = (IVec2, usize)> + '_ {
+        let resolution = self.resolution as i32;
+        let coord = vertex.div_euclid(IVec2::splat(resolution));
+        [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE]
+            .into_iter()
+            .map(move |offset| coord - offset)
+            .filter(move |coord| {
+                let local = vertex - *coord * resolution;
+                local.max_element() <= resolution && self.chunks.contains_key(coord)
+            })
+            .map(move |coord| (coord, self.local_index(vertex - coord * resolution)))
+    }
+
+    /// Height of a vertex of the global vertex grid, `None` outside of the generated chunks.
+    pub fn vertex_height(&self, vertex: IVec2) -> Option<f32> {
+        self.vertex_locations(vertex)
+            .next()
+            .map(|(coord, index)| self.chunks[&coord].heights[index])
+    }
+
+    pub fn vertex_splat(&self, vertex: IVec2) -> Option<SplatWeights> {
+        self.vertex_locations(vertex)
+            .next()
+            .map(|(coord, index)| self.chunks[&coord].splat[index])
+    }
+
+    pub fn set_vertex_height(&mut self, vertex: IVec2, height: f32) {
+        self.edit_vertex(vertex, |chunk, index| chunk.heights[index] = height);
+        // Normals of the neighbours depend on this vertex too
+        for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let coords: Vec<IVec2> = self
+                .vertex_locations(vertex + offset)
+                .map(|(coord, _)| coord)
+                .collect();
+            self.dirty.extend(coords);
+        }
+    }
+
+    pub fn set_vertex_splat(&mut self, vertex: IVec2, weights: SplatWeights) {
+        self.edit_vertex(vertex, |chunk, index| chunk.splat[index] = weights);
+    }
+
+    fn edit_vertex(&mut self, vertex: IVec2, mut edit: impl FnMut(&mut TerrainChunkData, usize)) {
+        let locations: Vec<(IVec2, usize)> = self.vertex_locations(vertex).collect();
+        for (coord, index) in locations {
+            if let Some(chunk) = self.chunks.get_mut(&coord) {
+                edit(chunk, index);
+            }
+            self.dirty.insert(coord);
+            self.edited.insert(coord);
+        }
+    }
+
+    /// Vertices within `radius` of `center` with a smooth falloff from 1 in the center to 0 at the edge.
+    pub fn vertices_in_radius(&self, center: Vec2, radius: f32) -> Vec<(IVec2, f32)> {
+        let cell_size = self.cell_size();
+        let min = ((center - radius) / cell_size).floor().as_ivec2();
+        let max = ((center + radius) / cell_size).ceil().as_ivec2();
+
+        let mut vertices = Vec::new();
+        for z in min.y..=max.y {
+            for x in min.x..=max.x {
+                let vertex = IVec2::new(x, z);
+                let distance = self.vertex_position(vertex).distance(center) / radius;
+                if distance < 1.0 && self.vertex_height(vertex).is_some() {
+                    let falloff = 1.0 - distance * distance;
+                    vertices.push((vertex, falloff * falloff));
+                }
+            }
+        }
+        vertices
+    }
+
+    /// First point where the ray hits the terrain.
+    pub fn raycast(&self, ray: Ray3d, max_distance: f32) -> Option<Vec3> {
+        let step = self.cell_size() * 0.5;
+        let is_below_ground = |distance: f32| {
+            let point = ray.get_point(distance);
+            self.height_at(point.x, point.z)
+                .is_some_and(|ground| point.y <= ground)
+        };
+
+        let mut distance = 0.0;
+        while distance < max_distance {
+            let next = distance + step;
+            if is_below_ground(next) {
+                // Refine the hit between the last two samples
+                let (mut above, mut below) = (distance, next);
+                for _ in 0..8 {
+                    let middle = (above + below) / 2.0;
+                    if is_below_ground(middle) {
+                        below = middle;
+                    } else {
+                        above = middle;
+                    }
+                }
+                return Some(ray.get_point(below));
+            }
+            distance = next;
+        }
+        None
+    }
+
+    /// A chunk as stored in the world save, `None` if it isn't loaded or wasn't changed by the player.
+    pub fn edited_chunk(&self, coord: IVec2) -> Option<TerrainChunkSave> {
+        if !self.edited.contains(&coord) {
+            return None;
+        }
+        self.chunks.get(&coord).map(|chunk| TerrainChunkSave {
+            coord,
+            heights: chunk.heights.clone(),
+            splat: chunk.splat.clone(),
+        })
+    }
+
+    /// Adds a generated or saved chunk. Saves that don't fit the terrain are generated anew.
+    pub fn insert_chunk(&mut self, save: TerrainChunkSave, edited: bool) {
+        let vertices = (self.resolution as usize + 1).pow(2);
+        let (save, edited) = if save.heights.len() != vertices || save.splat.len() != vertices {
+            warn!(
+                "Saved terrain chunk {} doesn't fit the terrain, generated anew",
+                save.coord
+            );
+            (self.chunk_generator().generate(save.coord), false)
+        } else {
+            (save, edited)
+        };
+        let coord = save.coord;
+        self.chunks.insert(
+            coord,
+            TerrainChunkData {
+                heights: save.heights,
+                splat: save.splat,
+            },
+        );
+        self.removed.remove(&coord);
+        self.dirty.insert(coord);
+        if edited {
+            self.edited.insert(coord);
+        } else {
+            self.edited.remove(&coord);
+        }
+        self.mark_neighbours_dirty(coord);
+    }
+
+    /// Removes a chunk, returning it if the player changed it.
+    pub fn remove_chunk(&mut self, coord: IVec2) -> Option<TerrainChunkSave> {
+        let save = self.edited_chunk(coord);
+        self.chunks.remove(&coord);
+        self.edited.remove(&coord);
+        self.dirty.remove(&coord);
+        self.removed.insert(coord);
+        self.mark_neighbours_dirty(coord);
+        save
+    }
+
+    /// Removes all chunks, e.g. before another world is loaded.
+    pub fn clear(&mut self) {
+        let coords: Vec<IVec2> = self.chunks.keys().copied().collect();
+        self.removed.extend(coords);
+        self.chunks.clear();
+        self.dirty.clear();
+        self.edited.clear();
+    }
+
+    /// Border vertices and normals of the neighbours depend on a chunk.
+    fn mark_neighbours_dirty(&mut self, coord: IVec2) {
+        for offset in [
+            IVec2::X,
+            IVec2::NEG_X,
+            IVec2::Y,
+            IVec2::NEG_Y,
+            IVec2::ONE,
+            IVec2::NEG_ONE,
+            IVec2::new(1, -1),
+            IVec2::new(-1, 1),
+        ] {
+            if self.chunks.contains_key(&(coord + offset)) {
+                self.dirty.insert(coord + offset);
+            }
+        }
+    }
+
+    /// Terrain height at a world position, `None` outside of the generated chunks.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let grid = Vec2::new(x, z) / self.cell_size();
+        let base = grid.floor();
+        let t = grid - base;
+        let base = base.as_ivec2();
+
+        let a = self.vertex_height(base)?;
+        let b = self.vertex_height(base + IVec2::X)?;
+        let c = self.vertex_height(base + IVec2::Y)?;
+        let d = self.vertex_height(base + IVec2::ONE)?;
+        Some(a.lerp(b, t.x).lerp(c.lerp(d, t.x), t.y))
+    }
+
+    /// Surface normal at a vertex, from the heights of its neighbours.
+    pub fn vertex_normal(&self, vertex: IVec2) -> Vec3 {
+        let height = self.vertex_height(vertex).unwrap_or(0.0);
+        let height_or_own = |vertex: IVec2| self.vertex_height(vertex).unwrap_or(height);
+        let dx = height_or_own(vertex + IVec2::X) - height_or_own(vertex - IVec2::X);
+        let dz = height_or_own(vertex + IVec2::Y) - height_or_own(vertex - IVec2::Y);
+        Vec3::new(-dx, 2.0 * self.cell_size(), -dz).normalize()
+    }
+
+    fn local_index(&self, local: IVec2) -> usize {
+        (local.y * (self.resolution as i32 + 1) + local.x) as usize
+    }
+}
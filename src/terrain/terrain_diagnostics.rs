@@ -0,0 +1,58 @@
+use super::{TerrainChunk, TerrainSettings};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+
+/// Mesh entities of the terrain. Every one of them is a single draw call.
+pub const TERRAIN_DRAW_CALLS: DiagnosticPath = DiagnosticPath::const_new("terrain/draw_calls");
+pub const TERRAIN_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("terrain/chunks");
+pub const TERRAIN_VERTICES: DiagnosticPath = DiagnosticPath::const_new("terrain/vertices");
+/// Entities the same ground would need with one entity per 2×2 m tile, as it was before chunking.
+pub const TERRAIN_TILE_ENTITIES: DiagnosticPath =
+    DiagnosticPath::const_new("terrain/tile_entities");
+
+/// Side of the ground tiles the terrain is compared with.
+const TILE_SIZE: f32 = 2.0;
+
+pub fn register_terrain_diagnostics(app: &mut App) {
+    app.register_diagnostic(Diagnostic::new(TERRAIN_DRAW_CALLS))
+        .register_diagnostic(Diagnostic::new(TERRAIN_CHUNKS))
+        .register_diagnostic(Diagnostic::new(TERRAIN_VERTICES))
+        .register_diagnostic(Diagnostic::new(TERRAIN_TILE_ENTITIES));
+}
+
+/// Counts the meshes of the loaded terrain and logs the comparison with a tiled ground
+/// once the first chunks are built.
+pub fn measure_terrain(
+    mut diagnostics: Diagnostics,
+    chunks: Query<&Children, With<TerrainChunk>>,
+    layer_meshes: Query<&Mesh3d>,
+    meshes: Res<Assets<Mesh>>,
+    settings: Res<TerrainSettings>,
+    mut logged: Local<bool>,
+) {
+    let chunk_count = chunks.iter().count();
+    let mut draw_calls = 0;
+    let mut vertices = 0;
+    for mesh in chunks
+        .iter()
+        .flat_map(|children| layer_meshes.iter_many(children))
+    {
+        draw_calls += 1;
+        vertices += meshes.get(&mesh.0).map_or(0, Mesh::count_vertices);
+    }
+    let tile_entities = chunk_count * (settings.chunk_size / TILE_SIZE).ceil().powi(2) as usize;
+
+    diagnostics.add_measurement(&TERRAIN_DRAW_CALLS, || draw_calls as f64);
+    diagnostics.add_measurement(&TERRAIN_CHUNKS, || chunk_count as f64);
+    diagnostics.add_measurement(&TERRAIN_VERTICES, || vertices as f64);
+    diagnostics.add_measurement(&TERRAIN_TILE_ENTITIES, || tile_entities as f64);
+
+    if !*logged && draw_calls > 0 {
+        *logged = true;
+        info!(
+            "Terrain drawn with {draw_calls} meshes in {chunk_count} chunks \
+            ({} entities), a ground of {TILE_SIZE}×{TILE_SIZE} m tiles would need {tile_entities}",
+            chunk_count + draw_calls,
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use super::terrain::{Terrain, MAX_TERRAIN_LAYERS};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+
+/// Builds the meshes of one chunk, positioned relative to the chunk origin.
+/// Every quad is drawn with the material layer that dominates the splat map at its corners,
+/// so there is one mesh per layer used in the chunk.
+/// UVs are in world space, so the material repeats every `uv_tile_size` meters
+/// and lines up across chunk borders.
+pub fn build_chunk_meshes(
+    terrain: &Terrain,
+    coord: IVec2,
+    uv_tile_size: f32,
+) -> Vec<(usize, Mesh)> {
+    let resolution = terrain.resolution() as i32;
+    let vertices = resolution + 1;
+    let first_vertex = coord * resolution;
+    let origin = terrain.chunk_origin(coord);
+
+    let mut positions = Vec::with_capacity((vertices * vertices) as usize);
+    let mut normals = Vec::with_capacity(positions.capacity());
+    let mut uvs = Vec::with_capacity(positions.capacity());
+    let mut splat = Vec::with_capacity(positions.capacity());
+    for z in 0..vertices {
+        for x in 0..vertices {
+            let vertex = first_vertex + IVec2::new(x, z);
+            let position = terrain.vertex_position(vertex);
+            let height = terrain.vertex_height(vertex).unwrap_or(0.0);
+            let local = position - origin;
+
+            positions.push([local.x, height, local.y]);
+            normals.push(terrain.vertex_normal(vertex).to_array());
+            uvs.push((position / uv_tile_size).to_array());
+            splat.push(terrain.vertex_splat(vertex).unwrap_or_default());
+        }
+    }
+
+    let mut layer_indices: [Vec<u32>; MAX_TERRAIN_LAYERS] = Default::default();
+    for z in 0..resolution {
+        for x in 0..resolution {
+            let a = (z * vertices + x) as u32;
+            let b = a + 1;
+            let c = a + vertices as u32;
+            let d = c + 1;
+
+            let layer = (0..MAX_TERRAIN_LAYERS)
+                .max_by_key(|&layer| {
+                    [a, b, c, d]
+                        .iter()
+                        .map(|&i| splat[i as usize][layer] as u32)
+                        .sum::<u32>()
+                })
+                .unwrap_or(0);
+            layer_indices[layer].extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+
+    layer_indices
+        .into_iter()
+        .enumerate()
+        .filter(|(_, indices)| !indices.is_empty())
+        .map(|(layer, indices)| {
+            let mut mesh = Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            )
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals.clone())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs.clone())
+            .with_inserted_indices(Indices::U32(indices));
+            mesh.generate_tangents()
+                .expect("Failed to generate tangents");
+            (layer, mesh)
+        })
+        .collect()
+}
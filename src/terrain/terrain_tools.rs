@@ -0,0 +1,242 @@
+use super::terrain::SplatWeights;
+use super::{Terrain, TerrainSettings, MAX_TERRAIN_LAYERS};
+use crate::universal_camera_controller::{UniCamChangeStateEvent, UniCamController, UniCamState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use bevy_egui::egui::Slider;
+use bevy_egui::{egui, EguiContexts};
+use std::f32::consts::FRAC_PI_2;
+
+/// How far from the camera the brush reaches.
+const BRUSH_REACH: f32 = 100.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TerrainTool {
+    Raise,
+    Lower,
+    Flatten,
+    Smooth,
+    Paint,
+}
+
+#[derive(Resource)]
+pub struct TerrainBrush {
+    pub tool: TerrainTool,
+    pub radius: f32,
+    /// Meters per second when raising and lowering,
+    /// share of the way to the result per second for the other tools.
+    pub strength: f32,
+    /// Material layer the paint tool paints with.
+    pub layer: usize,
+    /// Height the flatten tool levels to, taken where the stroke started.
+    flatten_height: Option<f32>,
+}
+
+impl Default for TerrainBrush {
+    fn default() -> Self {
+        Self {
+            tool: TerrainTool::Raise,
+            radius: 3.0,
+            strength: 1.0,
+            layer: 1,
+            flatten_height: None,
+        }
+    }
+}
+
+pub fn enter_terrain_tools(
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut evw_change_universal_cam: EventWriter<UniCamChangeStateEvent>,
+) {
+    window.cursor_options.grab_mode = CursorGrabMode::Confined;
+    window.cursor_options.visible = true;
+    evw_change_universal_cam.send(UniCamChangeStateEvent(UniCamState::Disabled));
+}
+
+pub fn exit_terrain_tools(
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut evw_change_universal_cam: EventWriter<UniCamChangeStateEvent>,
+) {
+    window.cursor_options.grab_mode = CursorGrabMode::Locked;
+    window.cursor_options.visible = false;
+    evw_change_universal_cam.send(UniCamChangeStateEvent(UniCamState::Enabled));
+}
+
+/// The cursor is used by the brush, so the camera only moves while the right mouse button is held.
+pub fn terrain_tools_camera_control(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut evw_change_universal_cam: EventWriter<UniCamChangeStateEvent>,
+) {
+    if buttons.just_pressed(MouseButton::Right) {
+        window.cursor_options.grab_mode = CursorGrabMode::Locked;
+        window.cursor_options.visible = false;
+        evw_change_universal_cam.send(UniCamChangeStateEvent(UniCamState::Enabled));
+    } else if buttons.just_released(MouseButton::Right) {
+        window.cursor_options.grab_mode = CursorGrabMode::Confined;
+        window.cursor_options.visible = true;
+        evw_change_universal_cam.send(UniCamChangeStateEvent(UniCamState::Disabled));
+    }
+}
+
+pub fn terrain_tools_ui(
+    mut contexts: EguiContexts,
+    mut brush: ResMut<TerrainBrush>,
+    settings: Res<TerrainSettings>,
+) {
+    egui::Window::new("Terrain tools").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            for (tool, label) in [
+                (TerrainTool::Raise, "Raise"),
+                (TerrainTool::Lower, "Lower"),
+                (TerrainTool::Flatten, "Flatten"),
+                (TerrainTool::Smooth, "Smooth"),
+                (TerrainTool::Paint, "Paint"),
+            ] {
+                ui.selectable_value(&mut brush.tool, tool, label);
+            }
+        });
+        ui.add(Slider::new(&mut brush.radius, 0.5..=10.0).text("Brush size"));
+        ui.add(Slider::new(&mut brush.strength, 0.1..=5.0).text("Strength"));
+
+        let is_painting = brush.tool == TerrainTool::Paint;
+        ui.add_enabled_ui(is_painting, |ui| {
+            ui.collapsing("Layers", |ui| {
+                for (layer, name) in settings.layers.iter().enumerate().take(MAX_TERRAIN_LAYERS) {
+                    ui.radio_value(&mut brush.layer, layer, name);
+                }
+            });
+        });
+        ui.separator();
+        ui.label("Hold the right mouse button to look around");
+    });
+}
+
+/// Everything needed to find what the cursor points at.
+#[derive(SystemParam)]
+pub struct CursorRayBridge<'w, 's> {
+    window: Single<'w, &'static Window, With<PrimaryWindow>>,
+    camera: Single<'w, (&'static Camera, &'static GlobalTransform), With<UniCamController>>,
+    contexts: EguiContexts<'w, 's>,
+}
+
+impl CursorRayBridge<'_, '_> {
+    /// Ray from the camera through the cursor, `None` if the cursor is outside of the window or over the UI.
+    fn cursor_ray(&mut self) -> Option<Ray3d> {
+        if self.contexts.ctx_mut().is_pointer_over_area() {
+            return None;
+        }
+        let cursor = self.window.cursor_position()?;
+        let (camera, camera_transform) = *self.camera;
+        camera.viewport_to_world(camera_transform, cursor).ok()
+    }
+}
+
+/// Applies the brush under the cursor while the left mouse button is held.
+pub fn apply_terrain_brush(
+    mut cursor_ray_bridge: CursorRayBridge,
+    mut terrain: ResMut<Terrain>,
+    mut brush: ResMut<TerrainBrush>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+) {
+    if buttons.pressed(MouseButton::Right) {
+        return;
+    }
+    let Some(ray) = cursor_ray_bridge.cursor_ray() else {
+        return;
+    };
+    let Some(hit) = terrain.raycast(ray, BRUSH_REACH) else {
+        return;
+    };
+
+    gizmos.circle(
+        Isometry3d::new(hit, Quat::from_rotation_x(FRAC_PI_2)),
+        brush.radius,
+        Color::WHITE,
+    );
+
+    if buttons.just_pressed(MouseButton::Left) {
+        brush.flatten_height = Some(hit.y);
+    }
+    if !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+
+    let amount = brush.strength * time.delta_secs();
+    let vertices = terrain.vertices_in_radius(hit.xz(), brush.radius);
+    let height_of = |terrain: &Terrain, vertex: IVec2| terrain.vertex_height(vertex).unwrap_or(0.0);
+
+    let new_heights: Vec<(IVec2, f32)> = match brush.tool {
+        TerrainTool::Raise | TerrainTool::Lower => {
+            let sign = if brush.tool == TerrainTool::Raise {
+                1.0
+            } else {
+                -1.0
+            };
+            vertices
+                .iter()
+                .map(|&(vertex, falloff)| {
+                    (
+                        vertex,
+                        height_of(&terrain, vertex) + sign * amount * falloff,
+                    )
+                })
+                .collect()
+        }
+        TerrainTool::Flatten => {
+            let target = brush.flatten_height.unwrap_or(hit.y);
+            vertices
+                .iter()
+                .map(|&(vertex, falloff)| {
+                    let height = height_of(&terrain, vertex);
+                    (vertex, height.lerp(target, (amount * falloff).min(1.0)))
+                })
+                .collect()
+        }
+        TerrainTool::Smooth => vertices
+            .iter()
+            .map(|&(vertex, falloff)| {
+                let height = height_of(&terrain, vertex);
+                let average = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+                    .iter()
+                    .map(|&offset| terrain.vertex_height(vertex + offset).unwrap_or(height))
+                    .sum::<f32>()
+                    / 4.0;
+                (vertex, height.lerp(average, (amount * falloff).min(1.0)))
+            })
+            .collect(),
+        TerrainTool::Paint => {
+            for &(vertex, falloff) in &vertices {
+                if let Some(weights) = terrain.vertex_splat(vertex) {
+                    let weights = paint(weights, brush.layer, (amount * falloff).min(1.0));
+                    terrain.set_vertex_splat(vertex, weights);
+                }
+            }
+            Vec::new()
+        }
+    };
+
+    for (vertex, height) in new_heights {
+        terrain.set_vertex_height(vertex, height);
+    }
+}
+
+/// Moves `amount` (0..1) of the weight of the other layers to `layer`.
+fn paint(weights: SplatWeights, layer: usize, amount: f32) -> SplatWeights {
+    if layer >= MAX_TERRAIN_LAYERS {
+        return weights;
+    }
+    let mut result = weights;
+    let mut others = 0_u32;
+    for (i, weight) in result.iter_mut().enumerate() {
+        if i != layer {
+            *weight = (*weight as f32 * (1.0 - amount)).floor() as u8;
+            others += *weight as u32;
+        }
+    }
+    result[layer] = (u8::MAX as u32).saturating_sub(others) as u8;
+    result
+}
@@ -1,4 +1,4 @@
-use super::{Bridge, UniCamTrait};
+use super::{min_camera_height, Bridge, UniCamTrait};
 // use bevy::prelude::{Component, EulerRot, KeyCode, Quat, Vec3};
 use bevy::prelude::*;
 
@@ -61,6 +61,15 @@ impl FlyingCamera {
             desired_position.y -= delta_move;
         }
 
+        if let Some(min_height) = min_camera_height(
+            bridge.terrain.as_deref(),
+            &bridge.settings,
+            desired_position.x,
+            desired_position.z,
+        ) {
+            desired_position.y = desired_position.y.max(min_height);
+        }
+
         self.desired_position = desired_position;
 
         // cam_transform.translation = cam_transform.translation.lerp(desired_position, 0.5);
@@ -4,6 +4,7 @@ mod settings;
 mod spherical_camera;
 
 use crate::settings::GameSettings;
+use crate::terrain::Terrain;
 use bevy::ecs::system::SystemParam;
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
@@ -54,7 +55,7 @@ fn change_cam_mode(
 
 /// A system parameter that provides access to various resources
 /// needed for camera updates, including time, settings,
-/// camera transform, mouse movement events, and keyboard input.
+/// camera transform, mouse movement events, keyboard input and the terrain.
 /// This acts as a bridge between the camera controllers and the Bevy ECS.
 #[derive(SystemParam)]
 struct Bridge<'w, 's> {
@@ -64,6 +65,19 @@ struct Bridge<'w, 's> {
     evr_mouse_movement: EventReader<'w, 's, MouseMotion>,
     keys: Res<'w, ButtonInput<KeyCode>>,
     game_settings: Res<'w, GameSettings>,
+    terrain: Option<Res<'w, Terrain>>,
+}
+
+/// Lowest height the camera may have at the given position, `None` where there is no ground.
+fn min_camera_height(
+    terrain: Option<&Terrain>,
+    settings: &UniCamSettings,
+    x: f32,
+    z: f32,
+) -> Option<f32> {
+    terrain
+        .and_then(|terrain| terrain.height_at(x, z))
+        .map(|ground| ground + settings.ground_clearance)
 }
 
 /// Trait that defines the behavior of different camera types.
@@ -2,6 +2,7 @@ use super::{
     change_cam_mode, uni_cam_controller, uni_cam_watchdog, UniCamChangeStateEvent, UniCamSettings,
     UniCamState,
 };
+use crate::keyboard_focus::shortcuts_enabled;
 use bevy::prelude::{in_state, App, AppExtStates, IntoSystemConfigs, Plugin, Update};
 
 pub struct UniCamPlugin;
@@ -14,7 +15,11 @@ impl Plugin for UniCamPlugin {
             .add_systems(Update, uni_cam_watchdog)
             .add_systems(
                 Update,
-                (change_cam_mode, uni_cam_controller).run_if(in_state(UniCamState::Enabled)),
+                (
+                    change_cam_mode.run_if(shortcuts_enabled),
+                    uni_cam_controller,
+                )
+                    .run_if(in_state(UniCamState::Enabled)),
             );
     }
 }
@@ -3,12 +3,15 @@ use bevy::prelude::Resource;
 #[derive(Resource)]
 pub struct UniCamSettings {
     pub movement_speed: f32,
+    /// Minimal distance between the camera and the terrain.
+    pub ground_clearance: f32,
 }
 
 impl Default for UniCamSettings {
     fn default() -> Self {
         Self {
             movement_speed: 7.0,
+            ground_clearance: 0.5,
         }
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,4 @@
-use super::{Bridge, UniCamTrait};
+use super::{min_camera_height, Bridge, UniCamTrait};
 use bevy::math::{Mat3, Quat};
 use bevy::prelude::{Component, Transform, Vec3};
 
@@ -56,6 +56,23 @@ impl UniCamTrait for SphericalCamera {
         let desired_rotation =
             Quat::from_mat3(&Mat3::from_cols(right, up_corrected, look_at_direction));
 
+        // Orbit around the ground at the origin and never go below the terrain
+        let target = Vec3::Y
+            * bridge
+                .terrain
+                .as_ref()
+                .and_then(|terrain| terrain.height_at(0.0, 0.0))
+                .unwrap_or(0.0);
+        let mut new_position = target + new_position;
+        if let Some(min_height) = min_camera_height(
+            bridge.terrain.as_deref(),
+            &bridge.settings,
+            new_position.x,
+            new_position.z,
+        ) {
+            new_position.y = new_position.y.max(min_height);
+        }
+
         // Smooth interpolation factor based on delta time
         let t = 1.0 - (-30.0 * bridge.time.delta_secs()).exp();
 
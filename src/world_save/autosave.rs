@@ -0,0 +1,74 @@
+use super::save_slots::{list_save_slots, slot_modified};
+use super::SaveWorldEvent;
+use crate::file_names::is_valid_file_stem;
+use crate::settings::GameSettings;
+use bevy::prelude::*;
+use std::time::Duration;
+
+const AUTOSAVE_PREFIX: &str = "autosave_";
+
+#[derive(Resource)]
+pub struct AutosaveTimer(Timer);
+
+impl Default for AutosaveTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(300.0, TimerMode::Repeating))
+    }
+}
+
+/// Periodically saves the world into the autosave slots, overwriting the oldest one.
+pub fn autosave_system(
+    mut timer: ResMut<AutosaveTimer>,
+    time: Res<Time>,
+    game_settings: Res<GameSettings>,
+    mut evw_save: EventWriter<SaveWorldEvent>,
+) {
+    let autosave = &game_settings.autosave;
+    if !autosave.enabled {
+        return;
+    }
+
+    let interval = Duration::from_secs_f32(autosave.interval_secs);
+    if timer.0.duration() != interval {
+        timer.0.set_duration(interval);
+    }
+
+    if timer.0.tick(time.delta()).just_finished() {
+        let slot = next_autosave_slot(autosave.slots);
+        info!("Autosaving into \"{slot}\"");
+        evw_save.send(SaveWorldEvent(slot));
+    }
+}
+
+/// A free autosave slot if there is one, otherwise the least recently written.
+fn next_autosave_slot(slots: u32) -> String {
+    (1..=slots.max(1))
+        .map(|index| format!("{AUTOSAVE_PREFIX}{index}"))
+        .min_by_key(|name| slot_modified(name))
+        .unwrap_or_default()
+}
+
+/// Whether the player may save under the name. Names of autosaves are reserved in any case,
+/// a manual save named like one would be rotated away or loaded as a fallback.
+pub fn is_valid_save_name(name: &str) -> bool {
+    is_valid_file_stem(name)
+        && !name
+            .trim()
+            .to_ascii_lowercase()
+            .starts_with(AUTOSAVE_PREFIX)
+}
+
+pub fn is_autosave_slot(name: &str) -> bool {
+    name.strip_prefix(AUTOSAVE_PREFIX)
+        .is_some_and(|index| index.parse::<u32>().is_ok())
+}
+
+/// Names of the autosave slots of a world, the most recent first.
+pub fn world_autosaves(world_id: u64) -> Vec<String> {
+    list_save_slots()
+        .into_iter()
+        .filter(|slot| is_autosave_slot(&slot.name))
+        .filter(|slot| slot.meta.as_ref().and_then(|meta| meta.world_id) == Some(world_id))
+        .map(|slot| slot.name)
+        .collect()
+}
@@ -0,0 +1,330 @@
+mod autosave;
+mod notices;
+mod save_slots;
+mod thumbnail;
+mod world_save;
+
+use crate::building::{
+    BuildingReadinessState, BuildingTier, MaterialVariant, Openable, PlacedBuilding,
+};
+use crate::environment::{TimeOfDay, Weather};
+use crate::harvesting::HarvestedNodes;
+use crate::inventory::{GameMode, Inventory};
+use crate::main_menu::MainMenuActivityState;
+use crate::terrain::{Terrain, TerrainSettings};
+use crate::universal_camera_controller::UniCamController;
+use crate::world_streaming::{StreamWorldEvent, WorldChunks};
+use autosave::{autosave_system, world_autosaves, AutosaveTimer};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use notices::save_notices_window;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use thumbnail::{capture_thumbnail_system, spawn_thumbnail_camera};
+use world_save::{read_meta, read_world, write_world, WorldSave, WorldSaveMeta, THUMBNAIL_FILE};
+
+pub use autosave::is_valid_save_name;
+pub use notices::SaveNotices;
+
+pub use world_save::{ChunkSave, ChunkSource, PlacedBuildingSave, WorldSaveError};
+
+pub use save_slots::{
+    delete_slot, duplicate_slot, list_save_slots, rename_slot, slot_dir, slot_exists, SaveSlotInfo,
+};
+
+/// Directory (relative to the working directory) with one subdirectory per save slot.
+pub const SAVES_DIR: &str = "saves";
+
+/// Saves the current world into the named slot, overwriting it if it exists.
+#[derive(Event)]
+pub struct SaveWorldEvent(pub String);
+
+/// Replaces the current world with the one from the named slot.
+#[derive(Event)]
+pub struct LoadWorldEvent(pub String);
+
+/// Replaces the current world with a new one. Its game mode can't be changed afterwards.
+#[derive(Event)]
+pub struct NewWorldEvent(pub GameMode);
+
+/// Time played in the current world, stored with the save.
+#[derive(Resource, Default)]
+pub struct PlayTime(pub Duration);
+
+/// Identifies the current world in its saves, so a damaged save only falls back to
+/// autosaves of the same world.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WorldId(pub u64);
+
+impl WorldId {
+    pub fn random() -> Self {
+        Self(RandomState::new().build_hasher().finish())
+    }
+}
+
+impl Default for WorldId {
+    fn default() -> Self {
+        Self::random()
+    }
+}
+
+pub struct WorldSavePlugin;
+
+impl Plugin for WorldSavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayTime>()
+            .init_resource::<WorldId>()
+            .init_resource::<AutosaveTimer>()
+            .init_resource::<SaveNotices>()
+            .add_event::<SaveWorldEvent>()
+            .add_event::<LoadWorldEvent>()
+            .add_event::<NewWorldEvent>()
+            .add_systems(
+                Update,
+                (
+                    tick_play_time,
+                    // Only once there is a world to save, and not while the saves may be browsed
+                    autosave_system.run_if(
+                        resource_exists::<Terrain>
+                            .and(in_state(BuildingReadinessState::Ready))
+                            .and(in_state(MainMenuActivityState::Inactive)),
+                    ),
+                    save_world_system,
+                    capture_thumbnail_system,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (new_world_system, load_world_system)
+                    .run_if(in_state(BuildingReadinessState::Ready)),
+            )
+            .add_systems(
+                Update,
+                save_notices_window.run_if(|notices: Res<SaveNotices>| !notices.is_empty()),
+            );
+    }
+}
+
+fn tick_play_time(mut play_time: ResMut<PlayTime>, time: Res<Time>) {
+    play_time.0 += time.delta();
+}
+
+/// Placed buildings with what is saved of them.
+type SavedBuildings<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static PlacedBuilding,
+        &'static Transform,
+        Option<&'static MaterialVariant>,
+        Option<&'static BuildingTier>,
+        Option<&'static Openable>,
+    ),
+>;
+
+/// Everything stored in a world save.
+#[derive(SystemParam)]
+struct WorldContents<'w, 's> {
+    placed_buildings: SavedBuildings<'w, 's>,
+    terrain: Option<Res<'w, Terrain>>,
+    terrain_settings: Res<'w, TerrainSettings>,
+    world_chunks: ResMut<'w, WorldChunks>,
+    world_id: Res<'w, WorldId>,
+    time_of_day: Res<'w, TimeOfDay>,
+    weather: Res<'w, Weather>,
+    game_mode: Res<'w, GameMode>,
+    inventory: Res<'w, Inventory>,
+    harvested_nodes: Res<'w, HarvestedNodes>,
+}
+
+impl WorldContents<'_, '_> {
+    fn world_save(&self) -> WorldSave {
+        WorldSave {
+            time_of_day: Some(self.time_of_day.clone()),
+            weather: Some(self.weather.clone()),
+            game_mode: *self.game_mode,
+            inventory: Some(self.inventory.clone()),
+            harvested_nodes: self.harvested_nodes.saves(),
+            ..default()
+        }
+    }
+
+    fn chunk_saves(&self) -> HashMap<IVec2, ChunkSave> {
+        let buildings =
+            self.placed_buildings
+                .iter()
+                .map(
+                    |(building, transform, variant, tier, openable)| PlacedBuildingSave {
+                        name: building.name.clone(),
+                        transform: *transform,
+                        material: variant.and_then(|variant| variant.0.clone()),
+                        tier: tier.copied().unwrap_or_default(),
+                        open: openable.is_some_and(|openable| openable.open),
+                    },
+                );
+        self.world_chunks.chunk_saves(
+            self.terrain.as_deref(),
+            buildings,
+            self.terrain_settings.chunk_size,
+        )
+    }
+
+    fn write(
+        &self,
+        dir: &std::path::Path,
+        play_time: &PlayTime,
+    ) -> Result<WorldSaveMeta, WorldSaveError> {
+        write_world(
+            dir,
+            &self.world_save(),
+            &self.chunk_saves(),
+            self.world_chunks.source(),
+            self.world_id.0,
+            play_time.0.as_secs_f64(),
+        )
+    }
+}
+
+fn save_world_system(
+    mut commands: Commands,
+    mut evr_save: EventReader<SaveWorldEvent>,
+    mut contents: WorldContents,
+    play_time: Res<PlayTime>,
+    camera: Single<&Transform, With<UniCamController>>,
+    mut images: ResMut<Assets<Image>>,
+    mut notices: ResMut<SaveNotices>,
+) {
+    for ev in evr_save.read() {
+        let dir = save_slots::slot_dir(&ev.0);
+        match contents.write(&dir, &play_time) {
+            Ok(meta) => {
+                info!("World saved to {}", dir.display());
+                spawn_thumbnail_camera(
+                    &mut commands,
+                    &mut images,
+                    **camera,
+                    dir.join(THUMBNAIL_FILE),
+                );
+                // The slot the world was loaded from may be deleted or overwritten from now on
+                contents.world_chunks.set_source(ChunkSource { dir, meta });
+            }
+            Err(err) => {
+                error!("Failed to save world \"{}\": {err}", ev.0);
+                notices.push(format!("Failed to save world \"{}\": {err}", ev.0));
+            }
+        }
+    }
+}
+
+/// Clock, weather, game mode and gathered resources of the world, restored from a save.
+#[derive(SystemParam)]
+struct SavedEnvironment<'w> {
+    time_of_day: ResMut<'w, TimeOfDay>,
+    weather: ResMut<'w, Weather>,
+    game_mode: ResMut<'w, GameMode>,
+    inventory: ResMut<'w, Inventory>,
+    harvested_nodes: ResMut<'w, HarvestedNodes>,
+}
+
+impl SavedEnvironment<'_> {
+    /// Saves from before the day/night cycle or the weather keep the current ones.
+    /// Saves from before survival mode start with a fresh inventory.
+    fn restore(&mut self, world: &WorldSave) {
+        if let Some(time_of_day) = &world.time_of_day {
+            *self.time_of_day = time_of_day.clone();
+        }
+        if let Some(weather) = &world.weather {
+            *self.weather = weather.clone();
+        }
+        *self.game_mode = world.game_mode;
+        *self.inventory = world.inventory.clone().unwrap_or_default();
+        // The nodes are scattered anew as the chunks of the world are streamed in
+        self.harvested_nodes.restore(&world.harvested_nodes);
+    }
+
+    /// A new world keeps the clock and the weather.
+    fn reset(&mut self, game_mode: GameMode) {
+        *self.game_mode = game_mode;
+        *self.inventory = Inventory::default();
+        self.harvested_nodes.restore(&[]);
+    }
+}
+
+fn new_world_system(
+    mut evr_new: EventReader<NewWorldEvent>,
+    mut play_time: ResMut<PlayTime>,
+    mut world_id: ResMut<WorldId>,
+    mut evw_stream_world: EventWriter<StreamWorldEvent>,
+    mut environment: SavedEnvironment,
+) {
+    for ev in evr_new.read() {
+        info!("New {:?} world", ev.0);
+        play_time.0 = Duration::ZERO;
+        *world_id = WorldId::random();
+        environment.reset(ev.0);
+        // The terrain is generated anew as the chunks are streamed in
+        evw_stream_world.send(StreamWorldEvent {
+            source: None,
+            legacy_buildings: Vec::new(),
+            legacy_terrain: Vec::new(),
+        });
+    }
+}
+
+fn load_world_system(
+    mut evr_load: EventReader<LoadWorldEvent>,
+    mut play_time: ResMut<PlayTime>,
+    mut world_id: ResMut<WorldId>,
+    mut evw_stream_world: EventWriter<StreamWorldEvent>,
+    mut environment: SavedEnvironment,
+    mut notices: ResMut<SaveNotices>,
+) {
+    for ev in evr_load.read() {
+        // A damaged save falls back to the autosaves of its world, the most recent first.
+        // Saves whose summary can't be read don't tell their world, they have no fallback.
+        let fallbacks = read_meta(&save_slots::slot_dir(&ev.0))
+            .ok()
+            .and_then(|meta| meta.world_id)
+            .map(world_autosaves)
+            .unwrap_or_default();
+        let loaded = std::iter::once(ev.0.clone())
+            .chain(fallbacks.into_iter().filter(|slot| *slot != ev.0))
+            .find_map(|slot| match read_world(&save_slots::slot_dir(&slot)) {
+                Ok((world, source)) => Some((slot, world, source)),
+                Err(err) => {
+                    error!("Failed to load world \"{slot}\": {err}");
+                    None
+                }
+            });
+        let Some((slot, world, source)) = loaded else {
+            error!("No usable save found to load instead of \"{}\"", ev.0);
+            notices.push(format!(
+                "Save \"{}\" is damaged and no autosave of its world could be loaded instead",
+                ev.0
+            ));
+            continue;
+        };
+        if slot != ev.0 {
+            warn!("Save \"{}\" is damaged, loaded \"{slot}\" instead", ev.0);
+            notices.push(format!(
+                "Save \"{}\" is damaged, its autosave \"{slot}\" was loaded instead",
+                ev.0
+            ));
+        }
+        info!("World loaded from {}", source.dir.display());
+        play_time.0 = Duration::from_secs_f64(source.meta.play_time_secs);
+        // Saves from before the world id become a world of their own
+        *world_id = source.meta.world_id.map_or_else(WorldId::random, WorldId);
+
+        environment.restore(&world);
+        // The buildings and terrain are streamed in from the chunks of the save
+        evw_stream_world.send(StreamWorldEvent {
+            source: Some(source),
+            legacy_buildings: world.buildings,
+            legacy_terrain: world.terrain,
+        });
+    }
+}
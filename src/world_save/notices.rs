@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+/// Problems with saves the player should know about, e.g. a damaged save replaced by an autosave.
+/// Shown until dismissed.
+#[derive(Resource, Default)]
+pub struct SaveNotices(Vec<String>);
+
+impl SaveNotices {
+    pub fn push(&mut self, notice: impl Into<String>) {
+        self.0.push(notice.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+pub fn save_notices_window(mut contexts: EguiContexts, mut notices: ResMut<SaveNotices>) {
+    egui::Window::new("Save problems")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for notice in &notices.0 {
+                ui.label(notice);
+            }
+            if ui.button("Dismiss").clicked() {
+                notices.0.clear();
+            }
+        });
+}
@@ -0,0 +1,108 @@
+use super::world_save::{read_meta, WorldSaveMeta, META_FILE, THUMBNAIL_FILE};
+use super::SAVES_DIR;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A save slot found in the saves directory. Each slot is a directory named after the save.
+pub struct SaveSlotInfo {
+    pub name: String,
+    pub modified: Option<SystemTime>,
+    /// `None` if the summary is missing or unreadable.
+    pub meta: Option<WorldSaveMeta>,
+    pub thumbnail: Option<PathBuf>,
+}
+
+pub fn slot_dir(name: &str) -> PathBuf {
+    PathBuf::from(SAVES_DIR).join(name)
+}
+
+pub fn slot_exists(name: &str) -> bool {
+    slot_dir(name).is_dir()
+}
+
+/// When the slot was last written. `None` if it doesn't exist.
+pub fn slot_modified(name: &str) -> Option<SystemTime> {
+    modified(&slot_dir(name))
+}
+
+/// The summary is rewritten by every save, unlike the directory itself
+/// whose time changes on rename on some platforms and not on others.
+fn modified(slot_dir: &Path) -> Option<SystemTime> {
+    fs::metadata(slot_dir.join(META_FILE))
+        .or_else(|_| fs::metadata(slot_dir))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Lists all save slots, the most recently modified first.
+pub fn list_save_slots() -> Vec<SaveSlotInfo> {
+    let Ok(entries) = fs::read_dir(SAVES_DIR) else {
+        return Vec::new();
+    };
+
+    let mut slots: Vec<SaveSlotInfo> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| read_slot_info(&entry.path()))
+        .collect();
+
+    slots.sort_by_key(|slot| std::cmp::Reverse(slot.modified));
+    slots
+}
+
+fn read_slot_info(path: &Path) -> SaveSlotInfo {
+    let thumbnail = path.join(THUMBNAIL_FILE);
+    SaveSlotInfo {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        modified: modified(path),
+        meta: read_meta(path).ok(),
+        thumbnail: thumbnail.is_file().then_some(thumbnail),
+    }
+}
+
+pub fn rename_slot(from: &str, to: &str) -> io::Result<()> {
+    if slot_exists(to) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Save \"{to}\" already exists"),
+        ));
+    }
+    fs::rename(slot_dir(from), slot_dir(to))
+}
+
+/// Copies the slot under a free name like "name (copy)" and returns that name.
+pub fn duplicate_slot(name: &str) -> io::Result<String> {
+    let mut copy_name = format!("{name} (copy)");
+    let mut index = 2;
+    while slot_exists(&copy_name) {
+        copy_name = format!("{name} (copy {index})");
+        index += 1;
+    }
+
+    copy_dir(&slot_dir(name), &slot_dir(&copy_name))?;
+    Ok(copy_name)
+}
+
+/// Copies a directory with its subdirectories, like the chunks of a save.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            copy_dir(&path, &to.join(entry.file_name()))?;
+        } else if path.is_file() {
+            fs::copy(&path, to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn delete_slot(name: &str) -> io::Result<()> {
+    fs::remove_dir_all(slot_dir(name))
+}
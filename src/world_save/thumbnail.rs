@@ -0,0 +1,86 @@
+use super::world_save::temp_path;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured};
+use std::path::PathBuf;
+
+const THUMBNAIL_WIDTH: u32 = 256;
+const THUMBNAIL_HEIGHT: u32 = 144;
+
+/// Frames to wait before the capture, so the offscreen camera has rendered at least once.
+const THUMBNAIL_WARMUP_FRAMES: u8 = 2;
+
+/// Offscreen camera that renders the world without UI into a small image for a save thumbnail.
+#[derive(Component)]
+pub struct ThumbnailCamera {
+    path: PathBuf,
+    frames_left: u8,
+}
+
+/// Spawns an offscreen camera at `transform` which captures a thumbnail into `path`.
+pub fn spawn_thumbnail_camera(
+    commands: &mut Commands,
+    images: &mut Assets<Image>,
+    transform: Transform,
+    path: PathBuf,
+) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: THUMBNAIL_WIDTH,
+            height: THUMBNAIL_HEIGHT,
+            ..default()
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::Image(images.add(image)),
+            order: -1,
+            ..default()
+        },
+        transform,
+        ThumbnailCamera {
+            path,
+            frames_left: THUMBNAIL_WARMUP_FRAMES,
+        },
+    ));
+}
+
+/// Takes the screenshot of the thumbnail camera once it is warmed up, then removes the camera.
+pub fn capture_thumbnail_system(
+    mut commands: Commands,
+    mut thumbnail_cameras: Query<(Entity, &mut ThumbnailCamera, &Camera)>,
+) {
+    for (entity, mut thumbnail_camera, camera) in thumbnail_cameras.iter_mut() {
+        if thumbnail_camera.frames_left > 0 {
+            thumbnail_camera.frames_left -= 1;
+            continue;
+        }
+
+        if let RenderTarget::Image(image) = camera.target.clone() {
+            let path = thumbnail_camera.path.clone();
+            let tmp = temp_path(&path);
+            let mut save_tmp = save_to_disk(tmp.clone());
+            commands.spawn(Screenshot::image(image)).observe(
+                move |trigger: Trigger<ScreenshotCaptured>| {
+                    save_tmp(trigger);
+                    if let Err(err) = std::fs::rename(&tmp, &path) {
+                        error!("Failed to save thumbnail {}: {err}", path.display());
+                    }
+                },
+            );
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
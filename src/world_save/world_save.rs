@@ -0,0 +1,376 @@
+use super::autosave::world_autosaves;
+use super::save_slots::slot_dir;
+use crate::building::BuildingTier;
+use crate::environment::{TimeOfDay, Weather};
+use crate::harvesting::HarvestedNodeSave;
+use crate::inventory::{GameMode, Inventory};
+use crate::terrain::TerrainChunkSave;
+use bevy::prelude::{IVec2, Transform};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const WORLD_FILE: &str = "world.ron";
+pub const META_FILE: &str = "meta.ron";
+pub const THUMBNAIL_FILE: &str = "thumbnail.png";
+/// Subdirectory of a slot with one file per chunk of the world.
+pub const CHUNKS_DIR: &str = "chunks";
+
+/// Summary of a save. Stored in a separate file so the save slot browser
+/// doesn't have to read the whole world to show it.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct WorldSaveMeta {
+    pub play_time_secs: f64,
+    pub piece_count: usize,
+    /// The world the save belongs to, shared by all its slots and autosaves.
+    /// Missing in saves from before it was recorded.
+    #[serde(default)]
+    pub world_id: Option<u64>,
+    /// Only in saves from before the manifest, checksum of the world file.
+    #[serde(default)]
+    pub world_checksum: Option<u64>,
+    /// Manifest of the save: checksum of the world file and of every chunk file, by path relative
+    /// to the slot directory. A mismatch means a file is damaged or belongs to another save.
+    /// Empty in saves from before the manifest.
+    #[serde(default)]
+    pub files: BTreeMap<String, u64>,
+}
+
+/// Everything needed to restore a world, besides its chunks.
+#[derive(Serialize, Deserialize, Default)]
+pub struct WorldSave {
+    /// Only in saves from before the world was split into chunks, now stored in `ChunkSave`s.
+    #[serde(default)]
+    pub buildings: Vec<PlacedBuildingSave>,
+    /// Only in saves from before the world was split into chunks, now stored in `ChunkSave`s.
+    #[serde(default)]
+    pub terrain: Vec<TerrainChunkSave>,
+    /// Missing in saves from before the day/night cycle.
+    #[serde(default)]
+    pub time_of_day: Option<TimeOfDay>,
+    /// Missing in saves from before the weather.
+    #[serde(default)]
+    pub weather: Option<Weather>,
+    /// Saves from before survival mode are creative.
+    #[serde(default)]
+    pub game_mode: GameMode,
+    /// Missing in saves from before survival mode.
+    #[serde(default)]
+    pub inventory: Option<Inventory>,
+    /// Gathered trees, rocks and deposits which haven't grown back yet.
+    #[serde(default)]
+    pub harvested_nodes: Vec<HarvestedNodeSave>,
+}
+
+/// One chunk of the world, stored in its own file so it is only read when the chunk is streamed in.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ChunkSave {
+    /// `None` if the player didn't change the terrain of the chunk, it is generated anew.
+    #[serde(default)]
+    pub terrain: Option<TerrainChunkSave>,
+    #[serde(default)]
+    pub buildings: Vec<PlacedBuildingSave>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlacedBuildingSave {
+    /// Name of the `BuildingAssetsPack` the building was spawned from.
+    pub name: String,
+    pub transform: Transform,
+    /// Material variant, `None` for the material of the glTF.
+    #[serde(default)]
+    pub material: Option<String>,
+    /// Saves from before the tiers were added only have twig buildings.
+    #[serde(default)]
+    pub tier: BuildingTier,
+    /// Whether the door, hatch or window is open.
+    #[serde(default)]
+    pub open: bool,
+}
+
+#[derive(Debug)]
+pub enum WorldSaveError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// The file doesn't match the checksum in the manifest.
+    ChecksumMismatch(String),
+    /// A chunk file of the save the world is streamed from is gone, it can't be carried over.
+    MissingFile(String),
+}
+
+impl Display for WorldSaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorldSaveError::Io(err) => write!(f, "IO error: {err}"),
+            WorldSaveError::Parse(err) => write!(f, "Parse error: {err}"),
+            WorldSaveError::Serialize(err) => write!(f, "Serialize error: {err}"),
+            WorldSaveError::ChecksumMismatch(file) => {
+                write!(f, "\"{file}\" doesn't match its checksum")
+            }
+            WorldSaveError::MissingFile(file) => {
+                write!(f, "\"{file}\" of the loaded save is missing")
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for WorldSaveError {
+    fn from(err: std::io::Error) -> Self {
+        WorldSaveError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for WorldSaveError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        WorldSaveError::Parse(err)
+    }
+}
+
+impl From<ron::Error> for WorldSaveError {
+    fn from(err: ron::Error) -> Self {
+        WorldSaveError::Serialize(err)
+    }
+}
+
+/// Writes the world, its chunks and its summary into the slot directory, creating it if needed,
+/// and returns the summary.
+/// The summary is written last with the checksum of every file: if the game crashes
+/// in between, the files don't match the old manifest and the save is detected as damaged.
+pub fn write_world(
+    slot_dir: &Path,
+    world: &WorldSave,
+    chunks: &HashMap<IVec2, ChunkSave>,
+    source: Option<&ChunkSource>,
+    world_id: u64,
+    play_time_secs: f64,
+) -> Result<WorldSaveMeta, WorldSaveError> {
+    fs::create_dir_all(slot_dir.join(CHUNKS_DIR))?;
+    let mut meta = WorldSaveMeta {
+        play_time_secs,
+        world_id: Some(world_id),
+        ..Default::default()
+    };
+    write_chunks(slot_dir, chunks, source, &mut meta)?;
+    let world_text = to_ron(world)?;
+    write_atomic(&slot_dir.join(WORLD_FILE), &world_text)?;
+    meta.files
+        .insert(WORLD_FILE.to_string(), checksum(world_text.as_bytes()));
+    write_atomic(&slot_dir.join(META_FILE), &to_ron(&meta)?)?;
+    remove_stale_chunks(slot_dir, &meta)?;
+    Ok(meta)
+}
+
+/// Reads the world once its file matches the manifest. The chunk files are checked
+/// as they are streamed in, so loading doesn't wait for all of them to be read.
+/// Saves from before the manifest only have the checksum of the world file.
+pub fn read_world(slot_dir: &Path) -> Result<(WorldSave, ChunkSource), WorldSaveError> {
+    let meta = read_meta(slot_dir)?;
+    let world_text = fs::read_to_string(slot_dir.join(WORLD_FILE))?;
+    let expected = meta.files.get(WORLD_FILE).copied().or(meta.world_checksum);
+    if expected.is_some_and(|expected| expected != checksum(world_text.as_bytes())) {
+        return Err(WorldSaveError::ChecksumMismatch(WORLD_FILE.to_string()));
+    }
+    let world = ron::from_str(&world_text)?;
+    let source = ChunkSource {
+        dir: slot_dir.to_path_buf(),
+        meta,
+    };
+    Ok((world, source))
+}
+
+/// Path of a chunk file relative to the slot directory, as it is listed in the manifest.
+fn chunk_file(coord: IVec2) -> String {
+    format!("{CHUNKS_DIR}/{}_{}.ron", coord.x, coord.y)
+}
+
+/// Coordinates of a chunk from the name of its file.
+fn chunk_of_file_name(name: &str) -> Option<IVec2> {
+    let (x, z) = name.strip_suffix(".ron")?.split_once('_')?;
+    Some(IVec2::new(x.parse().ok()?, z.parse().ok()?))
+}
+
+/// Coordinates of the chunk files in a slot directory.
+fn list_chunks(slot_dir: &Path) -> Vec<IVec2> {
+    let Ok(entries) = fs::read_dir(slot_dir.join(CHUNKS_DIR)) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| chunk_of_file_name(&entry.file_name().to_string_lossy()))
+        .collect()
+}
+
+/// A save slot the chunks of the world are streamed in from, with the manifest it was verified against.
+#[derive(Clone)]
+pub struct ChunkSource {
+    pub dir: PathBuf,
+    pub meta: WorldSaveMeta,
+}
+
+impl ChunkSource {
+    /// Coordinates of the chunks stored in the save.
+    fn chunks(&self) -> Vec<IVec2> {
+        if self.meta.files.is_empty() {
+            return list_chunks(&self.dir);
+        }
+        self.meta
+            .files
+            .keys()
+            .filter_map(|file| file.strip_prefix(CHUNKS_DIR)?.strip_prefix('/'))
+            .filter_map(chunk_of_file_name)
+            .collect()
+    }
+
+    /// Reads a chunk of the save, `None` if the save doesn't have it.
+    pub fn read_chunk(&self, coord: IVec2) -> Result<Option<ChunkSave>, WorldSaveError> {
+        match self.read_chunk_text(coord)? {
+            Some(text) => Ok(Some(ron::from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The file of a chunk, checked against the manifest.
+    /// Chunk files missing in the manifest are left over from an interrupted save and ignored.
+    fn read_chunk_text(&self, coord: IVec2) -> Result<Option<String>, WorldSaveError> {
+        let file = chunk_file(coord);
+        let expected = self.meta.files.get(&file);
+        if expected.is_none() && !self.meta.files.is_empty() {
+            return Ok(None);
+        }
+        let text = match fs::read_to_string(self.dir.join(&file)) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && expected.is_none() => {
+                return Ok(None)
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if expected.is_some_and(|expected| *expected != checksum(text.as_bytes())) {
+            return Err(WorldSaveError::ChecksumMismatch(file));
+        }
+        Ok(Some(text))
+    }
+
+    /// The chunk from the most recent autosave of the same world which has it undamaged,
+    /// with the directory of that autosave. For when the chunk's own file can't be read.
+    /// Saves from before the world id have no backups, any autosave may be of another world.
+    pub fn read_backup_chunk(&self, coord: IVec2) -> Option<(PathBuf, ChunkSave)> {
+        let own_dir = fs::canonicalize(&self.dir).ok();
+        world_autosaves(self.meta.world_id?)
+            .into_iter()
+            .map(|slot| slot_dir(&slot))
+            .filter(|dir| fs::canonicalize(dir).ok() != own_dir)
+            .find_map(|dir| {
+                let meta = read_meta(&dir).ok()?;
+                let backup = ChunkSource { dir, meta };
+                let chunk = backup.read_chunk(coord).ok()??;
+                Some((backup.dir, chunk))
+            })
+    }
+}
+
+/// Writes the chunks of the world and adds them to the manifest and the building count of `meta`.
+/// Chunks missing in `chunks` weren't loaded since the world was read from or saved to `source`,
+/// they are copied from there. Chunks which failed to load are among them: their files are
+/// carried over byte for byte, never replaced by an empty chunk. A chunk file which is gone
+/// fails the save rather than silently dropping the chunk.
+fn write_chunks(
+    slot_dir: &Path,
+    chunks: &HashMap<IVec2, ChunkSave>,
+    source: Option<&ChunkSource>,
+    meta: &mut WorldSaveMeta,
+) -> Result<(), WorldSaveError> {
+    for (coord, chunk) in chunks {
+        let text = to_ron(chunk)?;
+        write_atomic(&slot_dir.join(chunk_file(*coord)), &text)?;
+        meta.files
+            .insert(chunk_file(*coord), checksum(text.as_bytes()));
+        meta.piece_count += chunk.buildings.len();
+    }
+
+    // Chunks untouched since the world was loaded
+    let Some(source) = source else {
+        return Ok(());
+    };
+    let same_slot = fs::canonicalize(&source.dir).ok() == fs::canonicalize(slot_dir).ok();
+    for coord in source
+        .chunks()
+        .into_iter()
+        .filter(|coord| !chunks.contains_key(coord))
+    {
+        let file = chunk_file(coord);
+        let bytes = match fs::read(source.dir.join(&file)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(WorldSaveError::MissingFile(file))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if !same_slot {
+            write_atomic(&slot_dir.join(&file), &bytes)?;
+        }
+        meta.files.insert(file, checksum(&bytes));
+        // Damaged chunks don't count, their buildings are unknown
+        if let Some(chunk) = std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|text| ron::from_str::<ChunkSave>(text).ok())
+        {
+            meta.piece_count += chunk.buildings.len();
+        }
+    }
+    Ok(())
+}
+
+/// Removes the chunk files of an older save in the slot which aren't in the manifest anymore.
+/// Done once the summary is written, until then the old manifest still refers to them.
+fn remove_stale_chunks(slot_dir: &Path, meta: &WorldSaveMeta) -> std::io::Result<()> {
+    for coord in list_chunks(slot_dir) {
+        if !meta.files.contains_key(&chunk_file(coord)) {
+            fs::remove_file(slot_dir.join(chunk_file(coord)))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn read_meta(slot_dir: &Path) -> Result<WorldSaveMeta, WorldSaveError> {
+    let text = fs::read_to_string(slot_dir.join(META_FILE))?;
+    Ok(ron::from_str(&text)?)
+}
+
+/// Path of the temporary file a file is written to before being renamed over the original.
+/// The extension is kept last so writers that pick the format by extension still work.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let extension = path
+        .extension()
+        .map(|ext| format!("tmp.{}", ext.to_string_lossy()))
+        .unwrap_or_else(|| "tmp".to_string());
+    path.with_extension(extension)
+}
+
+/// Writes into a temporary file and renames it over `path`,
+/// so `path` always holds either the old or the new content.
+fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let tmp = temp_path(path);
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(content.as_ref())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+fn to_ron<T: Serialize>(value: &T) -> Result<String, WorldSaveError> {
+    Ok(ron::ser::to_string_pretty(
+        value,
+        ron::ser::PrettyConfig::default(),
+    )?)
+}
+
+/// FNV-1a. Unlike `DefaultHasher` it is stable between Rust versions, so it can be stored.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
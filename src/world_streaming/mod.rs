@@ -0,0 +1,429 @@
+use crate::building::{
+    spawn_placed_building, BuildingAssets, BuildingReadinessState, BuildingTier, MaterialVariant,
+    Openable, PlacedBuilding,
+};
+use crate::settings::GameSettings;
+use crate::terrain::{Terrain, TerrainChunkSave, TerrainSettings};
+use crate::universal_camera_controller::UniCamController;
+use crate::world_save::{ChunkSave, ChunkSource, PlacedBuildingSave, SaveNotices, WorldSaveError};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy::utils::{HashMap, HashSet};
+use std::sync::Arc;
+
+pub const CHUNKS_LOADED: DiagnosticPath = DiagnosticPath::const_new("world/chunks_loaded");
+pub const CHUNKS_LOADING: DiagnosticPath = DiagnosticPath::const_new("world/chunks_loading");
+
+/// Chunks which start loading per frame at most, the nearest first.
+const MAX_LOAD_REQUESTS_PER_FRAME: usize = 8;
+
+/// How far a chunk is in loading. Chunks which aren't loaded have no state.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChunkLoadState {
+    /// Read from the save or generated on another thread.
+    Loading,
+    /// Its terrain and buildings are in the world.
+    Loaded,
+    /// Its file is damaged and no autosave of the world has it either. It stays empty and isn't saved,
+    /// so the damaged file is kept rather than overwritten with nothing.
+    Failed,
+}
+
+/// A chunk read or generated by a loading task.
+struct LoadedChunk {
+    terrain: TerrainChunkSave,
+    /// Whether the terrain comes from the save rather than the height source.
+    edited: bool,
+    buildings: Vec<PlacedBuildingSave>,
+}
+
+/// The chunks of the world, which are loaded around the camera and unloaded far from it.
+/// Chunks share their size and coordinates with the terrain chunks.
+#[derive(Resource, Default)]
+pub struct WorldChunks {
+    states: HashMap<IVec2, ChunkLoadState>,
+    /// `None` once done for a chunk which failed to load.
+    tasks: HashMap<IVec2, Task<Option<LoadedChunk>>>,
+    /// Save slot the world was loaded from or last saved to, chunks not loaded since are read
+    /// from there. `None` for a new world which wasn't saved yet.
+    source: Option<Arc<ChunkSource>>,
+    /// Chunks unloaded since the world was loaded or saved. They are newer than the ones in `source`.
+    unloaded: HashMap<IVec2, ChunkSave>,
+}
+
+impl WorldChunks {
+    pub fn state(&self, coord: IVec2) -> Option<ChunkLoadState> {
+        self.states.get(&coord).copied()
+    }
+
+    pub fn is_loaded(&self, coord: IVec2) -> bool {
+        self.state(coord) == Some(ChunkLoadState::Loaded)
+    }
+
+    /// Whether the chunks of all `positions` are loaded.
+    pub fn all_loaded(&self, mut positions: impl Iterator<Item = Vec3>, chunk_size: f32) -> bool {
+        positions.all(|position| self.is_loaded(chunk_coord(position, chunk_size)))
+    }
+
+    pub fn loaded(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.states
+            .iter()
+            .filter(|(_, state)| **state == ChunkLoadState::Loaded)
+            .map(|(coord, _)| *coord)
+    }
+
+    pub fn source(&self) -> Option<&ChunkSource> {
+        self.source.as_deref()
+    }
+
+    /// Streams the chunks from the slot the world was just saved to, which holds the unloaded chunks too.
+    pub fn set_source(&mut self, source: ChunkSource) {
+        self.source = Some(Arc::new(source));
+        self.unloaded.clear();
+    }
+
+    fn count(&self, state: ChunkLoadState) -> usize {
+        self.states.values().filter(|s| **s == state).count()
+    }
+
+    /// The latest saved state of a chunk which isn't loaded, `None` if it can't be read.
+    fn stored_chunk(&self, coord: IVec2) -> Option<ChunkSave> {
+        if let Some(chunk) = self.unloaded.get(&coord) {
+            return Some(chunk.clone());
+        }
+        match self.source.as_deref() {
+            Some(source) => read_source_chunk(source, coord)
+                .ok()
+                .map(Option::unwrap_or_default),
+            None => Some(ChunkSave::default()),
+        }
+    }
+
+    /// The chunks as they are saved: the loaded ones and the ones unloaded since the world was loaded.
+    /// The other chunks are still the same as in `source`.
+    pub fn chunk_saves(
+        &self,
+        terrain: Option<&Terrain>,
+        buildings: impl Iterator<Item = PlacedBuildingSave>,
+        chunk_size: f32,
+    ) -> HashMap<IVec2, ChunkSave> {
+        let mut chunks = self.unloaded.clone();
+        for coord in self
+            .states
+            .keys()
+            .filter(|coord| self.state(**coord) == Some(ChunkLoadState::Loaded))
+        {
+            chunks.insert(
+                *coord,
+                ChunkSave {
+                    terrain: terrain.and_then(|terrain| terrain.edited_chunk(*coord)),
+                    buildings: Vec::new(),
+                },
+            );
+        }
+        for building in buildings {
+            let coord = chunk_coord(building.transform.translation, chunk_size);
+            if !chunks.contains_key(&coord) {
+                let Some(stored) = self.stored_chunk(coord) else {
+                    error!(
+                        "\"{}\" is in the damaged chunk {coord}, not saved",
+                        building.name
+                    );
+                    continue;
+                };
+                chunks.insert(coord, stored);
+            }
+            chunks
+                .get_mut(&coord)
+                .expect("inserted above")
+                .buildings
+                .push(building);
+        }
+        chunks
+    }
+}
+
+/// Replaces the world with the one of a save slot. Its chunks are streamed in around the camera.
+#[derive(Event)]
+pub struct StreamWorldEvent {
+    /// `None` for a new world.
+    pub source: Option<ChunkSource>,
+    /// Buildings of saves from before the world was split into chunks.
+    pub legacy_buildings: Vec<PlacedBuildingSave>,
+    /// Terrain of saves from before the world was split into chunks.
+    pub legacy_terrain: Vec<TerrainChunkSave>,
+}
+
+pub struct WorldStreamingPlugin;
+
+impl Plugin for WorldStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldChunks>()
+            .add_event::<StreamWorldEvent>()
+            .register_diagnostic(Diagnostic::new(CHUNKS_LOADED))
+            .register_diagnostic(Diagnostic::new(CHUNKS_LOADING))
+            .add_systems(
+                Update,
+                (
+                    stream_world,
+                    unload_far_chunks,
+                    request_near_chunks,
+                    finish_chunk_loads,
+                )
+                    .chain()
+                    .run_if(
+                        resource_exists::<Terrain>.and(in_state(BuildingReadinessState::Ready)),
+                    ),
+            );
+    }
+}
+
+/// Chunk containing a world position.
+pub fn chunk_coord(position: Vec3, chunk_size: f32) -> IVec2 {
+    (position.xz() / chunk_size).floor().as_ivec2()
+}
+
+/// Reads a chunk of the save the world was loaded from, `None` if the save doesn't have it.
+/// A chunk whose file can't be read is taken from the most recent autosave of the world which has it.
+fn read_source_chunk(
+    source: &ChunkSource,
+    coord: IVec2,
+) -> Result<Option<ChunkSave>, WorldSaveError> {
+    source.read_chunk(coord).or_else(|err| {
+        error!(
+            "Failed to read chunk {coord} of {}: {err}",
+            source.dir.display()
+        );
+        let (backup, chunk) = source.read_backup_chunk(coord).ok_or(err)?;
+        warn!("Chunk {coord} restored from {}", backup.display());
+        Ok(Some(chunk))
+    })
+}
+
+fn stream_world(
+    mut commands: Commands,
+    mut evr_stream: EventReader<StreamWorldEvent>,
+    mut world_chunks: ResMut<WorldChunks>,
+    mut terrain: ResMut<Terrain>,
+    placed_buildings: Query<Entity, With<PlacedBuilding>>,
+    terrain_settings: Res<TerrainSettings>,
+) {
+    for ev in evr_stream.read() {
+        placed_buildings.iter().for_each(|entity| {
+            commands.entity(entity).despawn_recursive();
+        });
+        terrain.clear();
+
+        let mut unloaded: HashMap<IVec2, ChunkSave> = HashMap::default();
+        for chunk in &ev.legacy_terrain {
+            unloaded.entry(chunk.coord).or_default().terrain = Some(chunk.clone());
+        }
+        for building in &ev.legacy_buildings {
+            let coord = chunk_coord(building.transform.translation, terrain_settings.chunk_size);
+            unloaded
+                .entry(coord)
+                .or_default()
+                .buildings
+                .push(building.clone());
+        }
+
+        // Dropping the tasks cancels them
+        *world_chunks = WorldChunks {
+            source: ev.source.clone().map(Arc::new),
+            unloaded,
+            ..default()
+        };
+    }
+}
+
+/// Chunks whose center is within `distance` of `center`, inside of the world, the nearest first.
+fn chunks_around(center: Vec3, distance: f32, settings: &TerrainSettings) -> Vec<IVec2> {
+    let chunk_size = settings.chunk_size;
+    let half = settings.world_size / 2.0;
+    let world_min = (-half / chunk_size).floor() as i32;
+    let world_max = (half / chunk_size).ceil() as i32 - 1;
+
+    let min = chunk_coord(center - distance, chunk_size).max(IVec2::splat(world_min));
+    let max = chunk_coord(center + distance, chunk_size).min(IVec2::splat(world_max));
+    let chunk_distance =
+        |coord: IVec2| ((coord.as_vec2() + 0.5) * chunk_size).distance(center.xz());
+
+    let mut coords: Vec<IVec2> = (min.x..=max.x)
+        .flat_map(|x| (min.y..=max.y).map(move |z| IVec2::new(x, z)))
+        .filter(|coord| chunk_distance(*coord) <= distance)
+        .collect();
+    coords.sort_by(|a, b| chunk_distance(*a).total_cmp(&chunk_distance(*b)));
+    coords
+}
+
+type PlacedBuildings<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static PlacedBuilding,
+        &'static Transform,
+        Option<&'static MaterialVariant>,
+        Option<&'static BuildingTier>,
+        Option<&'static Openable>,
+    ),
+>;
+
+/// Stores the chunks which got too far from the camera and removes their terrain and buildings.
+/// They are unloaded a chunk further than they are loaded, so chunks on the border don't flicker.
+fn unload_far_chunks(
+    mut commands: Commands,
+    mut world_chunks: ResMut<WorldChunks>,
+    mut terrain: ResMut<Terrain>,
+    placed_buildings: PlacedBuildings,
+    camera: Single<&Transform, With<UniCamController>>,
+    terrain_settings: Res<TerrainSettings>,
+    game_settings: Res<GameSettings>,
+) {
+    let chunk_size = terrain_settings.chunk_size;
+    let keep: HashSet<IVec2> = chunks_around(
+        camera.translation,
+        game_settings.video.view_distance + chunk_size,
+        &terrain_settings,
+    )
+    .into_iter()
+    .collect();
+    let far: Vec<IVec2> = world_chunks
+        .states
+        .keys()
+        .filter(|coord| !keep.contains(*coord))
+        .copied()
+        .collect();
+    if far.is_empty() {
+        return;
+    }
+
+    let mut unloaded: HashMap<IVec2, ChunkSave> = HashMap::default();
+    for coord in &far {
+        world_chunks.tasks.remove(coord);
+        if world_chunks.states.remove(coord) == Some(ChunkLoadState::Loaded) {
+            unloaded.insert(
+                *coord,
+                ChunkSave {
+                    terrain: terrain.remove_chunk(*coord),
+                    buildings: Vec::new(),
+                },
+            );
+        }
+    }
+    for (entity, building, transform, variant, tier, openable) in placed_buildings.iter() {
+        if let Some(chunk) = unloaded.get_mut(&chunk_coord(transform.translation, chunk_size)) {
+            chunk.buildings.push(PlacedBuildingSave {
+                name: building.name.clone(),
+                transform: *transform,
+                material: variant.and_then(|variant| variant.0.clone()),
+                tier: tier.copied().unwrap_or_default(),
+                open: openable.is_some_and(|openable| openable.open),
+            });
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    world_chunks.unloaded.extend(unloaded);
+}
+
+/// Starts loading the chunks near the camera on the async compute pool.
+fn request_near_chunks(
+    mut world_chunks: ResMut<WorldChunks>,
+    terrain: Res<Terrain>,
+    camera: Single<&Transform, With<UniCamController>>,
+    terrain_settings: Res<TerrainSettings>,
+    game_settings: Res<GameSettings>,
+) {
+    let missing: Vec<IVec2> = chunks_around(
+        camera.translation,
+        game_settings.video.view_distance,
+        &terrain_settings,
+    )
+    .into_iter()
+    .filter(|coord| !world_chunks.states.contains_key(coord))
+    .take(MAX_LOAD_REQUESTS_PER_FRAME)
+    .collect();
+
+    let pool = AsyncComputeTaskPool::get();
+    for coord in missing {
+        let generator = terrain.chunk_generator();
+        let stored = world_chunks.unloaded.get(&coord).cloned();
+        let source = world_chunks.source.clone();
+        let task = pool.spawn(async move {
+            let stored = match (stored, source) {
+                (Some(stored), _) => stored,
+                (None, Some(source)) => read_source_chunk(&source, coord).ok()?.unwrap_or_default(),
+                (None, None) => ChunkSave::default(),
+            };
+            let (terrain, edited) = match stored.terrain {
+                Some(terrain) => (terrain, true),
+                None => (generator.generate(coord), false),
+            };
+            Some(LoadedChunk {
+                terrain,
+                edited,
+                buildings: stored.buildings,
+            })
+        });
+        world_chunks.tasks.insert(coord, task);
+        world_chunks.states.insert(coord, ChunkLoadState::Loading);
+    }
+}
+
+/// Adds the terrain and buildings of the chunks whose loading finished.
+fn finish_chunk_loads(
+    mut commands: Commands,
+    mut world_chunks: ResMut<WorldChunks>,
+    mut terrain: ResMut<Terrain>,
+    building_assets: Res<BuildingAssets>,
+    mut diagnostics: Diagnostics,
+    mut notices: ResMut<SaveNotices>,
+) {
+    let mut finished = Vec::new();
+    for (coord, task) in world_chunks.tasks.iter_mut() {
+        if let Some(chunk) = block_on(future::poll_once(task)) {
+            finished.push((*coord, chunk));
+        }
+    }
+
+    for (coord, chunk) in finished {
+        world_chunks.tasks.remove(&coord);
+        let Some(chunk) = chunk else {
+            error!("Chunk {coord} is damaged and has no backup, it is left empty");
+            notices.push(format!(
+                "Chunk {coord} is damaged and no autosave of this world has it, it is left empty"
+            ));
+            world_chunks.states.insert(coord, ChunkLoadState::Failed);
+            continue;
+        };
+        // Loaded chunks are saved from the world itself again
+        world_chunks.unloaded.remove(&coord);
+        world_chunks.states.insert(coord, ChunkLoadState::Loaded);
+
+        terrain.insert_chunk(chunk.terrain, chunk.edited);
+        for building in chunk.buildings {
+            match building_assets.get(&building.name) {
+                Some(pack) => {
+                    spawn_placed_building(
+                        &mut commands,
+                        &pack.name,
+                        pack.scene.clone(),
+                        building.transform,
+                        building.material,
+                        building.tier,
+                        building.open,
+                    );
+                }
+                None => warn!("Unknown building \"{}\" in save, skipped", building.name),
+            }
+        }
+    }
+
+    diagnostics.add_measurement(&CHUNKS_LOADED, || {
+        world_chunks.count(ChunkLoadState::Loaded) as f64
+    });
+    diagnostics.add_measurement(&CHUNKS_LOADING, || {
+        world_chunks.count(ChunkLoadState::Loading) as f64
+    });
+}